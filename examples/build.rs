@@ -4,7 +4,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
     println!("outdir is : {:?}", out_dir.to_str());
     let binding = out_dir.clone();
-    let data = binding.to_str().clone().unwrap_or("empty").as_bytes();
+    let data = binding.to_str().unwrap_or("empty").as_bytes();
     fs::write("/tmp/foo", data).expect("Should be able to write to `/foo/tmp`");
 
     tonic_prost_build::configure()
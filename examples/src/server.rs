@@ -63,10 +63,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .build_v1()
         .unwrap();
     let greeter = TheGreeter::default();
+    let (health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<GreeterServer<TheGreeter>>()
+        .await;
 
     println!("GreeterServer listening on {addr}");
 
+    // http/1.1 and the grpc-web layer let the server be called with --protocol grpc-web too
     Server::builder()
+        .accept_http1(true)
+        .layer(tonic_web::GrpcWebLayer::new())
         .layer(
             TraceLayer::new_for_grpc()
                 .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
@@ -74,6 +81,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .on_response(DefaultOnResponse::new().level(Level::INFO)),
         )
         .add_service(GreeterServer::new(greeter))
+        .add_service(health_service)
         .add_service(service)
         .add_service(service_alpha)
         .serve(addr)
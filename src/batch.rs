@@ -0,0 +1,247 @@
+//! Calls read from NDJSON: one [`BatchRequest`] per line, one [`BatchResult`] written per request.
+//!
+//! ```text
+//! {"service":"helloworld.Greeter","method":"SayHello","body":{"name":"ann"},"metadata":{"x-team":"a"}}
+//! ```
+//!
+//! With a concurrency above 1, the results are written as the calls end, their `line` tells which request they answer.
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::call_options::CallOptions;
+use crate::client::{Client, GrpcClientError};
+use crate::encoding::{MessageSyntax, parse_message};
+use crate::output::OutputOptions;
+
+/// One call of a batch.
+///
+/// ```
+/// use grpc_client::batch::BatchRequest;
+/// let request = BatchRequest::parse(r#"{"service":"helloworld.Greeter","method":"SayHello"}"#).unwrap();
+/// assert_eq!(request.method, "SayHello");
+/// assert!(request.metadata.is_empty());
+/// assert!(BatchRequest::parse(r#"{"service":"helloworld.Greeter"}"#).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BatchRequest {
+    pub service: String,
+    pub method: String,
+    /// request message in the protobuf json mapping, an empty message when missing
+    #[serde(default)]
+    pub body: Option<serde_json::Value>,
+    /// metadata sent with this call, along the one given to every call
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl BatchRequest {
+    pub fn parse(line: &str) -> Result<Self, GrpcClientError> {
+        serde_json::from_str(line)
+            .map_err(|e| GrpcClientError::InvalidInput(format!("invalid batch request: {e}")))
+    }
+}
+
+/// Outcome of one call of a batch, written as one json line.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchResult {
+    /// line of the request in the input, starting from 1
+    pub line: usize,
+    /// absent when the line could not be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// `Ok`, or the name given by [`GrpcClientError::code_name`] to the error
+    pub status: String,
+    /// messages received, only for a successful call
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub responses: Option<Vec<serde_json::Value>>,
+    /// the error as [`GrpcClientError::to_json`] describes it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+    pub elapsed_ms: f64,
+}
+
+impl BatchResult {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchConfig {
+    /// number of calls in flight at the same time, 1 sends them in order
+    pub concurrency: usize,
+    /// do not start new calls once one failed, the calls already sent still get their result
+    pub stop_on_error: bool,
+    /// how the responses are turned into json
+    pub output: OutputOptions,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            concurrency: 1,
+            stop_on_error: false,
+            output: OutputOptions::default(),
+        }
+    }
+}
+
+impl BatchConfig {
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+    pub fn stop_on_error(mut self, yes: bool) -> Self {
+        self.stop_on_error = yes;
+        self
+    }
+    pub fn output(mut self, output: OutputOptions) -> Self {
+        self.output = output;
+        self
+    }
+}
+
+/// Counts of a batch, with the error of the first call that failed.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    /// requests left out because of [`BatchConfig::stop_on_error`]
+    pub skipped: usize,
+    pub first_error: Option<GrpcClientError>,
+}
+
+/// Send the request of every non empty line of `input`, `write` is given each result as soon as it is known.
+///
+/// A line that is not a valid request fails like a call would, the `options` apply to every call.
+/// The descriptors are fetched before the first call, failing to get them fails the whole batch.
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// # use grpc_client::{CallOptions, Client};
+/// use grpc_client::batch::{BatchConfig, run};
+/// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+/// let input = r#"{"service":"helloworld.Greeter","method":"SayHello","body":{"name":"ann"}}"#;
+/// let summary = run(&mut client, input, &CallOptions::default(), &BatchConfig::default(), |result| {
+///     println!("{}", serde_json::to_string(result).unwrap());
+/// })
+/// .await
+/// .unwrap();
+/// assert_eq!(summary.failed, 0);
+/// # })
+/// ```
+pub async fn run(
+    client: &mut Client,
+    input: &str,
+    options: &CallOptions,
+    config: &BatchConfig,
+    mut write: impl FnMut(&BatchResult),
+) -> Result<BatchSummary, GrpcClientError> {
+    // fetched once here, instead of once per call
+    client.descriptor_pool().await?;
+    let lines: Vec<(usize, &str)> = input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let mut summary = BatchSummary::default();
+    let mut calls = JoinSet::new();
+    let mut pending = lines.into_iter();
+    loop {
+        let stopped = config.stop_on_error && summary.failed > 0;
+        if !stopped
+            && calls.len() < config.concurrency.max(1)
+            && let Some((line, text)) = pending.next()
+        {
+            let mut client = client.clone();
+            let (text, options, output) =
+                (text.to_string(), options.clone(), config.output.clone());
+            calls.spawn(async move { call(&mut client, line, &text, options, &output).await });
+            continue;
+        }
+        let Some(ended) = calls.join_next().await else {
+            break;
+        };
+        let (result, error) = ended.expect("batch call panicked");
+        write(&result);
+        match error {
+            None => summary.succeeded += 1,
+            Some(error) => {
+                summary.failed += 1;
+                summary.first_error.get_or_insert(error);
+            }
+        }
+    }
+    summary.skipped = pending.count();
+    Ok(summary)
+}
+
+async fn call(
+    client: &mut Client,
+    line: usize,
+    text: &str,
+    options: CallOptions,
+    output: &OutputOptions,
+) -> (BatchResult, Option<GrpcClientError>) {
+    let started = Instant::now();
+    let (request, outcome) = match BatchRequest::parse(text) {
+        Ok(request) => {
+            let outcome = send(client, &request, options, output).await;
+            (Some(request), outcome)
+        }
+        Err(e) => (None, Err(e)),
+    };
+    let mut result = BatchResult {
+        line,
+        service: request.as_ref().map(|r| r.service.clone()),
+        method: request.map(|r| r.method),
+        status: format!("{:?}", tonic::Code::Ok),
+        responses: None,
+        error: None,
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+    };
+    match outcome {
+        Ok(responses) => {
+            result.responses = Some(responses);
+            (result, None)
+        }
+        Err(error) => {
+            result.status = error.code_name();
+            result.error = Some(error.to_json());
+            (result, Some(error))
+        }
+    }
+}
+
+/// send one request, its responses are returned as json
+async fn send(
+    client: &mut Client,
+    request: &BatchRequest,
+    mut options: CallOptions,
+    output: &OutputOptions,
+) -> Result<Vec<serde_json::Value>, GrpcClientError> {
+    for (key, value) in &request.metadata {
+        options = options.header(key, value)?;
+    }
+    let method = client
+        .find_method(&request.service, &request.method)
+        .await?;
+    let body = request
+        .body
+        .clone()
+        .unwrap_or_else(|| serde_json::json!({}));
+    let message = parse_message(method.input(), &body.to_string(), MessageSyntax::Json)?;
+    let result = client.request_message(&method, message, &options).await?;
+    result
+        .messages
+        .iter()
+        .map(|message| output.to_json_value(message))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| GrpcClientError::BadMessageType(format!("response not printable: {e}")))
+}
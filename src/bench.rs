@@ -0,0 +1,379 @@
+//! Load testing: the same call sent many times, concurrently, then summed up in a [`BenchReport`].
+//!
+//! Every worker sends its calls one after the other, so `concurrency` is the number of calls in flight.
+//! Workers share the given clients round robin, a client being one channel (one http/2 connection).
+//! With a target rate, the call number `i` is not started before `i / rate` seconds.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::call_options::CallOptions;
+use crate::client::{Client, GrpcClientError};
+use crate::exit_code;
+
+/// number of calls sent when neither a number of calls nor a duration is given
+pub const DEFAULT_REQUESTS: u64 = 200;
+/// number of bars of the latency histogram
+const HISTOGRAM_BUCKETS: usize = 10;
+/// width of the longest bar of the histogram, in characters
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// How many calls to send and how fast.
+///
+/// ```
+/// use std::time::Duration;
+/// use grpc_client::bench::BenchConfig;
+/// let config = BenchConfig::default().duration(Duration::from_secs(10)).concurrency(50);
+/// let config = config.rate(1000.0).unwrap();
+/// // a duration alone does not limit the number of calls
+/// assert_eq!(config.max_requests(), None);
+/// assert_eq!(BenchConfig::default().max_requests(), Some(200));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchConfig {
+    /// total number of calls
+    pub requests: Option<u64>,
+    /// stop starting new calls after this time
+    pub duration: Option<Duration>,
+    /// number of calls in flight at the same time
+    pub concurrency: usize,
+    /// number of calls started per second, as many as possible when none
+    pub rate: Option<f64>,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            requests: None,
+            duration: None,
+            concurrency: 1,
+            rate: None,
+        }
+    }
+}
+
+impl BenchConfig {
+    pub fn requests(mut self, requests: u64) -> Self {
+        self.requests = Some(requests);
+        self
+    }
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+    /// fails unless `rate` is a finite number above 0
+    pub fn rate(mut self, rate: f64) -> Result<Self, GrpcClientError> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(GrpcClientError::InvalidInput(format!(
+                "the rate must be a positive number of calls per second, got {rate}"
+            )));
+        }
+        self.rate = Some(rate);
+        Ok(self)
+    }
+    /// number of calls after which the bench stops, [`DEFAULT_REQUESTS`] when nothing bounds it
+    pub fn max_requests(&self) -> Option<u64> {
+        match (self.requests, self.duration) {
+            (None, None) => Some(DEFAULT_REQUESTS),
+            (requests, _) => requests,
+        }
+    }
+}
+
+/// Outcome of one call of the bench.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub latency: Duration,
+    /// `Ok`, or the name given by [`GrpcClientError::code_name`] to the error
+    pub status: String,
+}
+
+/// Send the call described by `service`, `method` and `arguments` as configured, and sum up the results.
+///
+/// Calls failing with a status or a timeout are counted, an error of usage (unknown method or field...)
+/// stops the bench and is returned.
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// # use grpc_client::{CallOptions, Client};
+/// use grpc_client::bench::{BenchConfig, run};
+/// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+/// // fetch the descriptors once, the workers share them
+/// client.descriptor_pool().await.unwrap();
+/// let config = BenchConfig::default().requests(1000).concurrency(10);
+/// let report = run(vec![client], "helloworld.Greeter", "SayHello", vec![], &CallOptions::default(), &config)
+///     .await
+///     .unwrap();
+/// println!("{report}");
+/// # })
+/// ```
+pub async fn run(
+    clients: Vec<Client>,
+    service: &str,
+    method: &str,
+    arguments: Vec<(String, String)>,
+    options: &CallOptions,
+    config: &BenchConfig,
+) -> Result<BenchReport, GrpcClientError> {
+    if clients.is_empty() {
+        return Err(GrpcClientError::InvalidInput(
+            "the bench needs at least one client".to_string(),
+        ));
+    }
+    let max_requests = config.max_requests();
+    let started = Instant::now();
+    let deadline = config.duration.map(|duration| started + duration);
+    let next = Arc::new(AtomicU64::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut workers = JoinSet::new();
+    for worker in 0..config.concurrency.max(1) {
+        let mut client = clients[worker % clients.len()].clone();
+        let (service, method) = (service.to_string(), method.to_string());
+        let (arguments, options) = (arguments.clone(), options.clone());
+        let (next, stop, rate) = (next.clone(), stop.clone(), config.rate);
+        workers.spawn(async move {
+            let mut samples = Vec::new();
+            while !stop.load(Ordering::Relaxed) {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if max_requests.is_some_and(|max| index >= max) {
+                    break;
+                }
+                if let Some(rate) = rate {
+                    let at = started + Duration::from_secs_f64(index as f64 / rate);
+                    tokio::time::sleep_until(at).await;
+                }
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    break;
+                }
+                let call_started = Instant::now();
+                let result = client
+                    .request_with_options(&service, &method, arguments.clone(), &options)
+                    .await;
+                let latency = call_started.elapsed();
+                let status = match result {
+                    Ok(result) => format!("{:?}", result.code),
+                    Err(e) if e.exit_code() == exit_code::USAGE => {
+                        stop.store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                    Err(e) => e.code_name(),
+                };
+                samples.push(Sample { latency, status });
+            }
+            Ok(samples)
+        });
+    }
+    let mut samples = Vec::new();
+    while let Some(worker) = workers.join_next().await {
+        samples.extend(worker.expect("bench worker panicked")?);
+    }
+    Ok(BenchReport::new(&samples, started.elapsed()))
+}
+
+/// Throughput, latencies and statuses of a bench, serializable as json.
+///
+/// ```
+/// use std::time::Duration;
+/// use grpc_client::bench::{BenchReport, Sample};
+/// let samples: Vec<Sample> = (1..=100)
+///     .map(|ms| Sample { latency: Duration::from_millis(ms), status: "Ok".to_string() })
+///     .collect();
+/// let report = BenchReport::new(&samples, Duration::from_secs(2));
+/// assert_eq!(report.throughput, 50.0);
+/// assert_eq!((report.latency.p50_ms, report.latency.p99_ms), (50.0, 99.0));
+/// assert_eq!(report.histogram.iter().map(|bucket| bucket.count).sum::<u64>(), 100);
+/// assert_eq!(report.status_codes["Ok"], 100);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BenchReport {
+    /// number of calls completed
+    pub requests: u64,
+    pub elapsed_ms: f64,
+    /// calls completed per second
+    pub throughput: f64,
+    pub latency: LatencySummary,
+    pub histogram: Vec<HistogramBucket>,
+    /// number of calls ended with each status
+    pub status_codes: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// calls slower than the previous bucket and at most as slow as `up_to_ms`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    pub up_to_ms: f64,
+    pub count: u64,
+}
+
+impl BenchReport {
+    pub fn new(samples: &[Sample], elapsed: Duration) -> Self {
+        let mut latencies: Vec<f64> = samples
+            .iter()
+            .map(|sample| sample.latency.as_secs_f64() * 1000.0)
+            .collect();
+        latencies.sort_by(f64::total_cmp);
+        let mut status_codes = BTreeMap::new();
+        for sample in samples {
+            *status_codes.entry(sample.status.clone()).or_default() += 1;
+        }
+        let requests = samples.len() as u64;
+        Self {
+            requests,
+            elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+            throughput: match elapsed.is_zero() {
+                true => 0.0,
+                false => requests as f64 / elapsed.as_secs_f64(),
+            },
+            latency: latency_summary(&latencies),
+            histogram: histogram(&latencies),
+            status_codes,
+        }
+    }
+    /// the report as `metric,value` lines, histogram buckets and statuses included
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use grpc_client::bench::{BenchReport, Sample};
+    /// let sample = Sample { latency: Duration::from_millis(3), status: "Unavailable".to_string() };
+    /// let csv = BenchReport::new(&[sample], Duration::from_secs(1)).to_csv();
+    /// assert!(csv.starts_with("metric,value\nrequests,1\n"));
+    /// assert!(csv.contains("\nlatency_p99_ms,3\n"));
+    /// assert!(csv.ends_with("\nstatus_Unavailable,1\n"));
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let latency = &self.latency;
+        let mut rows = vec![
+            ("requests".to_string(), self.requests.to_string()),
+            ("elapsed_ms".to_string(), self.elapsed_ms.to_string()),
+            ("throughput".to_string(), self.throughput.to_string()),
+            ("latency_min_ms".to_string(), latency.min_ms.to_string()),
+            ("latency_mean_ms".to_string(), latency.mean_ms.to_string()),
+            ("latency_p50_ms".to_string(), latency.p50_ms.to_string()),
+            ("latency_p90_ms".to_string(), latency.p90_ms.to_string()),
+            ("latency_p99_ms".to_string(), latency.p99_ms.to_string()),
+            ("latency_max_ms".to_string(), latency.max_ms.to_string()),
+        ];
+        for bucket in &self.histogram {
+            rows.push((
+                format!("histogram_up_to_{}_ms", bucket.up_to_ms),
+                bucket.count.to_string(),
+            ));
+        }
+        for (status, count) in &self.status_codes {
+            rows.push((format!("status_{status}"), count.to_string()));
+        }
+        let mut csv = "metric,value\n".to_string();
+        for (metric, value) in rows {
+            csv.push_str(&format!("{metric},{value}\n"));
+        }
+        csv
+    }
+}
+
+/// `latencies` must be sorted
+fn latency_summary(latencies: &[f64]) -> LatencySummary {
+    let (Some(min), Some(max)) = (latencies.first(), latencies.last()) else {
+        return LatencySummary::default();
+    };
+    LatencySummary {
+        min_ms: *min,
+        mean_ms: latencies.iter().sum::<f64>() / latencies.len() as f64,
+        p50_ms: percentile(latencies, 50.0),
+        p90_ms: percentile(latencies, 90.0),
+        p99_ms: percentile(latencies, 99.0),
+        max_ms: *max,
+    }
+}
+
+/// nearest rank percentile of sorted values
+fn percentile(sorted: &[f64], percent: f64) -> f64 {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// buckets of the same width from the fastest to the slowest call, `latencies` must be sorted
+fn histogram(latencies: &[f64]) -> Vec<HistogramBucket> {
+    let (Some(min), Some(max)) = (latencies.first(), latencies.last()) else {
+        return Vec::new();
+    };
+    let width = (max - min) / HISTOGRAM_BUCKETS as f64;
+    if width == 0.0 {
+        return vec![HistogramBucket {
+            up_to_ms: *max,
+            count: latencies.len() as u64,
+        }];
+    }
+    let mut buckets: Vec<HistogramBucket> = (1..=HISTOGRAM_BUCKETS)
+        .map(|i| HistogramBucket {
+            up_to_ms: min + width * i as f64,
+            count: 0,
+        })
+        .collect();
+    for latency in latencies {
+        let index = (((latency - min) / width).ceil() as usize).clamp(1, HISTOGRAM_BUCKETS);
+        buckets[index - 1].count += 1;
+    }
+    // rounding errors must not leave the slowest call out of the last bucket
+    buckets[HISTOGRAM_BUCKETS - 1].up_to_ms = *max;
+    buckets
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Summary:")?;
+        writeln!(f, "  Requests:    {}", self.requests)?;
+        writeln!(f, "  Total:       {:.3} s", self.elapsed_ms / 1000.0)?;
+        writeln!(f, "  Throughput:  {:.2} req/s", self.throughput)?;
+        let latency = &self.latency;
+        writeln!(f, "\nLatency:")?;
+        for (name, value) in [
+            ("min", latency.min_ms),
+            ("mean", latency.mean_ms),
+            ("p50", latency.p50_ms),
+            ("p90", latency.p90_ms),
+            ("p99", latency.p99_ms),
+            ("max", latency.max_ms),
+        ] {
+            writeln!(f, "  {name:<5} {value:.3} ms")?;
+        }
+        writeln!(f, "\nHistogram:")?;
+        let highest = self.histogram.iter().map(|b| b.count).max().unwrap_or(0);
+        for bucket in &self.histogram {
+            let bar = match highest {
+                0 => 0,
+                highest => bucket.count * HISTOGRAM_WIDTH / highest,
+            };
+            writeln!(
+                f,
+                "  {:>10.3} ms [{}] {}",
+                bucket.up_to_ms,
+                bucket.count,
+                "∎".repeat(bar as usize)
+            )?;
+        }
+        writeln!(f, "\nStatus codes:")?;
+        for (status, count) in &self.status_codes {
+            writeln!(f, "  {status}: {count}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataMap};
+
+use crate::client::GrpcClientError;
+
+use crate::projection::Projection;
+use crate::retry::RetryPolicy;
+
+/// Options applied to a single call made with [`crate::Client::request_with_options`].
+///
+/// ```
+/// use std::time::Duration;
+/// use grpc_client::CallOptions;
+/// let options = CallOptions::new().timeout(Duration::from_secs(2));
+/// assert_eq!(options.timeout, Some(Duration::from_secs(2)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    /// Maximum time to wait for the response.
+    /// It is sent to the server as the `grpc-timeout` header, and the call is cancelled locally once exceeded.
+    pub timeout: Option<Duration>,
+    /// Retry unary calls failing with a retryable status code, none by default.
+    /// The timeout above bounds the whole call, retries included.
+    pub retry: Option<RetryPolicy>,
+    /// Compress the request message with this encoding, the server must support it.
+    pub compression: Option<CompressionEncoding>,
+    /// Encodings advertised in `grpc-accept-encoding`, the server may compress its response with any of them.
+    pub accept_compression: Vec<CompressionEncoding>,
+    /// Custom metadata sent with the request.
+    pub metadata: MetadataMap,
+    /// Keep only these fields in the response messages.
+    /// The paths are checked against the output message before sending the request.
+    pub projection: Option<Projection>,
+}
+
+impl CallOptions {
+    /// options with every setting left to its default (no timeout, no retry, no compression)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// bound the call duration, see [`CallOptions::timeout`]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    /// retry failed unary calls, see [`CallOptions::retry`]
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+    /// compress the request, see [`CallOptions::compression`]
+    pub fn compression(mut self, encoding: CompressionEncoding) -> Self {
+        self.compression = Some(encoding);
+        self
+    }
+    /// add a metadata entry sent with the request, fails if the key or the value is not valid ascii metadata
+    ///
+    /// ```
+    /// use grpc_client::CallOptions;
+    /// let options = CallOptions::new().header("authorization", "Bearer token").unwrap();
+    /// assert_eq!(options.metadata.get("authorization").unwrap(), "Bearer token");
+    /// assert!(CallOptions::new().header("bad key", "value").is_err());
+    /// ```
+    pub fn header(mut self, key: &str, value: &str) -> Result<Self, GrpcClientError> {
+        let invalid = || GrpcClientError::InvalidMetadata(format!("{key}: {value}"));
+        let parsed_key: AsciiMetadataKey = key.parse().map_err(|_| invalid())?;
+        let parsed_value: AsciiMetadataValue = value.parse().map_err(|_| invalid())?;
+        self.metadata.append(parsed_key, parsed_value);
+        Ok(self)
+    }
+    /// keep only some fields of the responses, see [`CallOptions::projection`]
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.projection = Some(projection);
+        self
+    }
+    /// accept compressed responses, see [`CallOptions::accept_compression`]
+    pub fn accept_compression(mut self, encoding: CompressionEncoding) -> Self {
+        if !self.accept_compression.contains(&encoding) {
+            self.accept_compression.push(encoding);
+        }
+        self
+    }
+}
+
+/// every compression encoding supported by this client
+pub const SUPPORTED_COMPRESSIONS: [CompressionEncoding; 3] = [
+    CompressionEncoding::Gzip,
+    CompressionEncoding::Deflate,
+    CompressionEncoding::Zstd,
+];
+
+/// parse a compression encoding from its `grpc-encoding` name
+///
+/// ```
+/// use grpc_client::call_options::parse_compression;
+/// use tonic::codec::CompressionEncoding;
+/// assert_eq!(parse_compression("gzip"), Some(CompressionEncoding::Gzip));
+/// assert_eq!(parse_compression("ZSTD"), Some(CompressionEncoding::Zstd));
+/// assert_eq!(parse_compression("brotli"), None);
+/// ```
+pub fn parse_compression(s: &str) -> Option<CompressionEncoding> {
+    SUPPORTED_COMPRESSIONS
+        .into_iter()
+        .find(|encoding| encoding.to_string().eq_ignore_ascii_case(s))
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use prost_reflect::DynamicMessage;
+use tonic::Code;
+use tonic::metadata::MetadataMap;
+
+/// Everything known about a successful call made with [`crate::Client::request_with_options`].
+#[derive(Debug, Clone)]
+pub struct CallResult {
+    /// metadata sent with the last attempt of the request
+    pub request_metadata: MetadataMap,
+    /// initial metadata sent back by the server
+    pub headers: MetadataMap,
+    /// messages received, exactly one for an unary method
+    pub messages: Vec<DynamicMessage>,
+    /// trailing metadata sent back by the server, `grpc-status` excluded
+    pub trailers: MetadataMap,
+    /// final status of the call
+    pub code: Code,
+    /// status message sent along the code, usually empty when the call succeeded
+    pub status_message: String,
+    /// time spent between the first attempt and the trailers, retries included
+    pub elapsed: Duration,
+    /// number of attempts needed to get this result
+    pub attempts: u32,
+}
+
+impl CallResult {
+    /// first message received, which is the response of an unary method
+    pub fn message(&self) -> Option<&DynamicMessage> {
+        self.messages.first()
+    }
+    /// consume the result to keep only the first message received
+    pub fn into_message(self) -> Option<DynamicMessage> {
+        self.messages.into_iter().next()
+    }
+    /// compression used by the server for the response, if any
+    pub fn response_encoding(&self) -> Option<&str> {
+        self.headers
+            .get("grpc-encoding")
+            .and_then(|encoding| encoding.to_str().ok())
+    }
+}
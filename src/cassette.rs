@@ -0,0 +1,320 @@
+//! Record the calls made by a [`crate::Client`] in a json cassette, then replay them without any server.
+//!
+//! A cassette keeps the descriptors of the recorded methods, so its messages are written in the
+//! protobuf json mapping and can be edited by hand. A replayed call is matched by its method and its
+//! request message, the metadata sent is not compared. When the same request was recorded several
+//! times, the recorded answers are served in order, then the last one again.
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use http::{HeaderMap, HeaderName, HeaderValue};
+use prost_reflect::{
+    DescriptorPool, DeserializeOptions, DynamicMessage, MessageDescriptor, MethodDescriptor,
+};
+use serde::{Deserialize, Serialize};
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+use tracing::log::warn;
+
+use crate::call_result::CallResult;
+use crate::client::GrpcClientError;
+use crate::retry::parse_code;
+
+/// Calls recorded by a [`Recorder`], as saved in a cassette file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// `FileDescriptorSet` of the recorded methods and their dependencies, base64 encoded
+    #[serde(default)]
+    pub descriptor_set: String,
+    #[serde(default)]
+    pub interactions: Vec<Interaction>,
+}
+
+/// One recorded call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// full name of the method, ex: helloworld.Greeter.SayHello
+    pub method: String,
+    pub request: serde_json::Value,
+    #[serde(default)]
+    pub request_metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub responses: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub trailers: BTreeMap<String, String>,
+    /// name of the status code, ex: `Ok`, `NotFound`
+    pub code: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+    /// `google.rpc.Status` details of an error, base64 encoded
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub details: String,
+    /// timeout of a call ended by its deadline, in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, GrpcClientError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            GrpcClientError::InvalidInput(format!("cannot read {}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&text).map_err(|e| {
+            GrpcClientError::InvalidInput(format!("invalid cassette {}: {e}", path.display()))
+        })
+    }
+    pub fn save(&self, path: &Path) -> Result<(), GrpcClientError> {
+        let text = serde_json::to_string_pretty(self).expect("cassettes are valid json");
+        std::fs::write(path, text + "\n").map_err(|e| {
+            GrpcClientError::InvalidInput(format!("cannot write {}: {e}", path.display()))
+        })
+    }
+    /// descriptors saved with the calls
+    pub fn descriptor_pool(&self) -> Result<DescriptorPool, GrpcClientError> {
+        let bytes = BASE64_STANDARD.decode(&self.descriptor_set).map_err(|e| {
+            GrpcClientError::DescriptorLoadError(format!("invalid cassette descriptors: {e}"))
+        })?;
+        Ok(DescriptorPool::decode(bytes.as_slice())?)
+    }
+}
+
+/// Shared handle on the calls recorded so far, every clone records in the same cassette.
+/// See [`crate::Client::record`].
+#[derive(Debug, Clone, Default)]
+pub struct Recorder(Arc<Mutex<Recording>>);
+
+#[derive(Debug, Default)]
+struct Recording {
+    /// descriptors of the recorded methods, a single pool unless the clients use different ones
+    pools: Vec<DescriptorPool>,
+    interactions: Vec<Interaction>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// keep the outcome of a call, errors without a status from the server are left out, like the
+    /// calls with a message without json mapping, an `Any` of a type the descriptors do not have.
+    /// `metadata` is the one given to the call, a successful result holds the one really sent
+    pub(crate) fn record(
+        &self,
+        method: &MethodDescriptor,
+        request: &DynamicMessage,
+        metadata: &MetadataMap,
+        result: &Result<CallResult, GrpcClientError>,
+    ) {
+        let responses = match result {
+            Ok(result) => result.messages.iter().map(message_to_json).collect(),
+            Err(_) => Ok(Vec::new()),
+        };
+        let (Ok(request), Ok(responses)) = (message_to_json(request), responses) else {
+            warn!(
+                "call of {} not recorded, a message has no json mapping",
+                method.full_name()
+            );
+            return;
+        };
+        let mut interaction = Interaction {
+            method: method.full_name().to_string(),
+            request,
+            request_metadata: metadata_to_map(metadata),
+            headers: BTreeMap::new(),
+            responses: Vec::new(),
+            trailers: BTreeMap::new(),
+            code: format!("{:?}", Code::Ok),
+            message: String::new(),
+            details: String::new(),
+            timeout_ms: None,
+        };
+        match result {
+            Ok(result) => {
+                interaction.request_metadata = metadata_to_map(&result.request_metadata);
+                interaction.headers = metadata_to_map(&result.headers);
+                interaction.responses = responses;
+                interaction.trailers = metadata_to_map(&result.trailers);
+            }
+            Err(GrpcClientError::DeadlineExceeded(timeout)) => {
+                interaction.code = format!("{:?}", Code::DeadlineExceeded);
+                interaction.timeout_ms = Some(timeout.as_millis() as u64);
+            }
+            Err(error) => {
+                let Some(status) = error.status() else {
+                    return;
+                };
+                interaction.trailers = metadata_to_map(status.metadata());
+                interaction.code = format!("{:?}", status.code());
+                interaction.message = status.message().to_string();
+                interaction.details = BASE64_STANDARD.encode(status.details());
+            }
+        }
+        let mut recording = self.0.lock().expect("recorder lock poisoned");
+        let pool = method.parent_pool();
+        if !recording.pools.contains(pool) {
+            recording.pools.push(pool.clone());
+        }
+        recording.interactions.push(interaction);
+    }
+    /// the calls recorded so far, with the descriptors they need
+    ///
+    /// ```
+    /// use grpc_client::cassette::Recorder;
+    /// let cassette = Recorder::new().cassette();
+    /// assert!(cassette.interactions.is_empty());
+    /// assert!(cassette.descriptor_pool().unwrap().services().next().is_none());
+    /// ```
+    pub fn cassette(&self) -> Cassette {
+        let recording = self.0.lock().expect("recorder lock poisoned");
+        // encoded by prost-reflect, the files keep their extension options, like google.api.http
+        let mut names = Vec::new();
+        let mut set = Vec::new();
+        for pool in &recording.pools {
+            for file in pool.files() {
+                if !names.contains(&file.name().to_string()) {
+                    names.push(file.name().to_string());
+                    prost::encoding::bytes::encode(1, &file.encode_to_vec(), &mut set);
+                }
+            }
+        }
+        Cassette {
+            descriptor_set: BASE64_STANDARD.encode(set),
+            interactions: recording.interactions.clone(),
+        }
+    }
+}
+
+/// Serves the calls of a cassette, see [`crate::Client::replay`].
+#[derive(Debug)]
+pub struct Player {
+    pool: DescriptorPool,
+    interactions: Vec<Interaction>,
+    /// number of times each interaction was served
+    served: Mutex<Vec<usize>>,
+}
+
+impl Player {
+    pub fn new(cassette: Cassette) -> Result<Self, GrpcClientError> {
+        Ok(Self {
+            pool: cassette.descriptor_pool()?,
+            served: Mutex::new(vec![0; cassette.interactions.len()]),
+            interactions: cassette.interactions,
+        })
+    }
+    /// descriptors of the cassette
+    pub fn descriptor_pool(&self) -> &DescriptorPool {
+        &self.pool
+    }
+    /// the recorded answer to this request, `metadata` is returned as the metadata sent
+    pub(crate) fn play(
+        &self,
+        method: &MethodDescriptor,
+        request: &DynamicMessage,
+        metadata: &MetadataMap,
+    ) -> Result<CallResult, GrpcClientError> {
+        let matching: Vec<usize> = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, recorded)| recorded.method == method.full_name())
+            .filter(|(_, recorded)| {
+                json_to_message(method.input(), &recorded.request).is_ok_and(|r| &r == request)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        let index = {
+            let mut served = self.served.lock().expect("player lock poisoned");
+            let index = matching
+                .iter()
+                .find(|index| served[**index] == 0)
+                .or(matching.last())
+                .copied()
+                .ok_or_else(|| {
+                    GrpcClientError::NotFoundError(format!(
+                        "recorded call of {} with this request",
+                        method.full_name()
+                    ))
+                })?;
+            served[index] += 1;
+            index
+        };
+        let recorded = &self.interactions[index];
+        let code = parse_code(&recorded.code).ok_or_else(|| {
+            GrpcClientError::InvalidInput(format!(
+                "unknown status code '{}' in cassette",
+                recorded.code
+            ))
+        })?;
+        if let (Code::DeadlineExceeded, Some(timeout)) = (code, recorded.timeout_ms) {
+            return Err(GrpcClientError::DeadlineExceeded(Duration::from_millis(
+                timeout,
+            )));
+        }
+        if code != Code::Ok {
+            let details = BASE64_STANDARD.decode(&recorded.details).map_err(|e| {
+                GrpcClientError::InvalidInput(format!("invalid status details in cassette: {e}"))
+            })?;
+            let status = Status::with_details_and_metadata(
+                code,
+                recorded.message.clone(),
+                details.into(),
+                map_to_metadata(&recorded.trailers)?,
+            );
+            return Err(GrpcClientError::from_status(status, Some(&self.pool)));
+        }
+        let messages = recorded
+            .responses
+            .iter()
+            .map(|response| json_to_message(method.output(), response))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CallResult {
+            request_metadata: metadata.clone(),
+            headers: map_to_metadata(&recorded.headers)?,
+            messages,
+            trailers: map_to_metadata(&recorded.trailers)?,
+            code,
+            status_message: recorded.message.clone(),
+            elapsed: Duration::ZERO,
+            attempts: 1,
+        })
+    }
+}
+
+fn message_to_json(message: &DynamicMessage) -> Result<serde_json::Value, serde_json::Error> {
+    serde_json::to_value(message)
+}
+
+fn json_to_message(
+    desc: MessageDescriptor,
+    json: &serde_json::Value,
+) -> Result<DynamicMessage, GrpcClientError> {
+    DynamicMessage::deserialize_with_options(desc, json, &DeserializeOptions::new())
+        .map_err(|e| GrpcClientError::InvalidInput(format!("invalid message in cassette: {e}")))
+}
+
+/// metadata as text, binary values are kept base64 encoded like on the wire
+fn metadata_to_map(metadata: &MetadataMap) -> BTreeMap<String, String> {
+    metadata
+        .clone()
+        .into_headers()
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn map_to_metadata(map: &BTreeMap<String, String>) -> Result<MetadataMap, GrpcClientError> {
+    let mut headers = HeaderMap::new();
+    for (key, value) in map {
+        let invalid = || GrpcClientError::InvalidMetadata(format!("{key}: {value}"));
+        headers.append(
+            HeaderName::try_from(key.as_str()).map_err(|_| invalid())?,
+            HeaderValue::try_from(value.as_str()).map_err(|_| invalid())?,
+        );
+    }
+    Ok(MetadataMap::from_headers(headers))
+}
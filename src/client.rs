@@ -1,41 +1,70 @@
-use http::uri::InvalidUri;
+use http::uri::{InvalidUri, PathAndQuery};
 use prost::Message;
-use prost_reflect::{DescriptorError, DescriptorPool, DynamicMessage, prost_types};
+use prost_reflect::{
+    DescriptorError, DescriptorPool, DynamicMessage, MethodDescriptor, prost_types,
+};
 use prost_types::FileDescriptorProto;
-use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio_stream::Stream;
 use tokio_stream::StreamExt;
-use tonic::{Request, client::Grpc, transport::Channel};
+use tonic::metadata::MetadataMap;
+use tonic::{Request, Status, client::Grpc};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::{HealthCheckRequest, health_client::HealthClient};
 use tonic_reflection::pb::v1::{
     ServerReflectionRequest, server_reflection_client::ServerReflectionClient,
     server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
 };
-use tracing::log::{debug, error};
+use tracing::log::{debug, info};
 
+use crate::call_options::CallOptions;
+use crate::call_result::CallResult;
+use crate::cassette::{Player, Recorder};
+use crate::describe::Symbol;
 use crate::dynamic_codec::DynamicCodec;
+use crate::exit_code;
+pub use crate::filter::{GrpcFilter, GrpcFilters};
+use crate::listing::{PackageListing, list_services, render_services};
+use crate::status_details::decode_status_details;
+use crate::transport::{self, CallKind, Protocol, Transport};
 
 /// Grpc client with reflection support
+///
+/// Clones share the same channel and the descriptors fetched so far.
+#[derive(Clone)]
 pub struct Client {
     /// ServerReflectionClient does not seem to allow to retrieve the inner client so we need to duplicate it
     /// the channel support the only tcp connection of this, so this should not be too costly or seen in the server log.
     /// support only v1 reflection api for now.
-    reflection_client: ServerReflectionClient<Channel>,
-    cache: (), // todo : implement a cache system with file/in memory storage. (at least file for the client)
-    pub client: Grpc<Channel>,
-}
-#[derive(Debug, Clone)]
-pub struct GrpcFilter {
-    pub file: Option<String>,
-    pub service: Option<String>,
-    pub method: Option<String>,
+    reflection_client: ServerReflectionClient<Transport>,
+    /// descriptors used for the calls, fetched once with reflection or given by [`Client::set_descriptor_pool`]
+    cache: Option<DescriptorPool>, // todo : add a file storage for the client
+    /// standard health service, its descriptors are built in so it works without reflection
+    health_client: HealthClient<Transport>,
+    /// every call is added to this cassette when set
+    recorder: Option<Recorder>,
+    /// calls are answered from a cassette instead of the server when set
+    player: Option<Arc<Player>>,
+    /// protocol of the transport, connect needs to know how each call is made
+    protocol: Protocol,
+    /// metadata sent with the last attempt of the last call, even a failed one
+    last_request_metadata: Option<MetadataMap>,
+    pub client: Grpc<Transport>,
 }
-
 #[derive(Error, Debug)]
 pub enum GrpcClientError {
     #[error("Failed to connect to given url")]
     GrpcClientCreationError(#[from] tonic::transport::Error),
     #[error("tonic error {0}")]
     ReflectionRequestError(#[from] tonic::Status),
+    #[error("tonic error {0}\n{1:#}")]
+    StatusWithDetails(tonic::Status, serde_json::Value),
+    #[error("Deadline exceeded, no response after {0:?}")]
+    DeadlineExceeded(Duration),
+    #[error("Invalid metadata {0}")]
+    InvalidMetadata(String),
     #[error("Empty response")]
     EmptyResponse(String),
     #[error("Decode error : {0}")]
@@ -46,26 +75,195 @@ pub enum GrpcClientError {
     ConnectionFailed(String),
     #[error("Field {0} do not exist for message {1}")]
     ParamError(String, String),
+    #[error("Invalid field path '{0}': {1}")]
+    InvalidFieldPath(String, String),
+    #[error("Invalid filter '{0}': {1}")]
+    InvalidFilter(String, String),
     #[error("could not find {0}")]
     NotFoundError(String),
     #[error("[todo] desc error, maybe try getting the reflection data again: {0}")]
     DescriptorError(#[from] DescriptorError),
+    #[error("Could not load the descriptors: {0}")]
+    DescriptorLoadError(String),
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    #[error("Health check failed, status is {}", .0.as_str_name())]
+    NotServing(ServingStatus),
+    #[error("Server error: {0}")]
+    ServerError(String),
     #[error("[todo] desc error, maybe try getting the reflection data again: {0}")]
     UriError(#[from] InvalidUri),
 }
+impl GrpcClientError {
+    /// wrap a status returned by a call, decoding its rich error details if any (see [`decode_status_details`])
+    pub fn from_status(status: Status, pool: Option<&DescriptorPool>) -> Self {
+        match decode_status_details(&status, pool) {
+            Some(details) => GrpcClientError::StatusWithDetails(status, details),
+            None => GrpcClientError::ReflectionRequestError(status),
+        }
+    }
+    /// status returned by the server, if the error come from one
+    pub fn status(&self) -> Option<&Status> {
+        match self {
+            GrpcClientError::ReflectionRequestError(status)
+            | GrpcClientError::StatusWithDetails(status, _) => Some(status),
+            _ => None,
+        }
+    }
+    /// name of the grpc status code (`NotFound`, `Unavailable`...), or the error kind when there is no status
+    ///
+    /// ```
+    /// use grpc_client::client::GrpcClientError;
+    /// let error = GrpcClientError::from_status(tonic::Status::unavailable("down"), None);
+    /// assert_eq!(error.code_name(), "Unavailable");
+    /// assert_eq!(GrpcClientError::NotFoundError("method".to_string()).code_name(), "not_found");
+    /// ```
+    pub fn code_name(&self) -> String {
+        match (self, self.status()) {
+            (_, Some(status)) => format!("{:?}", status.code()),
+            (GrpcClientError::DeadlineExceeded(_), _) => {
+                format!("{:?}", tonic::Code::DeadlineExceeded)
+            }
+            (error, None) => error.kind().to_string(),
+        }
+    }
+    /// process exit code matching this error, see [`crate::exit_code`]
+    pub fn exit_code(&self) -> i32 {
+        exit_code::for_error(self)
+    }
+    /// short machine friendly name of the error kind
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GrpcClientError::GrpcClientCreationError(_) => "connection",
+            GrpcClientError::ConnectionFailed(_) => "connection",
+            GrpcClientError::ReflectionRequestError(_) => "status",
+            GrpcClientError::StatusWithDetails(_, _) => "status",
+            GrpcClientError::DeadlineExceeded(_) => "deadline_exceeded",
+            GrpcClientError::InvalidMetadata(_) => "invalid_metadata",
+            GrpcClientError::EmptyResponse(_) => "empty_response",
+            GrpcClientError::DecodeError(_) => "decode",
+            GrpcClientError::BadMessageType(_) => "bad_message_type",
+            GrpcClientError::ParamError(_, _) => "unknown_field",
+            GrpcClientError::InvalidFieldPath(_, _) => "invalid_field_path",
+            GrpcClientError::InvalidFilter(_, _) => "invalid_filter",
+            GrpcClientError::NotFoundError(_) => "not_found",
+            GrpcClientError::DescriptorError(_) => "descriptor",
+            GrpcClientError::DescriptorLoadError(_) => "descriptor_load",
+            GrpcClientError::InvalidInput(_) => "invalid_input",
+            GrpcClientError::NotServing(_) => "not_serving",
+            GrpcClientError::ServerError(_) => "server",
+            GrpcClientError::UriError(_) => "invalid_uri",
+        }
+    }
+    /// machine readable description of the error, with the grpc status and its details when there is one
+    ///
+    /// ```
+    /// use grpc_client::client::GrpcClientError;
+    /// let error = GrpcClientError::from_status(tonic::Status::not_found("no such user"), None);
+    /// let json = error.to_json();
+    /// assert_eq!(json["error"], "status");
+    /// assert_eq!(json["code"], "NotFound");
+    /// assert_eq!(json["message"], "no such user");
+    /// assert_eq!(json["exit_code"], 69);
+    /// ```
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "error": self.kind(),
+            "message": self.to_string(),
+            "exit_code": self.exit_code(),
+        });
+        let code = match self {
+            GrpcClientError::DeadlineExceeded(_) => Some(tonic::Code::DeadlineExceeded),
+            _ => self.status().map(|status| status.code()),
+        };
+        if let Some(code) = code {
+            json["code"] = format!("{code:?}").into();
+            json["code_number"] = (code as i32).into();
+        }
+        if let Some(status) = self.status() {
+            json["message"] = status.message().into();
+        }
+        if let GrpcClientError::StatusWithDetails(_, details) = self {
+            json["details"] = details["details"].clone();
+        }
+        json
+    }
+}
 impl Client {
     /// Create a new GrpcClient, given a channel (which will be cloned)
     pub async fn new(url: String) -> Result<Self, GrpcClientError> {
-        let channel = Channel::from_shared(url).unwrap().connect().await?;
+        Self::with_protocol(url, Protocol::Grpc).await
+    }
+    /// Create a client speaking this protocol, see [`crate::transport`]
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// use grpc_client::Client;
+    /// use grpc_client::transport::Protocol;
+    /// let mut client = Client::with_protocol("http://localhost:8080".to_string(), Protocol::GrpcWeb).await.unwrap();
+    /// let response = client.request("helloworld.Greeter", "SayHello", vec![]).await;
+    /// # })
+    /// ```
+    pub async fn with_protocol(url: String, protocol: Protocol) -> Result<Self, GrpcClientError> {
+        let channel = transport::connect(&url, protocol).await?;
 
         let mut client = Self {
             reflection_client: ServerReflectionClient::new(channel.clone()),
-            cache: (),
+            cache: None,
+            health_client: HealthClient::new(channel.clone()),
+            recorder: None,
+            player: None,
+            protocol,
+            last_request_metadata: None,
             client: Grpc::new(channel),
         };
-        client.client.ready().await?;
+        client
+            .client
+            .ready()
+            .await
+            .map_err(|e| GrpcClientError::ConnectionFailed(e.to_string()))?;
         Ok(client)
     }
+    /// A client answering from a cassette, without any server: see [`crate::cassette`].
+    /// The descriptors are the ones of the cassette. Must be called from a tokio runtime.
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// # use grpc_client::Client;
+    /// use grpc_client::cassette::{Cassette, Player};
+    /// let cassette = Cassette::load("cassette.json".as_ref()).unwrap();
+    /// let mut client = Client::replay(Player::new(cassette).unwrap());
+    /// let response = client.request("helloworld.Greeter", "SayHello", vec![]).await;
+    /// # })
+    /// ```
+    pub fn replay(player: Player) -> Self {
+        // never connected, the calls stop at the player
+        let channel = transport::disconnected();
+        Self {
+            reflection_client: ServerReflectionClient::new(channel.clone()),
+            cache: Some(player.descriptor_pool().clone()),
+            health_client: HealthClient::new(channel.clone()),
+            recorder: None,
+            player: Some(Arc::new(player)),
+            protocol: Protocol::Grpc,
+            last_request_metadata: None,
+            client: Grpc::new(channel),
+        }
+    }
+    /// Add every call made from now on to the recorder, with its request, metadata, responses and status.
+    /// Clones of the client made afterwards record too. Health checks are not recorded.
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// # use grpc_client::Client;
+    /// use grpc_client::cassette::Recorder;
+    /// let recorder = Recorder::new();
+    /// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+    /// client.record(recorder.clone());
+    /// let response = client.request("helloworld.Greeter", "SayHello", vec![]).await;
+    /// recorder.cassette().save("cassette.json".as_ref()).unwrap();
+    /// # })
+    /// ```
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
     /// Make a dynamic request taking as parameter a service name (filename.servicename), method name, and arguments.
     /// function will return the dynamic message from tonic.
     /// ```no_run
@@ -81,38 +279,294 @@ impl Client {
         method: &str,
         arguments: Vec<(String, String)>,
     ) -> Result<DynamicMessage, GrpcClientError> {
-        let proto_files = self.get_proto_files().await.unwrap();
-        let mut pool = DescriptorPool::new();
-        pool.add_file_descriptor_protos(proto_files.into_iter())?;
+        self.request_with_options(service, method, arguments, &CallOptions::default())
+            .await?
+            .into_message()
+            .ok_or(GrpcClientError::EmptyResponse(
+                "No response message received".to_string(),
+            ))
+    }
+    /// Same as [`Client::request`], with per call settings like a timeout.
+    /// The returned [`CallResult`] holds every message received, along with the headers, trailers and timing.
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// # use grpc_client::{CallOptions, Client};
+    /// # use std::time::Duration;
+    /// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+    /// let options = CallOptions::new().timeout(Duration::from_millis(500));
+    /// let response = client.request_with_options(&"filename.service", &"methodName", vec![], &options).await;
+    /// # })
+    /// ```
+    pub async fn request_with_options(
+        &mut self,
+        service: &str,
+        method: &str,
+        arguments: Vec<(String, String)>,
+        options: &CallOptions,
+    ) -> Result<CallResult, GrpcClientError> {
+        let method = self.find_method(service, method).await?;
+        let mut request_msg = DynamicMessage::new(method.input());
+        for arg in arguments {
+            if request_msg.get_field_by_name(&arg.0).is_none() {
+                return Err(GrpcClientError::ParamError(arg.0, service.to_string()));
+            }
+            request_msg.set_field_by_name(&arg.0, prost_reflect::Value::String(arg.1));
+        }
+        self.request_message(&method, request_msg, options).await
+    }
+    /// Descriptor of a method, from the descriptors of the server or the ones given with [`Client::set_descriptor_pool`]
+    pub async fn find_method(
+        &mut self,
+        service: &str,
+        method: &str,
+    ) -> Result<MethodDescriptor, GrpcClientError> {
+        let pool = self.descriptor_pool().await?;
         let service_pool =
             pool.get_service_by_name(service)
                 .ok_or(GrpcClientError::NotFoundError(format!(
                     "service '{service}'"
                 )))?;
-        let method = service_pool
+        service_pool
             .methods()
             .find(|x| x.name() == method)
-            .ok_or(GrpcClientError::NotFoundError(format!("method '{method}'")))?;
-
-        let mut request_msg = DynamicMessage::new(method.input());
-        for arg in arguments {
-            if request_msg.get_field_by_name(&arg.0) == None {
-                return Err(GrpcClientError::ParamError(arg.0, service.to_string()));
-            }
-            request_msg.set_field_by_name(&arg.0, prost_reflect::Value::String(arg.1));
-            println!("fields : {:#?}", request_msg.get_field_by_name("name"));
+            .ok_or(GrpcClientError::NotFoundError(format!("method '{method}'")))
+    }
+    /// Same as [`Client::request_with_options`], with a request message already built,
+    /// ex: parsed from json with [`crate::encoding::parse_message`]
+    pub async fn request_message(
+        &mut self,
+        method: &MethodDescriptor,
+        request_msg: DynamicMessage,
+        options: &CallOptions,
+    ) -> Result<CallResult, GrpcClientError> {
+        if let Some(projection) = &options.projection {
+            projection.validate(&method.output())?;
         }
-
+        self.last_request_metadata = None;
+        let mut result = match &self.player {
+            Some(player) => player.play(method, &request_msg, &options.metadata),
+            None => self.send(method, &request_msg, options).await,
+        };
+        if let Some(recorder) = &self.recorder {
+            recorder.record(method, &request_msg, &options.metadata, &result);
+        }
+        if let (Ok(result), Some(projection)) = (&mut result, &options.projection) {
+            result.messages = result
+                .messages
+                .iter()
+                .map(|m| projection.apply(m))
+                .collect();
+        }
+        result
+    }
+    /// Metadata sent with the last attempt of the last call, `grpc-timeout` and
+    /// `grpc-previous-rpc-attempts` included, kept when the call failed. None when nothing was
+    /// sent, like for a call answered from a cassette.
+    pub fn last_request_metadata(&self) -> Option<&MetadataMap> {
+        self.last_request_metadata.as_ref()
+    }
+    /// send the request to the server, retrying it as the options tell
+    async fn send(
+        &mut self,
+        method: &MethodDescriptor,
+        request_msg: &DynamicMessage,
+        options: &CallOptions,
+    ) -> Result<CallResult, GrpcClientError> {
+        let pool = method.parent_pool().clone();
         let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
         // Create our DynamicCodec for the output type
         let codec = DynamicCodec {
             pool: pool.clone(),
             message_name: method.output().full_name().to_string(),
+            json: self.protocol.json(),
+        };
+        let kind = CallKind {
+            streaming: method.is_client_streaming() || method.is_server_streaming(),
+            json: self.protocol.json(),
+        };
+        let path: PathAndQuery = path.parse()?;
+        // the timeout bounds the whole call, retries included
+        let deadline = options
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        // a streaming call may fail after some messages went through, only unary calls are retried
+        let retry = options.retry.as_ref().filter(|_| !kind.streaming);
+        let max_attempts = retry.map_or(1, |p| p.max_attempts.max(1));
+        debug!("sending request to {path}");
+        let mut grpc = self.client.clone();
+        if let Some(encoding) = options.compression {
+            grpc = grpc.send_compressed(encoding);
+        }
+        for encoding in &options.accept_compression {
+            grpc = grpc.accept_compressed(*encoding);
+        }
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let mut req = Request::new(request_msg.clone());
+            *req.metadata_mut() = options.metadata.clone();
+            req.extensions_mut().insert(kind);
+            if attempt > 1 {
+                req.metadata_mut()
+                    .insert("grpc-previous-rpc-attempts", (attempt - 1).into());
+            }
+            let remaining = deadline.map(|(deadline, timeout)| {
+                (deadline.saturating_duration_since(Instant::now()), timeout)
+            });
+            if let Some((remaining, _)) = remaining {
+                req.set_timeout(remaining);
+            }
+            let request_metadata = req.metadata().clone();
+            self.last_request_metadata = Some(request_metadata.clone());
+            let call = call_once(&mut grpc, req, path.clone(), codec.clone());
+            let call = match remaining {
+                Some((remaining, timeout)) => tokio::time::timeout(remaining, call)
+                    .await
+                    .map_err(|_| GrpcClientError::DeadlineExceeded(timeout))?,
+                None => call.await,
+            }
+            .map(|response| (request_metadata, response));
+            let status = match call {
+                Ok((request_metadata, (headers, messages, trailers, status))) => {
+                    if attempt > 1 {
+                        info!("call succeeded after {attempt} attempts");
+                    }
+                    let result = CallResult {
+                        request_metadata,
+                        headers,
+                        messages,
+                        trailers,
+                        code: status.code(),
+                        status_message: status.message().to_string(),
+                        elapsed: started.elapsed(),
+                        attempts: attempt,
+                    };
+                    if let Some(encoding) = result.response_encoding() {
+                        info!("response compressed with {encoding}");
+                    }
+                    return Ok(result);
+                }
+                Err(status) => status,
+            };
+            match retry {
+                Some(policy) if attempt < max_attempts && policy.is_retryable(status.code()) => {
+                    let backoff = policy.backoff(attempt);
+                    // the next attempt would start after the deadline
+                    if let Some((deadline, timeout)) = deadline
+                        && backoff >= deadline.saturating_duration_since(Instant::now())
+                    {
+                        info!("no time left for attempt {}, giving up", attempt + 1);
+                        return Err(GrpcClientError::DeadlineExceeded(timeout));
+                    }
+                    info!(
+                        "attempt {attempt}/{max_attempts} failed with {:?}, retrying in {backoff:?}",
+                        status.code()
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                _ => {
+                    if attempt > 1 {
+                        info!("giving up after {attempt} attempts");
+                    }
+                    return Err(match deadline {
+                        // the server gave up before us, report it the same way as a local timeout
+                        Some((_, timeout)) if status.code() == tonic::Code::DeadlineExceeded => {
+                            GrpcClientError::DeadlineExceeded(timeout)
+                        }
+                        _ => GrpcClientError::from_status(status, Some(&pool)),
+                    });
+                }
+            }
+        }
+    }
+    /// Descriptors of every service exposed by the server, fetched with reflection on the first call then cached.
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// # use grpc_client::Client;
+    /// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+    /// let pool = client.descriptor_pool().await.unwrap();
+    /// let hello = pool.get_message_by_name("helloworld.HelloRequest");
+    /// # })
+    /// ```
+    pub async fn descriptor_pool(&mut self) -> Result<DescriptorPool, GrpcClientError> {
+        if let Some(pool) = &self.cache {
+            return Ok(pool.clone());
+        }
+        let proto_files = self.reflected_files().await?;
+        // some servers, like the tonic ones, do not send the files imported along a service
+        let pool = self.pool_with_dependencies(proto_files).await?;
+        self.cache = Some(pool.clone());
+        Ok(pool)
+    }
+    /// Use these descriptors instead of asking the server with reflection,
+    /// for servers without reflection, see [`crate::descriptor_source::DescriptorSource`]
+    pub fn set_descriptor_pool(&mut self, pool: DescriptorPool) {
+        self.cache = Some(pool);
+    }
+    /// Ask `grpc.health.v1.Health/Check` whether a service is serving, an empty name checks the whole server.
+    /// Only the metadata and the timeout of the options are used.
+    /// ```no_run
+    /// # tokio_test::block_on(async {
+    /// # use grpc_client::{CallOptions, Client};
+    /// use tonic_health::pb::health_check_response::ServingStatus;
+    /// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+    /// let status = client.check_health("helloworld.Greeter", &CallOptions::default()).await.unwrap();
+    /// assert_eq!(status, ServingStatus::Serving);
+    /// # })
+    /// ```
+    pub async fn check_health(
+        &mut self,
+        service: &str,
+        options: &CallOptions,
+    ) -> Result<ServingStatus, GrpcClientError> {
+        let mut req = Request::new(HealthCheckRequest {
+            service: service.to_string(),
+        });
+        *req.metadata_mut() = options.metadata.clone();
+        let response = match options.timeout {
+            Some(timeout) => {
+                req.set_timeout(timeout);
+                tokio::time::timeout(timeout, self.health_client.check(req))
+                    .await
+                    .map_err(|_| GrpcClientError::DeadlineExceeded(timeout))?
+            }
+            None => self.health_client.check(req).await,
         };
-        let req = Request::new(request_msg);
-        println!("sending unary request.");
-        let response = self.client.unary(req, path.parse()?, codec).await?;
-        Ok(response.into_inner())
+        let response = response.map_err(|status| GrpcClientError::from_status(status, None))?;
+        Ok(response.into_inner().status())
+    }
+    /// Follow the status of a service with `grpc.health.v1.Health/Watch`.
+    /// The current status comes first, then every change until the server closes the stream.
+    /// Depending on the server, an unknown service is reported as `SERVICE_UNKNOWN` or with a `NOT_FOUND` status.
+    pub async fn watch_health(
+        &mut self,
+        service: &str,
+        options: &CallOptions,
+    ) -> Result<impl Stream<Item = Result<ServingStatus, GrpcClientError>> + use<>, GrpcClientError>
+    {
+        let mut req = Request::new(HealthCheckRequest {
+            service: service.to_string(),
+        });
+        *req.metadata_mut() = options.metadata.clone();
+        if let Some(timeout) = options.timeout {
+            req.set_timeout(timeout);
+        }
+        req.extensions_mut().insert(CallKind {
+            streaming: true,
+            json: false,
+        });
+        let stream = self
+            .health_client
+            .watch(req)
+            .await
+            .map_err(|status| GrpcClientError::from_status(status, None))?
+            .into_inner();
+        Ok(stream.map(|response| {
+            response
+                .map(|response| response.status())
+                .map_err(|status| GrpcClientError::from_status(status, None))
+        }))
     }
     /// send a reflection request and wait for the response.
     /// Should probably be private
@@ -125,7 +579,11 @@ impl Client {
             host: "".to_string(),
             message_request: Some(message),
         };
-        let request = Request::new(tokio_stream::once(request));
+        let mut request = Request::new(tokio_stream::once(request));
+        request.extensions_mut().insert(CallKind {
+            streaming: true,
+            json: false,
+        });
         let mut inbound = self
             .reflection_client
             .server_reflection_info(request)
@@ -136,74 +594,28 @@ impl Client {
             return Ok(response?.message_response.expect("some MessageResponse"));
         }
 
-        return Err(GrpcClientError::EmptyResponse(
+        Err(GrpcClientError::EmptyResponse(
             "No response received".to_string(),
-        ));
+        ))
     }
 
-    /// show services exposed by a grpc server on stdout.
+    /// show services exposed by a grpc server on stdout, with their messages expanded,
+    /// see [`crate::listing::render_services`].
     /// Filter which service to show by an include filter vector
     pub async fn list_services_to_stdout(
         &mut self,
         filter: GrpcFilters,
     ) -> Result<(), GrpcClientError> {
-        let files = self.get_proto_files().await?;
-        debug!("filter : {:?}", filter);
-        for f in files {
-            debug!("checking file '{}'", &f.package());
-            if filter.filter_file(&f.package.clone().unwrap_or_default()) {
-                continue;
-            }
-            println!("\nfile {} :", &f.package());
-            for s in &f.service {
-                debug!("checking service '{}'", s.name());
-                if filter.filter_service(s.name()) {
-                    continue;
-                }
-                println!("  service {:?}", &s.name());
-                for m in &s.method {
-                    debug!("checking method '{}'", m.name());
-
-                    if filter.filter_method(m.name()) {
-                        continue;
-                    }
-                    println!(
-                        "    method : {}, input: {}, output : {}",
-                        &m.name(),
-                        &m.input_type(),
-                        &m.output_type()
-                    );
-                    // let's show the input_type message definition and the output_type message here
-                    let _ = self.print_grpc_message(&f, &m.input_type());
-
-                    while let Some(o) = &m.options {
-                        println!("   option : {:#?}", &o);
-                    }
-                }
-            }
-        }
+        let pool = self.descriptor_pool().await?;
+        print!("{}", render_services(&pool, &filter));
         Ok(())
     }
-    fn print_grpc_message(
-        self: &Self,
-        file: &FileDescriptorProto,
-        msg_name: &str,
-    ) -> Result<(), Box<dyn Error>> {
-        for msg in &file.message_type {
-            if format!(".{}.{}", file.package(), msg.name()) != msg_name {
-                //println!(".{}.{} != {}", file.package(), &msg.name(), &msg_name);
-                continue;
-            }
-            for m in &msg.field {
-                println!(
-                    "        Field '{}' is of type {}",
-                    m.json_name(),
-                    &m.r#type().as_str_name()
-                )
-            }
-            // println!("\n{:#?}\n\n", &msg);
-        }
-        Ok(())
+    /// services exposed by a grpc server as serializable data, see [`crate::listing::list_services`]
+    pub async fn list_services(
+        &mut self,
+        filter: GrpcFilters,
+    ) -> Result<Vec<PackageListing>, GrpcClientError> {
+        Ok(list_services(&self.descriptor_pool().await?, &filter))
     }
     /// get protobuf file from a remote server with reflection v1 api.
     /// For now v1_alpha is not accepted, it would be nice to do the work to make it parametrable on runtime
@@ -218,6 +630,11 @@ impl Client {
     pub async fn get_proto_files(
         &mut self,
     ) -> Result<Vec<prost_types::FileDescriptorProto>, GrpcClientError> {
+        let files = self.reflected_files().await?;
+        Ok(files.into_iter().map(|file| file.proto).collect())
+    }
+    /// internal, the files of every service, see [`Client::get_proto_files`]
+    async fn reflected_files(&mut self) -> Result<Vec<ReflectedFile>, GrpcClientError> {
         let response = self
             .make_reflection_request(MessageRequest::ListServices(String::new()))
             .await?;
@@ -226,99 +643,184 @@ impl Client {
             let mut proto_files = Vec::new();
 
             for service in services_response.service {
-                let mut descriptors = self
+                let descriptors = self
                     .get_file_descriptor_from_symbol(service.name.clone())
                     .await?;
-                proto_files.append(&mut descriptors);
+                // services of the same file send it again, along with the dependencies they share
+                for file in descriptors {
+                    if !proto_files
+                        .iter()
+                        .any(|known: &ReflectedFile| known.proto.name() == file.proto.name())
+                    {
+                        proto_files.push(file);
+                    }
+                }
             }
-            return Ok(proto_files);
+            Ok(proto_files)
         } else {
-            return Err(GrpcClientError::BadMessageType(
+            Err(GrpcClientError::BadMessageType(
                 "Expected a ListServicesResponse variant".to_string(),
-            ));
+            ))
         }
     }
     /// internal, used to get a single file descriptor proto from a symbol
     async fn get_file_descriptor_from_symbol(
         &mut self,
         symbol: String,
-    ) -> Result<Vec<prost_types::FileDescriptorProto>, GrpcClientError> {
-        let response = self
-            .make_reflection_request(MessageRequest::FileContainingSymbol(symbol))
-            .await?;
-
-        if let MessageResponse::FileDescriptorResponse(descriptor_response) = response {
-            let mut descriptors = Vec::new();
-            for file_descriptor_proto in descriptor_response.file_descriptor_proto {
-                let file_descriptor =
-                    prost_types::FileDescriptorProto::decode(&file_descriptor_proto[..])?;
-                descriptors.push(file_descriptor);
+    ) -> Result<Vec<ReflectedFile>, GrpcClientError> {
+        self.get_file_descriptors(MessageRequest::FileContainingSymbol(symbol))
+            .await
+    }
+    /// internal, send a reflection request answered by file descriptors
+    async fn get_file_descriptors(
+        &mut self,
+        request: MessageRequest,
+    ) -> Result<Vec<ReflectedFile>, GrpcClientError> {
+        match self.make_reflection_request(request).await? {
+            MessageResponse::FileDescriptorResponse(descriptor_response) => {
+                let mut descriptors = Vec::new();
+                for file_descriptor_proto in descriptor_response.file_descriptor_proto {
+                    let file_descriptor =
+                        prost_types::FileDescriptorProto::decode(&file_descriptor_proto[..])?;
+                    descriptors.push(ReflectedFile {
+                        proto: file_descriptor,
+                        encoded: file_descriptor_proto,
+                    });
+                }
+                Ok(descriptors)
             }
-            return Ok(descriptors);
-        } else {
-            return Err(GrpcClientError::BadMessageType(
+            MessageResponse::ErrorResponse(error) => Err(GrpcClientError::ReflectionRequestError(
+                Status::new(error.error_code.into(), error.error_message),
+            )),
+            _ => Err(GrpcClientError::BadMessageType(
                 "Expected a FileDescriptorResponse variant".to_string(),
-            ));
-        }
-    }
-}
-#[derive(Debug, Clone)]
-pub struct GrpcFilters(Vec<GrpcFilter>);
-impl GrpcFilters {
-    pub fn new(input: Vec<std::string::String>) -> GrpcFilters {
-        let mut filters = vec![];
-        for filter in input {
-            let mut v = filter.split(",");
-            filters.push(GrpcFilter {
-                file: v.next().map(|e| e.to_string()),
-                service: v.next().map(|e| e.to_string()),
-                method: v.next().map(|e| e.to_string()),
-            });
+            )),
         }
-        GrpcFilters(filters)
     }
-    pub fn new_empty() -> GrpcFilters {
-        GrpcFilters(vec![])
-    }
-
-    // todo : put this as functionnal design in an impl grpcfilter.
-    /// return false if filter is empty or match the value
-    pub fn filter_file(&self, val: &String) -> bool {
-        if val.starts_with("grpc.reflection.v") && self.0.is_empty() {
-            return true;
-        }
-        if self.0.is_empty()
-            || self
-                .0
-                .iter()
-                .any(|f| f.file.as_ref().map_or(true, |f| f == val))
+    /// Find the definition of any fully qualified symbol, see [`crate::describe::Symbol`].
+    ///
+    /// The descriptors already loaded are used when they define it, otherwise the file containing
+    /// the symbol is asked with reflection, along with its dependencies.
+    ///
+    ///```rust, no_run
+    /// # tokio_test::block_on(async {
+    /// use grpc_client::Client;
+    /// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+    /// let symbol = client.describe("helloworld.HelloRequest").await.unwrap();
+    /// println!("{} is a {}:\n{symbol}", symbol.full_name(), symbol.kind());
+    /// # })
+    ///```
+    pub async fn describe(&mut self, symbol: &str) -> Result<Symbol, GrpcClientError> {
+        let symbol = symbol.trim_start_matches('.');
+        if let Some(found) = self
+            .cache
+            .as_ref()
+            .and_then(|pool| Symbol::find(pool, symbol))
         {
-            return false;
+            return Ok(found);
         }
-        return true;
-    }
-    /// return false if filter is empty or match the value
-    pub fn filter_method(&self, val: &str) -> bool {
-        if self.0.is_empty()
-            || self
-                .0
-                .iter()
-                .any(|f| f.method.as_ref().map_or(true, |f| f == val))
+        let not_found = || GrpcClientError::NotFoundError(format!("symbol '{symbol}'"));
+        let files = match self
+            .get_file_descriptor_from_symbol(symbol.to_string())
+            .await
         {
-            return false;
-        }
-        return true;
+            Ok(files) => files,
+            // some servers only index types and services, not their fields or enum values
+            Err(e)
+                if e.status()
+                    .is_some_and(|s| s.code() == tonic::Code::NotFound) =>
+            {
+                let Some((parent, _)) = symbol.rsplit_once('.') else {
+                    return Err(not_found());
+                };
+                self.get_file_descriptor_from_symbol(parent.to_string())
+                    .await
+                    .map_err(|e| match e.status() {
+                        Some(status) if status.code() == tonic::Code::NotFound => not_found(),
+                        _ => e,
+                    })?
+            }
+            Err(e) => return Err(e),
+        };
+        let pool = self.pool_with_dependencies(files).await?;
+        Symbol::find(&pool, symbol).ok_or_else(not_found)
     }
-    /// return false if filter is empty or match the value
-    pub fn filter_service(&self, val: &str) -> bool {
-        if self.0.is_empty()
-            || self
-                .0
-                .iter()
-                .any(|f| f.service.as_ref().map_or(true, |f| f == val))
-        {
-            return false;
+    /// internal, build a pool from files and every file they import, asked by name with reflection.
+    /// The well known types are taken from prost-reflect when the server does not give them.
+    async fn pool_with_dependencies(
+        &mut self,
+        mut files: Vec<ReflectedFile>,
+    ) -> Result<DescriptorPool, GrpcClientError> {
+        let mut missing: Vec<String> = files
+            .iter()
+            .flat_map(|file| file.proto.dependency.clone())
+            .collect();
+        while let Some(name) = missing.pop() {
+            if files.iter().any(|file| file.proto.name() == name) {
+                continue;
+            }
+            let fetched = match self
+                .get_file_descriptors(MessageRequest::FileByFilename(name.clone()))
+                .await
+            {
+                Ok(fetched) => fetched,
+                Err(e) => match DescriptorPool::global().get_file_by_name(&name) {
+                    Some(file) => vec![ReflectedFile {
+                        proto: file.file_descriptor_proto().clone(),
+                        encoded: file.encode_to_vec(),
+                    }],
+                    None => return Err(e),
+                },
+            };
+            for file in fetched {
+                missing.extend(file.proto.dependency.clone());
+                if !files
+                    .iter()
+                    .any(|known| known.proto.name() == file.proto.name())
+                {
+                    files.push(file);
+                }
+            }
         }
-        return true;
+        // decoded from their bytes, the files keep their extension options, like google.api.http
+        let mut set = Vec::new();
+        for file in &files {
+            prost::encoding::bytes::encode(1, &file.encoded, &mut set);
+        }
+        let mut pool = DescriptorPool::new();
+        pool.decode_file_descriptor_set(set.as_slice())?;
+        Ok(pool)
+    }
+}
+/// a file sent by reflection, decoded and as sent: prost_types drops the extension options
+struct ReflectedFile {
+    proto: FileDescriptorProto,
+    encoded: Vec<u8>,
+}
+/// send a single request and read every message of the response, then its trailers
+async fn call_once(
+    grpc: &mut Grpc<Transport>,
+    req: Request<DynamicMessage>,
+    path: PathAndQuery,
+    codec: DynamicCodec,
+) -> Result<(MetadataMap, Vec<DynamicMessage>, MetadataMap, Status), Status> {
+    grpc.ready()
+        .await
+        .map_err(|e| Status::unavailable(format!("service not ready: {e}")))?;
+    let (headers, mut stream, _) = grpc.server_streaming(req, path, codec).await?.into_parts();
+    let mut messages = Vec::new();
+    while let Some(message) = stream.message().await? {
+        messages.push(message);
     }
+    let mut trailers = stream.trailers().await?.unwrap_or_default().into_headers();
+    // tonic turned any other code into an error, the status may still have a message
+    let status = Status::from_header_map(&trailers).unwrap_or_else(|| Status::ok(""));
+    trailers.remove("grpc-status");
+    trailers.remove("grpc-message");
+    Ok((
+        headers,
+        messages,
+        MetadataMap::from_headers(trailers),
+        status,
+    ))
 }
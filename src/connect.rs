@@ -0,0 +1,432 @@
+//! The connect protocol (connectrpc.com) over http/1.1.
+//!
+//! [`ConnectService`] turns the grpc requests made by tonic into connect ones, and their responses
+//! back into grpc ones. A unary call is a plain POST of its message: the trailers come back as
+//! `trailer-` headers and an error as a json body. A streaming call sends its messages in envelopes,
+//! laid out like grpc frames, and its response ends with a json message holding the status and the
+//! trailers. The [`CallKind`] of a request tells which form the call takes, and whether its
+//! messages are json.
+//!
+//! Bidi streams, reflection included, need a server accepting them over http/1.1: use local descriptors
+//! with the others. Compressed end of stream messages are not supported, they are only sent when asking
+//! for compression.
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use bytes::{BufMut, Bytes, BytesMut};
+use http::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, TE};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version};
+use http_body::Frame;
+use http_body_util::{BodyExt, Full, StreamBody};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use prost::Message;
+use prost_reflect::prost_types::Any;
+use serde::Deserialize;
+use tonic::body::Body;
+use tonic::metadata::MetadataMap;
+use tonic::{Code, Status};
+use tower::Service;
+
+use crate::retry::parse_code;
+use crate::transport::{CallKind, FrameDecoder, causes, decoded_body};
+
+/// flag of a compressed envelope, the same as for grpc frames
+const COMPRESSED_FLAG: u8 = 0x01;
+/// flag of the envelope ending a streamed response
+const END_STREAM_FLAG: u8 = 0x02;
+/// error details are base64 encoded, with or without padding
+const DETAILS_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Sends grpc calls to a connect server, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ConnectService {
+    client: Client<HttpConnector, Body>,
+    /// scheme and authority of the server, the path comes from each request
+    base: Uri,
+}
+
+impl ConnectService {
+    pub fn new(base: Uri) -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            base,
+        }
+    }
+}
+
+impl Service<http::Request<Body>> for ConnectService {
+    type Response = http::Response<Body>;
+    type Error = Status;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { service.send(request).await })
+    }
+}
+
+impl ConnectService {
+    async fn send(self, request: http::Request<Body>) -> Result<http::Response<Body>, Status> {
+        let kind = request
+            .extensions()
+            .get::<CallKind>()
+            .copied()
+            .unwrap_or_default();
+        let (parts, body) = request.into_parts();
+        let mut headers = parts.headers;
+        headers.remove(TE);
+        let format = if kind.json { "json" } else { "proto" };
+        let (content_type, content_encoding, accept_encoding) = match kind.streaming {
+            true => (
+                format!("application/connect+{format}"),
+                HeaderName::from_static("connect-content-encoding"),
+                HeaderName::from_static("connect-accept-encoding"),
+            ),
+            false => (
+                format!("application/{format}"),
+                CONTENT_ENCODING,
+                ACCEPT_ENCODING,
+            ),
+        };
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_str(&content_type).expect("valid content type"),
+        );
+        headers.insert("connect-protocol-version", HeaderValue::from_static("1"));
+        let timeout = headers.remove("grpc-timeout");
+        if let Some(millis) = timeout.and_then(|value| timeout_millis(value.to_str().ok()?)) {
+            headers.insert("connect-timeout-ms", millis.into());
+        }
+        if let Some(encodings) = headers.remove("grpc-accept-encoding") {
+            headers.insert(accept_encoding, encodings);
+        }
+        let encoding = headers.remove("grpc-encoding");
+        let body = match kind.streaming {
+            // grpc frames and connect envelopes are laid out the same, the messages go as they come
+            true => {
+                if let Some(encoding) = encoding {
+                    headers.insert(content_encoding, encoding);
+                }
+                body
+            }
+            false => {
+                let frame = body.collect().await?.to_bytes();
+                let (compressed, message) = split_frame(frame)?;
+                if let (true, Some(encoding)) = (compressed, encoding) {
+                    headers.insert(content_encoding, encoding);
+                }
+                Body::new(Full::new(message))
+            }
+        };
+
+        let mut uri = self.base.into_parts();
+        uri.path_and_query = parts.uri.path_and_query().cloned();
+        let uri = Uri::from_parts(uri)
+            .map_err(|e| Status::invalid_argument(format!("invalid connect url: {e}")))?;
+        let mut request = http::Request::new(body);
+        *request.method_mut() = Method::POST;
+        *request.uri_mut() = uri;
+        *request.version_mut() = Version::HTTP_11;
+        *request.headers_mut() = headers;
+
+        let response =
+            self.client.request(request).await.map_err(|e| {
+                Status::unavailable(format!("connect request failed: {}", causes(&e)))
+            })?;
+        let (parts, body) = response.into_parts();
+        if parts.status == StatusCode::OK && kind.streaming {
+            return Ok(streaming_response(parts, body));
+        }
+        let bytes = body
+            .collect()
+            .await
+            .map_err(|e| Status::unavailable(format!("response interrupted: {e}")))?
+            .to_bytes();
+        let (headers, trailers) = split_trailers(parts.headers)?;
+        if parts.status != StatusCode::OK {
+            let error = serde_json::from_slice::<ConnectError>(&bytes)
+                .unwrap_or_else(|_| ConnectError::from_http_status(parts.status));
+            // tonic reads the status of a response without body from its headers
+            let mut metadata = headers;
+            metadata.extend(trailers);
+            return Ok(error.into_status(metadata)?.into_http());
+        }
+        unary_response(headers, trailers, bytes)
+    }
+}
+
+/// a unary response back into a grpc one: its message in a frame, then the trailers
+fn unary_response(
+    mut headers: HeaderMap,
+    mut trailers: HeaderMap,
+    message: Bytes,
+) -> Result<http::Response<Body>, Status> {
+    let compressed = match headers.remove(CONTENT_ENCODING) {
+        Some(encoding) if encoding != "identity" => {
+            headers.insert("grpc-encoding", encoding);
+            true
+        }
+        _ => false,
+    };
+    grpc_headers(&mut headers);
+    trailers.insert("grpc-status", HeaderValue::from(Code::Ok as i32));
+    let mut frame = BytesMut::with_capacity(5 + message.len());
+    frame.put_u8(compressed as u8);
+    frame.put_u32(message.len() as u32);
+    frame.put_slice(&message);
+    let frames = [Frame::data(frame.freeze()), Frame::trailers(trailers)];
+    let body = StreamBody::new(tokio_stream::iter(frames.map(Ok::<_, Status>)));
+    let mut response = http::Response::new(Body::new(body));
+    *response.headers_mut() = headers;
+    Ok(response)
+}
+
+/// a streamed response back into a grpc one, its envelopes are decoded as they come
+fn streaming_response<B>(mut parts: http::response::Parts, body: B) -> http::Response<Body>
+where
+    B: http_body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: std::fmt::Display + Send,
+{
+    if let Some(encoding) = parts.headers.remove("connect-content-encoding") {
+        parts.headers.insert("grpc-encoding", encoding);
+    }
+    grpc_headers(&mut parts.headers);
+    http::Response::from_parts(parts, decoded_body(body, EnvelopeDecoder::default()))
+}
+
+/// headers of a connect response made the ones tonic expects of a grpc one
+fn grpc_headers(headers: &mut HeaderMap) {
+    headers.remove(CONTENT_LENGTH);
+    headers.remove("connect-accept-encoding");
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc+proto"),
+    );
+}
+
+/// the headers of a unary response, and its trailers sent as headers prefixed by `trailer-`
+fn split_trailers(all: HeaderMap) -> Result<(HeaderMap, HeaderMap), Status> {
+    let (mut headers, mut trailers) = (HeaderMap::new(), HeaderMap::new());
+    for (name, value) in &all {
+        match name.as_str().strip_prefix("trailer-") {
+            Some(trailer) => {
+                let trailer = HeaderName::from_bytes(trailer.as_bytes())
+                    .map_err(|e| Status::internal(format!("invalid connect trailer: {e}")))?;
+                trailers.append(trailer, value.clone());
+            }
+            None => {
+                headers.append(name, value.clone());
+            }
+        }
+    }
+    Ok((headers, trailers))
+}
+
+/// the compression flag and the message of the single grpc frame of a unary request
+fn split_frame(mut frame: Bytes) -> Result<(bool, Bytes), Status> {
+    if frame.len() < 5 {
+        return Err(Status::internal("unary request without message"));
+    }
+    let header = frame.split_to(5);
+    Ok((header[0] & COMPRESSED_FLAG != 0, frame))
+}
+
+/// `grpc-timeout`, a number followed by its unit, in milliseconds rounded up
+fn timeout_millis(value: &str) -> Option<u64> {
+    let unit = value.chars().last()?;
+    let amount: u64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    Some(match unit {
+        'H' => amount * 3_600_000,
+        'M' => amount * 60_000,
+        'S' => amount * 1000,
+        'm' => amount,
+        'u' => amount.div_ceil(1000),
+        'n' => amount.div_ceil(1_000_000),
+        _ => return None,
+    })
+}
+
+/// An error of the connect protocol, the body of a failed unary call or part of an end of stream.
+#[derive(Debug, Deserialize)]
+struct ConnectError {
+    code: String,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    details: Vec<ErrorDetail>,
+}
+
+/// a `google.protobuf.Any` in the connect error json
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    /// full name of the message type, without the `type.googleapis.com/` prefix of an `Any`
+    #[serde(rename = "type")]
+    type_name: String,
+    /// the message, base64 encoded
+    value: String,
+}
+
+impl ConnectError {
+    /// the error of a response whose body is not a connect error, like the one of a proxy
+    fn from_http_status(status: StatusCode) -> Self {
+        let code = match status {
+            StatusCode::BAD_REQUEST => "internal",
+            StatusCode::UNAUTHORIZED => "unauthenticated",
+            StatusCode::FORBIDDEN => "permission_denied",
+            StatusCode::NOT_FOUND => "unimplemented",
+            StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => "unavailable",
+            _ => "unknown",
+        };
+        Self {
+            code: code.to_string(),
+            message: format!("connect server answered with http status {status}"),
+            details: Vec::new(),
+        }
+    }
+
+    /// the status with its details encoded in a `google.rpc.Status`, like grpc sends them
+    fn into_status(self, metadata: HeaderMap) -> Result<Status, Status> {
+        let code = match self.code.as_str() {
+            // the only code spelled differently by connect
+            "canceled" => Code::Cancelled,
+            code => parse_code(code).unwrap_or(Code::Unknown),
+        };
+        let details = self
+            .details
+            .into_iter()
+            .map(|detail| {
+                let value = DETAILS_BASE64.decode(&detail.value).map_err(|e| {
+                    Status::internal(format!("invalid connect error detail value: {e}"))
+                })?;
+                Ok(Any {
+                    type_url: format!("type.googleapis.com/{}", detail.type_name),
+                    value,
+                })
+            })
+            .collect::<Result<Vec<_>, Status>>()?;
+        let details = match details.is_empty() {
+            true => Bytes::new(),
+            false => tonic_types::pb::Status {
+                code: code as i32,
+                message: self.message.clone(),
+                details,
+            }
+            .encode_to_vec()
+            .into(),
+        };
+        Ok(Status::with_details_and_metadata(
+            code,
+            self.message,
+            details,
+            MetadataMap::from_headers(metadata),
+        ))
+    }
+}
+
+/// the last envelope of a streamed response
+#[derive(Debug, Deserialize)]
+struct EndStream {
+    error: Option<ConnectError>,
+    #[serde(default)]
+    metadata: HashMap<String, Vec<String>>,
+}
+
+impl EndStream {
+    /// the trailers of grpc: the metadata, then the status
+    fn into_trailers(self) -> Result<HeaderMap, Status> {
+        let mut trailers = HeaderMap::new();
+        for (name, values) in self.metadata {
+            let invalid = || Status::internal(format!("invalid connect trailer '{name}'"));
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid())?;
+            for value in values {
+                let value = HeaderValue::from_str(&value).map_err(|_| invalid())?;
+                trailers.append(name.clone(), value);
+            }
+        }
+        match self.error {
+            Some(error) => error
+                .into_status(HeaderMap::new())?
+                .add_header(&mut trailers)?,
+            None => {
+                trailers.insert("grpc-status", HeaderValue::from(Code::Ok as i32));
+            }
+        }
+        Ok(trailers)
+    }
+}
+
+/// Turns the envelopes of a streamed connect response into grpc frames, then http trailers.
+///
+/// ```
+/// use grpc_client::connect::EnvelopeDecoder;
+/// use grpc_client::transport::FrameDecoder;
+/// let mut decoder = EnvelopeDecoder::default();
+/// let body = b"\x00\x00\x00\x00\x02\x08\x01\x02\x00\x00\x00\x1c{\"error\":{\"code\":\"aborted\"}}";
+/// let frames = decoder.decode(body).unwrap();
+/// assert_eq!(frames[0].data_ref().unwrap().as_ref(), b"\x00\x00\x00\x00\x02\x08\x01");
+/// assert_eq!(frames[1].trailers_ref().unwrap()["grpc-status"], "10");
+/// assert!(decoder.finish().is_ok());
+/// ```
+#[derive(Debug, Default)]
+pub struct EnvelopeDecoder {
+    /// bytes of the envelope being received
+    buffer: BytesMut,
+    ended: bool,
+}
+
+impl FrameDecoder for EnvelopeDecoder {
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<Frame<Bytes>>, Status> {
+        self.buffer.extend_from_slice(chunk);
+        let mut frames = Vec::new();
+        while self.buffer.len() >= 5 {
+            let length =
+                u32::from_be_bytes(self.buffer[1..5].try_into().expect("4 bytes")) as usize;
+            if self.buffer.len() < 5 + length {
+                break;
+            }
+            let envelope = self.buffer.split_to(5 + length).freeze();
+            let flags = envelope[0];
+            if flags & END_STREAM_FLAG == 0 {
+                frames.push(Frame::data(envelope));
+                continue;
+            }
+            if flags & COMPRESSED_FLAG != 0 {
+                return Err(Status::unimplemented(
+                    "compressed connect end of stream messages are not supported",
+                ));
+            }
+            let end: EndStream = serde_json::from_slice(&envelope[5..]).map_err(|e| {
+                Status::internal(format!("invalid connect end of stream message: {e}"))
+            })?;
+            frames.push(Frame::trailers(end.into_trailers()?));
+            self.ended = true;
+        }
+        Ok(frames)
+    }
+
+    fn finish(&self) -> Result<(), Status> {
+        match self.ended && self.buffer.is_empty() {
+            true => Ok(()),
+            false => Err(Status::internal(
+                "connect response ended before its end of stream message",
+            )),
+        }
+    }
+}
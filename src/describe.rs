@@ -0,0 +1,524 @@
+//! Find any fully qualified symbol in the descriptors and print its definition in proto syntax.
+//!
+//! Services, methods, messages, fields, oneofs, enums, enum values and extensions can be described,
+//! with their comments when the descriptors kept them, their options, reserved ranges and nested types.
+use std::fmt::{self, Write};
+
+use prost_reflect::prost_types::source_code_info::Location;
+use prost_reflect::{
+    Cardinality, DescriptorPool, DynamicMessage, EnumDescriptor, EnumValueDescriptor,
+    ExtensionDescriptor, FieldDescriptor, FileDescriptor, Kind, MessageDescriptor,
+    MethodDescriptor, OneofDescriptor, ServiceDescriptor, Syntax, Value,
+};
+
+/// highest field number, `max` in reserved and extension ranges
+const MAX_FIELD_NUMBER: u32 = 536_870_911;
+
+/// A definition found in a [`DescriptorPool`], printed in proto syntax by its `Display`
+///
+/// ```
+/// use grpc_client::describe::Symbol;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let symbol = Symbol::find(&pool, "google.rpc.RetryInfo").unwrap();
+/// assert_eq!(symbol.kind(), "message");
+/// assert!(symbol.to_string().contains("  .google.protobuf.Duration retry_delay = 1;\n"));
+/// let field = Symbol::find(&pool, "google.rpc.RetryInfo.retry_delay").unwrap();
+/// assert_eq!(field.kind(), "field");
+/// assert!(Symbol::find(&pool, "google.rpc.Missing").is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub enum Symbol {
+    Service(ServiceDescriptor),
+    Method(MethodDescriptor),
+    Message(MessageDescriptor),
+    Field(FieldDescriptor),
+    Oneof(OneofDescriptor),
+    Enum(EnumDescriptor),
+    EnumValue(EnumValueDescriptor),
+    Extension(ExtensionDescriptor),
+}
+
+impl Symbol {
+    /// look for a fully qualified name, with or without its leading dot
+    pub fn find(pool: &DescriptorPool, name: &str) -> Option<Symbol> {
+        let name = name.trim_start_matches('.');
+        if let Some(service) = pool.get_service_by_name(name) {
+            return Some(Symbol::Service(service));
+        }
+        if let Some(message) = pool.get_message_by_name(name) {
+            return Some(Symbol::Message(message));
+        }
+        if let Some(enum_desc) = pool.get_enum_by_name(name) {
+            return Some(Symbol::Enum(enum_desc));
+        }
+        if let Some(extension) = pool.get_extension_by_name(name) {
+            return Some(Symbol::Extension(extension));
+        }
+        let (parent, child) = name.rsplit_once('.')?;
+        if let Some(service) = pool.get_service_by_name(parent) {
+            return service
+                .methods()
+                .find(|method| method.name() == child)
+                .map(Symbol::Method);
+        }
+        if let Some(message) = pool.get_message_by_name(parent) {
+            if let Some(field) = message.get_field_by_name(child) {
+                return Some(Symbol::Field(field));
+            }
+            if let Some(oneof) = message.oneofs().find(|oneof| oneof.name() == child) {
+                return Some(Symbol::Oneof(oneof));
+            }
+        }
+        if let Some(enum_desc) = pool.get_enum_by_name(parent) {
+            return enum_desc.get_value_by_name(child).map(Symbol::EnumValue);
+        }
+        // enum values are scoped next to their enum in protobuf, not inside it
+        pool.all_enums()
+            .flat_map(|enum_desc| enum_desc.values().collect::<Vec<_>>())
+            .find(|value| value.full_name() == name)
+            .map(Symbol::EnumValue)
+    }
+
+    /// what the symbol is, ex: "message"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Symbol::Service(_) => "service",
+            Symbol::Method(_) => "method",
+            Symbol::Message(_) => "message",
+            Symbol::Field(_) => "field",
+            Symbol::Oneof(_) => "oneof",
+            Symbol::Enum(_) => "enum",
+            Symbol::EnumValue(_) => "enum value",
+            Symbol::Extension(_) => "extension",
+        }
+    }
+
+    pub fn full_name(&self) -> &str {
+        match self {
+            Symbol::Service(desc) => desc.full_name(),
+            Symbol::Method(desc) => desc.full_name(),
+            Symbol::Message(desc) => desc.full_name(),
+            Symbol::Field(desc) => desc.full_name(),
+            Symbol::Oneof(desc) => desc.full_name(),
+            Symbol::Enum(desc) => desc.full_name(),
+            Symbol::EnumValue(desc) => desc.full_name(),
+            Symbol::Extension(desc) => desc.full_name(),
+        }
+    }
+
+    /// the file defining the symbol
+    pub fn parent_file(&self) -> FileDescriptor {
+        match self {
+            Symbol::Service(desc) => desc.parent_file(),
+            Symbol::Method(desc) => desc.parent_file(),
+            Symbol::Message(desc) => desc.parent_file(),
+            Symbol::Field(desc) => desc.parent_file(),
+            Symbol::Oneof(desc) => desc.parent_file(),
+            Symbol::Enum(desc) => desc.parent_file(),
+            Symbol::EnumValue(desc) => desc.parent_file(),
+            Symbol::Extension(desc) => desc.parent_file(),
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut writer = ProtoWriter::default();
+        match self {
+            Symbol::Service(desc) => writer.service(desc),
+            Symbol::Method(desc) => writer.method(desc),
+            Symbol::Message(desc) => writer.message(desc),
+            Symbol::Field(desc) => writer.field(desc),
+            Symbol::Oneof(desc) => writer.oneof(desc),
+            Symbol::Enum(desc) => writer.enumeration(desc),
+            Symbol::EnumValue(desc) => writer.enum_value(desc),
+            Symbol::Extension(desc) => {
+                writer.extensions(&desc.containing_message(), std::slice::from_ref(desc))
+            }
+        }
+        f.write_str(&writer.out)
+    }
+}
+
+/// name of a field type as written in a proto file, messages and enums are fully qualified
+///
+/// ```
+/// use grpc_client::describe::type_name;
+/// use prost_reflect::Kind;
+/// assert_eq!(type_name(&Kind::Sint64), "sint64");
+/// ```
+pub fn type_name(kind: &Kind) -> String {
+    match kind {
+        Kind::Double => "double".to_string(),
+        Kind::Float => "float".to_string(),
+        Kind::Int32 => "int32".to_string(),
+        Kind::Int64 => "int64".to_string(),
+        Kind::Uint32 => "uint32".to_string(),
+        Kind::Uint64 => "uint64".to_string(),
+        Kind::Sint32 => "sint32".to_string(),
+        Kind::Sint64 => "sint64".to_string(),
+        Kind::Fixed32 => "fixed32".to_string(),
+        Kind::Fixed64 => "fixed64".to_string(),
+        Kind::Sfixed32 => "sfixed32".to_string(),
+        Kind::Sfixed64 => "sfixed64".to_string(),
+        Kind::Bool => "bool".to_string(),
+        Kind::String => "string".to_string(),
+        Kind::Bytes => "bytes".to_string(),
+        Kind::Message(desc) => format!(".{}", desc.full_name()),
+        Kind::Enum(desc) => format!(".{}", desc.full_name()),
+    }
+}
+
+/// `map<key, value>` for a map field, its label and type otherwise, ex: `repeated string`
+pub fn field_type(field: &FieldDescriptor) -> String {
+    if field.is_map()
+        && let Kind::Message(entry) = field.kind()
+    {
+        return format!(
+            "map<{}, {}>",
+            type_name(&entry.map_entry_key_field().kind()),
+            type_name(&entry.map_entry_value_field().kind())
+        );
+    }
+    let proto = field.field_descriptor_proto();
+    let label = match field.cardinality() {
+        Cardinality::Repeated => "repeated ",
+        Cardinality::Required => "required ",
+        // the fields of a oneof have no label, even in proto2
+        Cardinality::Optional
+            if field
+                .containing_oneof()
+                .is_some_and(|oneof| !oneof.is_synthetic()) =>
+        {
+            ""
+        }
+        Cardinality::Optional
+            if field.parent_file().syntax() == Syntax::Proto2 || proto.proto3_optional() =>
+        {
+            "optional "
+        }
+        Cardinality::Optional => "",
+    };
+    format!("{label}{}", type_name(&field.kind()))
+}
+
+/// `json_name` is always filled by protoc, it is only shown when it differs from the default one
+fn default_json_name(name: &str) -> String {
+    let mut json_name = String::new();
+    let mut upper = false;
+    for c in name.chars() {
+        match c {
+            '_' => upper = true,
+            c if upper => {
+                json_name.push(c.to_ascii_uppercase());
+                upper = false;
+            }
+            c => json_name.push(c),
+        }
+    }
+    json_name
+}
+
+/// each option set in an options message, as `(name, value)` written in proto syntax
+fn options(options: &DynamicMessage) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut push = |name: String, kind: Kind, value: &Value| match value {
+        Value::List(items) => entries.extend(
+            items
+                .iter()
+                .map(|item| (name.clone(), option_value(&kind, item))),
+        ),
+        value => entries.push((name, option_value(&kind, value))),
+    };
+    for (field, value) in options.fields() {
+        push(field.name().to_string(), field.kind(), value);
+    }
+    for (extension, value) in options.extensions() {
+        push(
+            format!("({})", extension.full_name()),
+            extension.kind(),
+            value,
+        );
+    }
+    entries
+}
+
+fn option_value(kind: &Kind, value: &Value) -> String {
+    match value {
+        Value::String(text) => format!("{text:?}"),
+        Value::Bytes(bytes) => format!("\"{}\"", bytes.escape_ascii()),
+        Value::EnumNumber(number) => kind
+            .as_enum()
+            .and_then(|desc| desc.get_value(*number))
+            .map(|value| value.name().to_string())
+            .unwrap_or_else(|| number.to_string()),
+        Value::Message(message) => format!("{{ {} }}", message.to_text_format()),
+        other => other.to_string(),
+    }
+}
+
+/// `n`, `n to m` or `n to max`
+fn range(start: i64, end_inclusive: i64, max: i64) -> String {
+    if start == end_inclusive {
+        start.to_string()
+    } else if end_inclusive >= max {
+        format!("{start} to max")
+    } else {
+        format!("{start} to {end_inclusive}")
+    }
+}
+
+#[derive(Default)]
+struct ProtoWriter {
+    out: String,
+    indent: usize,
+}
+
+impl ProtoWriter {
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{}{text}", "  ".repeat(self.indent));
+    }
+
+    /// leading comments of the element at `path` in its file, when the source info was kept
+    fn comments(&mut self, file: &FileDescriptor, path: &[i32]) {
+        let location: Option<&Location> = file
+            .file_descriptor_proto()
+            .source_code_info
+            .as_ref()
+            .and_then(|info| info.location.iter().find(|location| location.path == path));
+        let Some(comments) = location.and_then(|location| location.leading_comments.as_deref())
+        else {
+            return;
+        };
+        for line in comments.trim_end_matches('\n').split('\n') {
+            self.line(&format!("//{line}"));
+        }
+    }
+
+    /// `option name = value;` lines of a block
+    fn block_options(&mut self, options_message: &DynamicMessage) {
+        for (name, value) in options(options_message) {
+            self.line(&format!("option {name} = {value};"));
+        }
+    }
+
+    fn service(&mut self, service: &ServiceDescriptor) {
+        self.comments(&service.parent_file(), service.path());
+        self.line(&format!("service {} {{", service.name()));
+        self.indent += 1;
+        self.block_options(&service.options());
+        for method in service.methods() {
+            self.method(&method);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn method(&mut self, method: &MethodDescriptor) {
+        self.comments(&method.parent_file(), method.path());
+        let stream = |yes: bool| if yes { "stream " } else { "" };
+        let signature = format!(
+            "rpc {}({}.{}) returns ({}.{})",
+            method.name(),
+            stream(method.is_client_streaming()),
+            method.input().full_name(),
+            stream(method.is_server_streaming()),
+            method.output().full_name()
+        );
+        let options = options(&method.options());
+        if options.is_empty() {
+            self.line(&format!("{signature};"));
+            return;
+        }
+        self.line(&format!("{signature} {{"));
+        self.indent += 1;
+        for (name, value) in options {
+            self.line(&format!("option {name} = {value};"));
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn message(&mut self, message: &MessageDescriptor) {
+        self.comments(&message.parent_file(), message.path());
+        self.line(&format!("message {} {{", message.name()));
+        self.indent += 1;
+        self.block_options(&message.options());
+
+        let mut written_oneofs = Vec::new();
+        for field in message.fields() {
+            match field.containing_oneof() {
+                Some(oneof) if !oneof.is_synthetic() => {
+                    if !written_oneofs.contains(&oneof.name().to_string()) {
+                        written_oneofs.push(oneof.name().to_string());
+                        self.oneof(&oneof);
+                    }
+                }
+                _ => self.field(&field),
+            }
+        }
+        for child in message.child_messages().filter(|m| !m.is_map_entry()) {
+            self.message(&child);
+        }
+        for child in message.child_enums() {
+            self.enumeration(&child);
+        }
+        let extensions: Vec<ExtensionDescriptor> = message.child_extensions().collect();
+        let mut extendees: Vec<MessageDescriptor> = Vec::new();
+        for extension in &extensions {
+            if !extendees.contains(&extension.containing_message()) {
+                extendees.push(extension.containing_message());
+            }
+        }
+        for extendee in extendees {
+            let group: Vec<ExtensionDescriptor> = extensions
+                .iter()
+                .filter(|extension| extension.containing_message() == extendee)
+                .cloned()
+                .collect();
+            self.extensions(&extendee, &group);
+        }
+
+        let max = i64::from(MAX_FIELD_NUMBER);
+        let ranges = |ranges: Vec<std::ops::Range<u32>>| -> Vec<String> {
+            ranges
+                .into_iter()
+                .map(|r| range(i64::from(r.start), i64::from(r.end) - 1, max))
+                .collect()
+        };
+        let extension_ranges = ranges(message.extension_ranges().collect());
+        if !extension_ranges.is_empty() {
+            self.line(&format!("extensions {};", extension_ranges.join(", ")));
+        }
+        let reserved = ranges(message.reserved_ranges().collect());
+        if !reserved.is_empty() {
+            self.line(&format!("reserved {};", reserved.join(", ")));
+        }
+        self.reserved_names(message.reserved_names());
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn reserved_names<'a>(&mut self, names: impl Iterator<Item = &'a str>) {
+        let names: Vec<String> = names.map(|name| format!("{name:?}")).collect();
+        if !names.is_empty() {
+            self.line(&format!("reserved {};", names.join(", ")));
+        }
+    }
+
+    fn field(&mut self, field: &FieldDescriptor) {
+        self.comments(&field.parent_file(), field.path());
+        let proto = field.field_descriptor_proto();
+        let mut inline_options = Vec::new();
+        if let Some(default) = &proto.default_value {
+            let default = match field.kind() {
+                Kind::String | Kind::Bytes => format!("{default:?}"),
+                _ => default.clone(),
+            };
+            inline_options.push(format!("default = {default}"));
+        }
+        if field.json_name() != default_json_name(field.name()) {
+            inline_options.push(format!("json_name = {:?}", field.json_name()));
+        }
+        inline_options.extend(
+            options(&field.options())
+                .into_iter()
+                .map(|(name, value)| format!("{name} = {value}")),
+        );
+        self.line(&format!(
+            "{} {} = {}{};",
+            field_type(field),
+            field.name(),
+            field.number(),
+            inline(&inline_options)
+        ));
+    }
+
+    fn oneof(&mut self, oneof: &OneofDescriptor) {
+        self.comments(&oneof.parent_file(), oneof.path());
+        self.line(&format!("oneof {} {{", oneof.name()));
+        self.indent += 1;
+        self.block_options(&oneof.options());
+        for field in oneof.fields() {
+            self.field(&field);
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn enumeration(&mut self, enum_desc: &EnumDescriptor) {
+        self.comments(&enum_desc.parent_file(), enum_desc.path());
+        self.line(&format!("enum {} {{", enum_desc.name()));
+        self.indent += 1;
+        self.block_options(&enum_desc.options());
+        for value in enum_desc.values() {
+            self.enum_value(&value);
+        }
+        let reserved: Vec<String> = enum_desc
+            .reserved_ranges()
+            .map(|r| {
+                range(
+                    i64::from(*r.start()),
+                    i64::from(*r.end()),
+                    i64::from(i32::MAX),
+                )
+            })
+            .collect();
+        if !reserved.is_empty() {
+            self.line(&format!("reserved {};", reserved.join(", ")));
+        }
+        self.reserved_names(enum_desc.reserved_names());
+        self.indent -= 1;
+        self.line("}");
+    }
+
+    fn enum_value(&mut self, value: &EnumValueDescriptor) {
+        self.comments(&value.parent_file(), value.path());
+        let inline_options: Vec<String> = options(&value.options())
+            .into_iter()
+            .map(|(name, value)| format!("{name} = {value}"))
+            .collect();
+        self.line(&format!(
+            "{} = {}{};",
+            value.name(),
+            value.number(),
+            inline(&inline_options)
+        ));
+    }
+
+    /// an `extend` block of the extensions of one message
+    fn extensions(&mut self, extendee: &MessageDescriptor, extensions: &[ExtensionDescriptor]) {
+        self.line(&format!("extend .{} {{", extendee.full_name()));
+        self.indent += 1;
+        for extension in extensions {
+            self.comments(&extension.parent_file(), extension.path());
+            let inline_options: Vec<String> = options(&extension.options())
+                .into_iter()
+                .map(|(name, value)| format!("{name} = {value}"))
+                .collect();
+            let label = match extension.cardinality() {
+                Cardinality::Repeated => "repeated ",
+                _ if extension.parent_file().syntax() == Syntax::Proto2 => "optional ",
+                _ => "",
+            };
+            self.line(&format!(
+                "{label}{} {} = {}{};",
+                type_name(&extension.kind()),
+                extension.name(),
+                extension.number(),
+                inline(&inline_options)
+            ));
+        }
+        self.indent -= 1;
+        self.line("}");
+    }
+}
+
+/// ` [a = 1, b = 2]` after a field or an enum value, nothing without options
+fn inline(options: &[String]) -> String {
+    match options.is_empty() {
+        true => String::new(),
+        false => format!(" [{}]", options.join(", ")),
+    }
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use prost_reflect::DescriptorPool;
+
+use crate::client::GrpcClientError;
+
+/// Where the protobuf descriptors come from.
+///
+/// Reflection needs a [`crate::Client`] connected to the server, see [`crate::Client::descriptor_pool`].
+/// Protosets and proto sources are loaded locally, without any network.
+#[derive(Debug, Clone, Default)]
+pub enum DescriptorSource {
+    /// ask the server with the reflection api
+    #[default]
+    Reflection,
+    /// `FileDescriptorSet` files, as written by `protoc --descriptor_set_out --include_imports`
+    Protoset(Vec<PathBuf>),
+    /// `.proto` files compiled on the fly, imports are searched in `import_paths`
+    Protos {
+        files: Vec<PathBuf>,
+        import_paths: Vec<PathBuf>,
+    },
+}
+
+impl DescriptorSource {
+    /// build the descriptor pool of a local source, None for reflection
+    pub fn load_local(&self) -> Option<Result<DescriptorPool, GrpcClientError>> {
+        match self {
+            DescriptorSource::Reflection => None,
+            DescriptorSource::Protoset(paths) => Some(load_protosets(paths)),
+            DescriptorSource::Protos {
+                files,
+                import_paths,
+            } => Some(compile_protos(files, import_paths)),
+        }
+    }
+}
+
+/// decode and merge `FileDescriptorSet` files
+pub fn load_protosets(paths: &[PathBuf]) -> Result<DescriptorPool, GrpcClientError> {
+    let mut pool = DescriptorPool::new();
+    for path in paths {
+        let bytes = std::fs::read(path).map_err(|e| {
+            GrpcClientError::DescriptorLoadError(format!("{}: {e}", path.display()))
+        })?;
+        pool.decode_file_descriptor_set(bytes.as_slice())?;
+    }
+    Ok(pool)
+}
+
+/// compile `.proto` files, with their comments kept for the documentation
+pub fn compile_protos(
+    files: &[PathBuf],
+    import_paths: &[PathBuf],
+) -> Result<DescriptorPool, GrpcClientError> {
+    // the directory of each file is a natural import path when none is given
+    let import_paths: Vec<PathBuf> = if import_paths.is_empty() {
+        files
+            .iter()
+            .map(|file| match file.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            })
+            .collect()
+    } else {
+        import_paths.to_vec()
+    };
+    let mut compiler = protox::Compiler::new(import_paths)
+        .map_err(|e| GrpcClientError::DescriptorLoadError(e.to_string()))?;
+    compiler.include_imports(true).include_source_info(true);
+    compiler
+        .open_files(files)
+        .map_err(|e| GrpcClientError::DescriptorLoadError(e.to_string()))?;
+    Ok(compiler.descriptor_pool())
+}
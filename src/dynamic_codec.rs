@@ -1,8 +1,12 @@
+use bytes::BufMut;
 use prost::Message;
 
-use prost_reflect::{DescriptorPool, DynamicMessage};
+use prost_reflect::{DescriptorPool, DeserializeOptions, DynamicMessage};
 use tonic::Status;
 use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf};
+use tracing::log::warn;
+
+use crate::raw_decode::raw_message_descriptor;
 
 /// A tonic Codec that can dynamically encode/decode gRPC messages using reflection.
 #[derive(Clone)]
@@ -11,6 +15,8 @@ pub struct DynamicCodec {
     pub pool: DescriptorPool,
     /// The fully-qualified name of the message type to decode.
     pub message_name: String,
+    /// Messages in the protobuf json mapping instead of binary, for connect with json.
+    pub json: bool,
 }
 
 impl Codec for DynamicCodec {
@@ -20,26 +26,33 @@ impl Codec for DynamicCodec {
     type Decoder = DynamicDecoder;
 
     fn encoder(&mut self) -> Self::Encoder {
-        DynamicEncoder
+        DynamicEncoder { json: self.json }
     }
 
     fn decoder(&mut self) -> Self::Decoder {
         DynamicDecoder {
             pool: self.pool.clone(),
             message_name: self.message_name.clone(),
+            json: self.json,
         }
     }
 }
 
 /// Encoder for DynamicMessage
 #[derive(Clone)]
-pub struct DynamicEncoder;
+pub struct DynamicEncoder {
+    pub json: bool,
+}
 
 impl tonic::codec::Encoder for DynamicEncoder {
     type Item = DynamicMessage;
     type Error = Status;
 
     fn encode(&mut self, item: Self::Item, buf: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        if self.json {
+            return serde_json::to_writer(buf.writer(), &item)
+                .map_err(|e| Status::internal(format!("json encode error: {e}")));
+        }
         // Use prost::Message::encode directly into the provided buffer
         item.encode(buf)
             .map_err(|e| Status::internal(format!("encode error: {e}")))
@@ -51,6 +64,7 @@ impl tonic::codec::Encoder for DynamicEncoder {
 pub struct DynamicDecoder {
     pub pool: DescriptorPool,
     pub message_name: String,
+    pub json: bool,
 }
 
 impl Decoder for DynamicDecoder {
@@ -60,22 +74,29 @@ impl Decoder for DynamicDecoder {
     fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
         use bytes::Buf;
 
-        // If there’s no data, we can’t decode yet.
-        if !buf.has_remaining() {
-            return Ok(None);
-        }
-
-        let desc = self
-            .pool
-            .get_message_by_name(&self.message_name)
-            .ok_or_else(|| {
-                Status::internal(format!(
-                    "message '{}' not found in pool",
+        // tonic only calls the decoder with a whole frame, an empty one is an empty message
+        // without the type, every field is kept as an unknown field to be decoded raw
+        let desc = match self.pool.get_message_by_name(&self.message_name) {
+            Some(desc) => desc,
+            None => {
+                warn!(
+                    "message '{}' not found in pool, decoding it raw",
                     &self.message_name
-                ))
-            })?;
+                );
+                raw_message_descriptor()
+            }
+        };
 
         let bytes = buf.copy_to_bytes(buf.remaining());
+        if self.json {
+            // fields added to the server since the descriptors were fetched are left out
+            let options = DeserializeOptions::new().deny_unknown_fields(false);
+            let mut deserializer = serde_json::Deserializer::from_slice(&bytes);
+            let msg = DynamicMessage::deserialize_with_options(desc, &mut deserializer, &options)
+                .and_then(|msg| deserializer.end().map(|()| msg))
+                .map_err(|e| Status::internal(format!("json decode error: {e}")))?;
+            return Ok(Some(msg));
+        }
         let msg = DynamicMessage::decode(desc, bytes)
             .map_err(|e| Status::internal(format!("decode error: {e}")))?;
 
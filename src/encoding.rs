@@ -0,0 +1,175 @@
+//! Convert messages between json, protobuf text format and their serialized bytes, without any call.
+use std::fmt;
+use std::str::FromStr;
+
+use base64::prelude::{BASE64_STANDARD, Engine};
+use prost::Message;
+use prost_reflect::{DeserializeOptions, DynamicMessage, MessageDescriptor};
+
+use crate::client::GrpcClientError;
+
+/// How serialized protobuf bytes are written or read
+///
+/// ```
+/// use grpc_client::encoding::BytesEncoding;
+/// let hex = BytesEncoding::Hex.encode(&[8, 150, 1]);
+/// assert_eq!(hex, b"089601");
+/// assert_eq!(BytesEncoding::Hex.decode(&hex).unwrap(), vec![8, 150, 1]);
+/// assert_eq!(BytesEncoding::Base64.decode(b"CJYB\n").unwrap(), vec![8, 150, 1]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BytesEncoding {
+    /// raw bytes
+    #[default]
+    Binary,
+    /// standard base64 with padding
+    Base64,
+    /// lowercase hexadecimal
+    Hex,
+}
+
+impl BytesEncoding {
+    pub const ALL: [BytesEncoding; 3] = [
+        BytesEncoding::Binary,
+        BytesEncoding::Base64,
+        BytesEncoding::Hex,
+    ];
+
+    pub fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            BytesEncoding::Binary => bytes.to_vec(),
+            BytesEncoding::Base64 => BASE64_STANDARD.encode(bytes).into_bytes(),
+            BytesEncoding::Hex => bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+                .into_bytes(),
+        }
+    }
+
+    /// decode the input, surrounding whitespaces of textual encodings are ignored
+    pub fn decode(self, input: &[u8]) -> Result<Vec<u8>, GrpcClientError> {
+        let text = || {
+            std::str::from_utf8(input).map(str::trim).map_err(|e| {
+                GrpcClientError::InvalidInput(format!("{self} input is not text: {e}"))
+            })
+        };
+        match self {
+            BytesEncoding::Binary => Ok(input.to_vec()),
+            BytesEncoding::Base64 => BASE64_STANDARD
+                .decode(text()?)
+                .map_err(|e| GrpcClientError::InvalidInput(format!("invalid base64: {e}"))),
+            BytesEncoding::Hex => {
+                let text = text()?;
+                if !text.is_ascii() {
+                    return Err(GrpcClientError::InvalidInput(
+                        "invalid hex: non ascii character".to_string(),
+                    ));
+                }
+                if text.len() % 2 != 0 {
+                    return Err(GrpcClientError::InvalidInput(
+                        "invalid hex: odd number of digits".to_string(),
+                    ));
+                }
+                (0..text.len())
+                    .step_by(2)
+                    .map(|i| {
+                        u8::from_str_radix(&text[i..i + 2], 16).map_err(|e| {
+                            GrpcClientError::InvalidInput(format!("invalid hex at {i}: {e}"))
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+impl fmt::Display for BytesEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BytesEncoding::Binary => "binary",
+            BytesEncoding::Base64 => "base64",
+            BytesEncoding::Hex => "hex",
+        })
+    }
+}
+
+impl FromStr for BytesEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BytesEncoding::ALL
+            .into_iter()
+            .find(|encoding| encoding.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown encoding `{s}`, use binary, base64 or hex"))
+    }
+}
+
+/// Textual representation of a message given as input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MessageSyntax {
+    /// protobuf json mapping
+    #[default]
+    Json,
+    /// protobuf text format
+    Text,
+}
+
+impl fmt::Display for MessageSyntax {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MessageSyntax::Json => "json",
+            MessageSyntax::Text => "text",
+        })
+    }
+}
+
+impl FromStr for MessageSyntax {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(MessageSyntax::Json),
+            "text" => Ok(MessageSyntax::Text),
+            _ => Err(format!("unknown message syntax `{s}`, use json or text")),
+        }
+    }
+}
+
+/// parse a message written in json or protobuf text format, unknown fields are an error
+pub fn parse_message(
+    desc: MessageDescriptor,
+    input: &str,
+    syntax: MessageSyntax,
+) -> Result<DynamicMessage, GrpcClientError> {
+    match syntax {
+        MessageSyntax::Json => {
+            let mut deserializer = serde_json::Deserializer::from_str(input);
+            let message = DynamicMessage::deserialize_with_options(
+                desc,
+                &mut deserializer,
+                &DeserializeOptions::new(),
+            )
+            .and_then(|message| deserializer.end().map(|_| message))
+            .map_err(|e| GrpcClientError::InvalidInput(format!("invalid json message: {e}")))?;
+            Ok(message)
+        }
+        MessageSyntax::Text => DynamicMessage::parse_text_format(desc, input)
+            .map_err(|e| GrpcClientError::InvalidInput(format!("invalid text message: {e}"))),
+    }
+}
+
+/// serialize a message, then write it with the given encoding
+pub fn encode_message(message: &DynamicMessage, encoding: BytesEncoding) -> Vec<u8> {
+    encoding.encode(&message.encode_to_vec())
+}
+
+/// read bytes with the given encoding, then deserialize them as a message of type `desc`
+pub fn decode_message(
+    desc: MessageDescriptor,
+    input: &[u8],
+    encoding: BytesEncoding,
+) -> Result<DynamicMessage, GrpcClientError> {
+    let bytes = encoding.decode(input)?;
+    Ok(DynamicMessage::decode(desc, bytes.as_slice())?)
+}
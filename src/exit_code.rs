@@ -0,0 +1,91 @@
+//! Process exit codes used by the command line, so scripts can tell errors apart, see [`EXIT_CODES`].
+//!
+//! A local timeout is reported as the `DEADLINE_EXCEEDED` status, so with the exit code 68.
+use tonic::Code;
+
+use crate::client::GrpcClientError;
+
+pub const SUCCESS: i32 = 0;
+pub const UNEXPECTED: i32 = 1;
+pub const USAGE: i32 = 2;
+pub const CONNECTION: i32 = 3;
+pub const DESCRIPTOR: i32 = 4;
+/// the health check answered, but with another status than `SERVING`
+pub const NOT_SERVING: i32 = 5;
+/// exit codes of grpc status start from here
+pub const STATUS_BASE: i32 = 64;
+
+/// every exit code with its meaning, as listed by `--help` and the README
+pub const EXIT_CODES: &[(&str, &str)] = &[
+    ("0", "success"),
+    ("1", "unexpected error (output formatting, io...)"),
+    (
+        "2",
+        "bad usage: invalid argument, unknown service, method or field",
+    ),
+    ("3", "connection to the server failed"),
+    (
+        "4",
+        "descriptor error: reflection data missing, invalid or not decodable",
+    ),
+    ("5", "health check: the service answered but is not serving"),
+    (
+        "64 + code",
+        "the server answered with the grpc status `code`, ex: 69 for NOT_FOUND, 78 for UNAVAILABLE",
+    ),
+];
+
+/// the exit codes, as shown at the end of `--help`
+///
+/// ```
+/// use grpc_client::exit_code::help;
+/// assert!(help().starts_with("Exit codes:\n  0          success\n"));
+/// ```
+pub fn help() -> String {
+    let mut help = "Exit codes:".to_string();
+    for (code, meaning) in EXIT_CODES {
+        help.push_str(&format!("\n  {code:<10} {meaning}"));
+    }
+    help
+}
+
+/// exit code of a call that ended with this grpc status code
+///
+/// ```
+/// use grpc_client::exit_code::for_status_code;
+/// assert_eq!(for_status_code(tonic::Code::NotFound), 69);
+/// assert_eq!(for_status_code(tonic::Code::Unavailable), 78);
+/// assert_eq!(for_status_code(tonic::Code::Ok), 0);
+/// ```
+pub fn for_status_code(code: Code) -> i32 {
+    match code {
+        Code::Ok => SUCCESS,
+        code => STATUS_BASE + code as i32,
+    }
+}
+
+/// exit code matching an error of the library
+pub fn for_error(error: &GrpcClientError) -> i32 {
+    match error {
+        GrpcClientError::ReflectionRequestError(status)
+        | GrpcClientError::StatusWithDetails(status, _) => for_status_code(status.code()),
+        GrpcClientError::DeadlineExceeded(_) => for_status_code(Code::DeadlineExceeded),
+        GrpcClientError::GrpcClientCreationError(_) | GrpcClientError::ConnectionFailed(_) => {
+            CONNECTION
+        }
+        GrpcClientError::ParamError(_, _)
+        | GrpcClientError::InvalidFieldPath(_, _)
+        | GrpcClientError::InvalidFilter(_, _)
+        | GrpcClientError::NotFoundError(_)
+        | GrpcClientError::InvalidMetadata(_)
+        | GrpcClientError::InvalidInput(_)
+        | GrpcClientError::UriError(_) => USAGE,
+        GrpcClientError::EmptyResponse(_)
+        | GrpcClientError::DecodeError(_)
+        | GrpcClientError::BadMessageType(_)
+        | GrpcClientError::DescriptorError(_)
+        | GrpcClientError::DescriptorLoadError(_) => DESCRIPTOR,
+        GrpcClientError::NotServing(_) => NOT_SERVING,
+        GrpcClientError::ServerError(_) => UNEXPECTED,
+    }
+}
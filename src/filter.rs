@@ -0,0 +1,396 @@
+//! Select which services and methods are listed.
+//!
+//! Each filter is a string with this grammar:
+//!
+//! ```text
+//! filter  = [ "!" ] part [ "," part [ "," part ] ]
+//! part    = "" | glob | "/" regex "/"
+//! glob    = any text, "*" matches any characters (dots included) and "?" a single one
+//! ```
+//!
+//! - with a single part, the pattern is matched against the package, the service and the method
+//!   of each method, so `helloworld` keeps a whole package, `*.Admin*` every admin service and
+//!   `Get*` every method starting with Get.
+//! - with several parts, they are matched in order against the package, the service and the method.
+//!   A missing or empty part matches anything: `,Greeter` keeps the `Greeter` service of any package.
+//! - services and methods are matched by their fully qualified name (`helloworld.Greeter.SayHello`)
+//!   or by their short name (`SayHello`). Packages only have their fully qualified name.
+//! - patterns match the whole name, a regex is written between slashes: `/Say(Hello|Goodbye)/`.
+//! - a filter starting with `!` excludes what it matches.
+//!
+//! A method is kept when it matches one of the include filters, or when there is none, and it
+//! matches no exclude filter. A service is kept when one of its methods is kept, a file when one of
+//! its services is kept. Without any include filter, the reflection service itself is hidden.
+//!
+//! On top of these filters, [`GrpcFilters`] can require every kept method to match predicates:
+//! the path of its proto file, its package, its input or output type, how it streams and whether
+//! it is deprecated.
+use std::fmt;
+use std::str::FromStr;
+
+use prost_reflect::{FileDescriptor, MethodDescriptor, ServiceDescriptor};
+use regex::Regex;
+
+use crate::client::GrpcClientError;
+
+/// A glob or a regex matching a whole name, see the [module documentation](self).
+///
+/// ```
+/// use grpc_client::filter::Pattern;
+/// let glob: Pattern = "Get*".parse().unwrap();
+/// assert!(glob.matches("GetProduct"));
+/// assert!(!glob.matches("ForgetProduct"));
+/// let regex: Pattern = "/(Get|List)Products?/".parse().unwrap();
+/// assert!(regex.matches("ListProducts"));
+/// assert!("/(unclosed/".parse::<Pattern>().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    /// None for an empty pattern, which matches anything
+    regex: Option<Regex>,
+}
+
+impl Pattern {
+    pub fn matches(&self, name: &str) -> bool {
+        self.regex.as_ref().is_none_or(|regex| regex.is_match(name))
+    }
+    /// the pattern of a fully qualified symbol, tried on the full name, then on the last part of it
+    pub fn matches_symbol(&self, full_name: &str) -> bool {
+        let short_name = full_name.rsplit('.').next().unwrap_or(full_name);
+        self.matches(full_name) || self.matches(short_name)
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = GrpcClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let regex = if s.is_empty() {
+            None
+        } else if let Some(regex) = s.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')) {
+            Some(regex.to_string())
+        } else {
+            let glob: Vec<String> = s
+                .split('*')
+                .map(|chunk| {
+                    chunk
+                        .split('?')
+                        .map(regex::escape)
+                        .collect::<Vec<_>>()
+                        .join(".")
+                })
+                .collect();
+            Some(glob.join(".*"))
+        };
+        let regex = regex
+            .map(|regex| Regex::new(&format!("^(?:{regex})$")))
+            .transpose()
+            .map_err(|e| GrpcClientError::InvalidFilter(s.to_string(), e.to_string()))?;
+        Ok(Pattern {
+            source: s.to_string(),
+            regex,
+        })
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// One filter, see the [module documentation](self) for its syntax.
+///
+/// ```
+/// use grpc_client::filter::GrpcFilter;
+/// let filter: GrpcFilter = "!helloworld,,/Say.*/".parse().unwrap();
+/// assert!(filter.exclude);
+/// // an empty part matches anything
+/// assert!(filter.service.unwrap().matches("Greeter"));
+/// assert_eq!(filter.method.unwrap().to_string(), "/Say.*/");
+/// ```
+#[derive(Debug, Clone)]
+pub struct GrpcFilter {
+    /// the filter removes what it matches
+    pub exclude: bool,
+    /// matched against the package, or against every name for a single part filter
+    pub package: Option<Pattern>,
+    pub service: Option<Pattern>,
+    pub method: Option<Pattern>,
+}
+
+impl GrpcFilter {
+    /// true if the method is selected by the filter, whether it includes or excludes it
+    pub fn matches(&self, method: &MethodDescriptor) -> bool {
+        let service = method.parent_service();
+        let package = service.package_name();
+        let any = |pattern: &Option<Pattern>, matches: &dyn Fn(&Pattern) -> bool| {
+            pattern.as_ref().is_none_or(matches)
+        };
+        if self.service.is_none() && self.method.is_none() {
+            // a single part is tried on every name of the method
+            return any(&self.package, &|p| {
+                p.matches(package)
+                    || p.matches_symbol(service.full_name())
+                    || p.matches_symbol(method.full_name())
+            });
+        }
+        any(&self.package, &|p| p.matches(package))
+            && any(&self.service, &|p| p.matches_symbol(service.full_name()))
+            && any(&self.method, &|p| p.matches_symbol(method.full_name()))
+    }
+}
+
+impl FromStr for GrpcFilter {
+    type Err = GrpcClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (exclude, rest) = match s.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let parts = split_parts(rest);
+        if parts.len() > 3 {
+            return Err(GrpcClientError::InvalidFilter(
+                s.to_string(),
+                "at most 3 parts: package,service,method".to_string(),
+            ));
+        }
+        let mut parts = parts.into_iter().map(|part| part.parse::<Pattern>());
+        Ok(GrpcFilter {
+            exclude,
+            package: parts.next().transpose()?,
+            service: parts.next().transpose()?,
+            method: parts.next().transpose()?,
+        })
+    }
+}
+
+/// split on commas, except inside a `/regex/` part
+fn split_parts(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut in_regex = false;
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_regex => escaped = true,
+            '/' if i == start => in_regex = true,
+            '/' if in_regex => in_regex = false,
+            ',' if !in_regex => {
+                parts.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Which methods stream their messages
+///
+/// ```
+/// use grpc_client::filter::Streaming;
+/// assert_eq!("server".parse::<Streaming>(), Ok(Streaming::Server));
+/// assert!("sideways".parse::<Streaming>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Streaming {
+    /// a single request and a single response
+    Unary,
+    /// a stream of requests, bidirectional methods included
+    Client,
+    /// a stream of responses, bidirectional methods included
+    Server,
+    /// a stream in both directions
+    Bidi,
+    /// a stream in any direction
+    Any,
+}
+
+impl Streaming {
+    pub const ALL: [Streaming; 5] = [
+        Streaming::Unary,
+        Streaming::Client,
+        Streaming::Server,
+        Streaming::Bidi,
+        Streaming::Any,
+    ];
+
+    pub fn matches(self, method: &MethodDescriptor) -> bool {
+        let (client, server) = (method.is_client_streaming(), method.is_server_streaming());
+        match self {
+            Streaming::Unary => !client && !server,
+            Streaming::Client => client,
+            Streaming::Server => server,
+            Streaming::Bidi => client && server,
+            Streaming::Any => client || server,
+        }
+    }
+}
+
+impl fmt::Display for Streaming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Streaming::Unary => "unary",
+            Streaming::Client => "client",
+            Streaming::Server => "server",
+            Streaming::Bidi => "bidi",
+            Streaming::Any => "any",
+        })
+    }
+}
+
+impl FromStr for Streaming {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Streaming::ALL
+            .into_iter()
+            .find(|streaming| streaming.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown streaming `{s}`, use unary, client, server, bidi or any")
+            })
+    }
+}
+
+/// true if the method, its service or its file is marked deprecated
+pub fn is_deprecated(method: &MethodDescriptor) -> bool {
+    let method_options = method.method_descriptor_proto().options.as_ref();
+    method_options.and_then(|o| o.deprecated).unwrap_or(false)
+        || is_service_deprecated(method.parent_service())
+}
+
+/// true if the service or its file is marked deprecated
+pub fn is_service_deprecated(service: &ServiceDescriptor) -> bool {
+    let file = service.parent_file();
+    let service_options = service.service_descriptor_proto().options.as_ref();
+    let file_options = file.file_descriptor_proto().options.as_ref();
+    service_options.and_then(|o| o.deprecated).unwrap_or(false)
+        || file_options.and_then(|o| o.deprecated).unwrap_or(false)
+}
+
+/// Every filter given to the listing, and the predicates every kept method must match,
+/// see the [module documentation](self) for their meaning.
+///
+/// ```
+/// use grpc_client::GrpcFilters;
+/// use grpc_client::filter::Streaming;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET).unwrap();
+/// let service = pool.get_service_by_name("grpc.reflection.v1.ServerReflection").unwrap();
+/// // the reflection service is hidden by default
+/// assert!(GrpcFilters::new_empty().filter_service(&service));
+/// let filters = GrpcFilters::new(vec!["grpc.reflection.*".to_string()]).unwrap();
+/// assert!(!filters.filter_service(&service));
+/// assert!(!filters.clone().streaming(Streaming::Bidi).filter_service(&service));
+/// assert!(filters.streaming(Streaming::Unary).filter_service(&service));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GrpcFilters {
+    filters: Vec<GrpcFilter>,
+    file: Option<Pattern>,
+    package: Option<Pattern>,
+    input_type: Option<Pattern>,
+    output_type: Option<Pattern>,
+    streaming: Option<Streaming>,
+    deprecated: Option<bool>,
+}
+
+impl GrpcFilters {
+    /// parse every filter, an invalid one is an error
+    pub fn new(input: Vec<String>) -> Result<GrpcFilters, GrpcClientError> {
+        let filters = input
+            .iter()
+            .map(|filter| filter.parse())
+            .collect::<Result<_, _>>()?;
+        Ok(GrpcFilters {
+            filters,
+            ..Default::default()
+        })
+    }
+    pub fn new_empty() -> GrpcFilters {
+        GrpcFilters::default()
+    }
+    pub fn filters(&self) -> &[GrpcFilter] {
+        &self.filters
+    }
+    /// only keep the methods defined in a file whose path matches, ex: `admin/*.proto`
+    pub fn file(mut self, pattern: Pattern) -> Self {
+        self.file = Some(pattern);
+        self
+    }
+    /// only keep the methods of a package matching the pattern
+    pub fn package(mut self, pattern: Pattern) -> Self {
+        self.package = Some(pattern);
+        self
+    }
+    /// only keep the methods taking a message whose name matches
+    pub fn input_type(mut self, pattern: Pattern) -> Self {
+        self.input_type = Some(pattern);
+        self
+    }
+    /// only keep the methods returning a message whose name matches
+    pub fn output_type(mut self, pattern: Pattern) -> Self {
+        self.output_type = Some(pattern);
+        self
+    }
+    pub fn streaming(mut self, streaming: Streaming) -> Self {
+        self.streaming = Some(streaming);
+        self
+    }
+    /// only keep the deprecated methods, or only the other ones, see [`is_deprecated`]
+    pub fn deprecated(mut self, deprecated: bool) -> Self {
+        self.deprecated = Some(deprecated);
+        self
+    }
+
+    /// return true if every service of the file is filtered out
+    pub fn filter_file(&self, file: &FileDescriptor) -> bool {
+        file.services().all(|service| self.filter_service(&service))
+    }
+    /// return true if every method of the service is filtered out
+    pub fn filter_service(&self, service: &ServiceDescriptor) -> bool {
+        service.methods().all(|method| self.filter_method(&method))
+    }
+    /// return true if the method fails a predicate, is excluded,
+    /// or is not included when there are include filters
+    pub fn filter_method(&self, method: &MethodDescriptor) -> bool {
+        if !self.matches_predicates(method) {
+            return true;
+        }
+        let (excludes, includes): (Vec<&GrpcFilter>, Vec<&GrpcFilter>) =
+            self.filters.iter().partition(|filter| filter.exclude);
+        if excludes.iter().any(|filter| filter.matches(method)) {
+            return true;
+        }
+        if includes.is_empty() {
+            return method
+                .parent_service()
+                .package_name()
+                .starts_with("grpc.reflection.v");
+        }
+        !includes.iter().any(|filter| filter.matches(method))
+    }
+    fn matches_predicates(&self, method: &MethodDescriptor) -> bool {
+        let holds = |pattern: &Option<Pattern>, name: &str, symbol: bool| {
+            pattern.as_ref().is_none_or(|p| match symbol {
+                true => p.matches_symbol(name),
+                false => p.matches(name),
+            })
+        };
+        holds(&self.file, method.parent_file().name(), false)
+            && holds(&self.package, method.parent_file().package_name(), false)
+            && holds(&self.input_type, method.input().full_name(), true)
+            && holds(&self.output_type, method.output().full_name(), true)
+            && self
+                .streaming
+                .is_none_or(|streaming| streaming.matches(method))
+            && self
+                .deprecated
+                .is_none_or(|deprecated| deprecated == is_deprecated(method))
+    }
+}
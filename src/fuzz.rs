@@ -0,0 +1,207 @@
+//! Fuzzing: random requests sent to a method, one after the other, to find the ones the server fails on.
+//!
+//! The requests come from a [`MessageGenerator`] seeded with [`FuzzReport::seed`], so running again
+//! with that seed sends the same requests. A [`Finding`] is a call ended by an `INTERNAL` or `UNKNOWN`
+//! status, or by the connection to the server dropping: `UNAVAILABLE` or a connection error.
+use std::collections::BTreeMap;
+use std::fmt;
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use prost::Message;
+use prost_reflect::DynamicMessage;
+use rand::RngExt;
+use serde::Serialize;
+use tonic::Code;
+
+use crate::call_options::CallOptions;
+use crate::client::{Client, GrpcClientError};
+use crate::exit_code;
+use crate::random_message::{DEFAULT_MAX_DEPTH, DEFAULT_MAX_ENTRIES, MessageGenerator};
+
+/// number of requests sent when none is given
+pub const DEFAULT_REQUESTS: u64 = 100;
+
+/// How many requests to send and how they are generated.
+///
+/// ```
+/// use grpc_client::fuzz::FuzzConfig;
+/// let config = FuzzConfig::default().requests(10).seed(42).max_depth(2);
+/// assert_eq!(config.requests, 10);
+/// assert_eq!(config.seed, Some(42));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FuzzConfig {
+    pub requests: u64,
+    /// a random seed is picked when none is given
+    pub seed: Option<u64>,
+    /// see [`MessageGenerator::max_depth`]
+    pub max_depth: usize,
+    /// see [`MessageGenerator::max_entries`]
+    pub max_entries: usize,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            requests: DEFAULT_REQUESTS,
+            seed: None,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+}
+
+impl FuzzConfig {
+    pub fn requests(mut self, requests: u64) -> Self {
+        self.requests = requests;
+        self
+    }
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+}
+
+/// A request the server failed on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    /// position of the request among the ones generated with the seed, from 0
+    pub index: u64,
+    /// the request, in the protobuf json mapping, or its protobuf encoding in base64 when it has
+    /// none, like an `Any` whose type is not in the descriptors
+    pub request: serde_json::Value,
+    /// name of the status code, or `connection` when the call did not get one
+    pub status: String,
+    pub message: String,
+}
+
+/// Outcome of a fuzzing run.
+#[derive(Debug, Serialize)]
+pub struct FuzzReport {
+    /// seed of the requests, to send them again
+    pub seed: u64,
+    pub requests: u64,
+    /// number of calls per status code name
+    pub status_codes: BTreeMap<String, u64>,
+    pub findings: Vec<Finding>,
+    /// error of the first finding
+    #[serde(skip)]
+    pub first_error: Option<GrpcClientError>,
+}
+
+impl fmt::Display for FuzzReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Requests:  {} (seed {})", self.requests, self.seed)?;
+        writeln!(f, "Findings:  {}", self.findings.len())?;
+        writeln!(f, "Status codes:")?;
+        for (status, count) in &self.status_codes {
+            writeln!(f, "  {status:<20} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+/// whether the server failed on the request, rather than rejected it
+///
+/// ```
+/// use grpc_client::client::GrpcClientError;
+/// use grpc_client::fuzz::is_finding;
+/// assert!(is_finding(&GrpcClientError::from_status(tonic::Status::internal("panic"), None)));
+/// assert!(!is_finding(&GrpcClientError::from_status(tonic::Status::invalid_argument("id"), None)));
+/// assert!(is_finding(&GrpcClientError::ConnectionFailed("reset".to_string())));
+/// ```
+pub fn is_finding(error: &GrpcClientError) -> bool {
+    match error.status() {
+        Some(status) => matches!(
+            status.code(),
+            Code::Internal | Code::Unknown | Code::Unavailable
+        ),
+        None => error.exit_code() == exit_code::CONNECTION,
+    }
+}
+
+/// Send `config.requests` random requests to the method, `found` is given each finding as soon as it is known.
+///
+/// Errors of usage, like an unknown method, stop the run and are returned.
+///
+/// ```no_run
+/// # tokio_test::block_on(async {
+/// # use grpc_client::{CallOptions, Client};
+/// use grpc_client::fuzz::{FuzzConfig, run};
+/// let mut client = Client::new("https://localhost:8080".to_string()).await.unwrap();
+/// let config = FuzzConfig::default().requests(20);
+/// let report = run(&mut client, "helloworld.Greeter", "SayHello", &CallOptions::default(), &config, |finding| {
+///     println!("{}", serde_json::to_string(finding).unwrap());
+/// })
+/// .await
+/// .unwrap();
+/// println!("{report}");
+/// # })
+/// ```
+pub async fn run(
+    client: &mut Client,
+    service: &str,
+    method: &str,
+    options: &CallOptions,
+    config: &FuzzConfig,
+    mut found: impl FnMut(&Finding),
+) -> Result<FuzzReport, GrpcClientError> {
+    let method = client.find_method(service, method).await?;
+    let seed = config.seed.unwrap_or_else(|| rand::rng().random());
+    let mut generator = MessageGenerator::new()
+        .seed(seed)
+        .max_depth(config.max_depth)
+        .max_entries(config.max_entries);
+    let mut report = FuzzReport {
+        seed,
+        requests: 0,
+        status_codes: BTreeMap::new(),
+        findings: Vec::new(),
+        first_error: None,
+    };
+    for index in 0..config.requests {
+        let request = generator.message(&method.input());
+        let status = match client
+            .request_message(&method, request.clone(), options)
+            .await
+        {
+            Ok(result) => format!("{:?}", result.code),
+            Err(e) if e.exit_code() == exit_code::USAGE => return Err(e),
+            Err(e) => {
+                let status = e.code_name();
+                if is_finding(&e) {
+                    let finding = Finding {
+                        index,
+                        request: request_json(&request),
+                        status: status.clone(),
+                        message: e
+                            .status()
+                            .map_or_else(|| e.to_string(), |s| s.message().to_string()),
+                    };
+                    found(&finding);
+                    report.findings.push(finding);
+                    report.first_error.get_or_insert(e);
+                }
+                status
+            }
+        };
+        report.requests += 1;
+        *report.status_codes.entry(status).or_default() += 1;
+    }
+    Ok(report)
+}
+
+fn request_json(request: &DynamicMessage) -> serde_json::Value {
+    serde_json::to_value(request).unwrap_or_else(|_| {
+        serde_json::Value::String(BASE64_STANDARD.encode(request.encode_to_vec()))
+    })
+}
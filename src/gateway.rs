@@ -0,0 +1,620 @@
+//! A REST/json gateway in front of a grpc server, built from its descriptors.
+//!
+//! Every method is served at `POST /<package.Service>/<Method>` with its request as the json body,
+//! and at the routes of its `google.api.http` annotation when it has one:
+//!
+//! ```proto
+//! rpc GetBook(GetBookRequest) returns (Book) {
+//!   option (google.api.http) = { get: "/v1/{name=shelves/*/books/*}" };
+//! }
+//! ```
+//!
+//! The variables of the path set fields of the request, `body` names the field read from the json
+//! body (`*` for the whole request), and the fields set by neither come from the query string.
+//! `response_body` names the field of the response returned instead of the whole response.
+//!
+//! The calls are made by a [`Client`]. The `authorization` header and the `grpc-metadata-` ones are
+//! sent as metadata. A server streaming method answers a json array of its messages, a client
+//! streaming one is sent the single message of the body. An error is a json object with the grpc
+//! `code`, `message` and `details`, and the http status of [`http_status`].
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::body::{Body, to_bytes};
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use http::{HeaderMap, Method, StatusCode};
+use percent_encoding::percent_decode_str;
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, MethodDescriptor,
+    ReflectMessage, SerializeOptions,
+};
+use serde_json::{Map, Value, json};
+use tokio::net::TcpListener;
+use tonic::Code;
+use tonic::metadata::MetadataMap;
+use tracing::log::{info, warn};
+
+use crate::call_options::CallOptions;
+use crate::client::{Client, GrpcClientError};
+use crate::exit_code;
+use crate::status_details::decode_status_details;
+
+/// headers sent as metadata once this prefix is removed, like grpc-gateway does
+const METADATA_PREFIX: &str = "grpc-metadata-";
+
+/// Path of a `google.api.http` route, with the variables setting fields of the request.
+///
+/// ```
+/// use grpc_client::gateway::PathTemplate;
+/// let template: PathTemplate = "/v1/{name=shelves/*/books/*}:read".parse().unwrap();
+/// let variables = template.matches("/v1/shelves/1/books/my%20book:read").unwrap();
+/// assert_eq!(variables, [("name".to_string(), "shelves/1/books/my book".to_string())]);
+/// assert!(template.matches("/v1/shelves/1/books/2").is_none());
+/// assert!("/v1/{name".parse::<PathTemplate>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathTemplate {
+    template: String,
+    segments: Vec<Segment>,
+    /// field path of each variable, the segments refer to them by index
+    variables: Vec<String>,
+    /// custom verb ending the path, after a `:`
+    verb: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    kind: SegmentKind,
+    /// index of the variable the segment is part of
+    variable: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SegmentKind {
+    Literal(String),
+    /// `*`, a single segment
+    Single,
+    /// `**`, any number of segments
+    Rest,
+}
+
+impl FromStr for PathTemplate {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("invalid path template '{template}': {reason}");
+        let path = template
+            .strip_prefix('/')
+            .ok_or_else(|| invalid("it must start with /"))?;
+        let (path, verb) = match path.rsplit_once(':') {
+            Some((path, verb)) if !verb.contains(['/', '}']) => (path, Some(verb.to_string())),
+            _ => (path, None),
+        };
+        let mut parsed = PathTemplate {
+            template: template.to_string(),
+            segments: Vec::new(),
+            variables: Vec::new(),
+            verb,
+        };
+        // the slashes inside braces separate the segments of a variable, not the ones of the path
+        let mut depth = 0;
+        let raw_segments = path.split(|c| {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            c == '/' && depth == 0
+        });
+        for raw in raw_segments {
+            let Some(variable) = raw.strip_prefix('{') else {
+                parsed.segments.push(Segment {
+                    kind: segment_kind(raw).map_err(|reason| invalid(&reason))?,
+                    variable: None,
+                });
+                continue;
+            };
+            let variable = variable
+                .strip_suffix('}')
+                .ok_or_else(|| invalid("unclosed variable"))?;
+            let (field, pattern) = variable.split_once('=').unwrap_or((variable, "*"));
+            let identifier = |name: &str| {
+                name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            };
+            if !field.split('.').all(identifier) {
+                return Err(invalid(&format!("invalid field path '{field}'")));
+            }
+            let index = parsed.variables.len();
+            parsed.variables.push(field.to_string());
+            for raw in pattern.split('/') {
+                parsed.segments.push(Segment {
+                    kind: segment_kind(raw).map_err(|reason| invalid(&reason))?,
+                    variable: Some(index),
+                });
+            }
+        }
+        let rests = parsed
+            .segments
+            .iter()
+            .filter(|segment| segment.kind == SegmentKind::Rest)
+            .count();
+        if rests > 1 {
+            return Err(invalid("only one ** is allowed"));
+        }
+        Ok(parsed)
+    }
+}
+
+/// a segment outside of the variables, or in their pattern
+fn segment_kind(raw: &str) -> Result<SegmentKind, String> {
+    match raw {
+        "*" => Ok(SegmentKind::Single),
+        "**" => Ok(SegmentKind::Rest),
+        "" => Err("empty segment".to_string()),
+        literal if literal.contains(['{', '}', '*', '=']) => {
+            Err(format!("invalid segment '{literal}'"))
+        }
+        literal => Ok(SegmentKind::Literal(literal.to_string())),
+    }
+}
+
+impl PathTemplate {
+    /// the field path and the percent decoded value of each variable, none when the path does not match
+    pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let path = path.strip_prefix('/')?;
+        let path = match &self.verb {
+            Some(verb) => path.strip_suffix(verb.as_str())?.strip_suffix(':')?,
+            None => path,
+        };
+        let parts: Vec<&str> = path.split('/').collect();
+        let mut values: Vec<Vec<&str>> = vec![Vec::new(); self.variables.len()];
+        let mut next = 0;
+        for (position, segment) in self.segments.iter().enumerate() {
+            let taken = match segment.kind {
+                // whatever the segments after it leave
+                SegmentKind::Rest => parts
+                    .len()
+                    .checked_sub(next + self.segments.len() - position - 1)?,
+                _ => 1,
+            };
+            let matched = parts.get(next..next + taken)?;
+            match &segment.kind {
+                SegmentKind::Literal(literal) if matched[0] != literal => return None,
+                SegmentKind::Single if matched[0].is_empty() => return None,
+                _ => {}
+            }
+            if let Some(variable) = segment.variable {
+                values[variable].extend(matched);
+            }
+            next += taken;
+        }
+        if next != parts.len() {
+            return None;
+        }
+        Some(
+            self.variables
+                .iter()
+                .zip(values)
+                .map(|(field, value)| {
+                    let value = percent_decode_str(&value.join("/"))
+                        .decode_utf8_lossy()
+                        .into_owned();
+                    (field.clone(), value)
+                })
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for PathTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// A route of the gateway: an http method and path, and the grpc method called.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub http_method: Method,
+    pub path: PathTemplate,
+    pub method: MethodDescriptor,
+    /// field of the request read from the body, `*` for the whole request, empty for none
+    pub body: String,
+    /// field of the response returned, empty for the whole response
+    pub response_body: String,
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} -> {}",
+            self.http_method,
+            self.path,
+            self.method.full_name()
+        )
+    }
+}
+
+impl Route {
+    /// `POST /package.Service/Method`, with the whole request in the body
+    pub fn generic(method: &MethodDescriptor) -> Self {
+        let path = format!("/{}/{}", method.parent_service().full_name(), method.name());
+        Self {
+            http_method: Method::POST,
+            path: path
+                .parse()
+                .expect("service and method names are valid literals"),
+            method: method.clone(),
+            body: "*".to_string(),
+            response_body: String::new(),
+        }
+    }
+
+    /// the routes of the `google.api.http` annotation of a method, its additional bindings included
+    pub fn annotated(method: &MethodDescriptor) -> Result<Vec<Self>, String> {
+        let Some(extension) = method
+            .parent_pool()
+            .get_extension_by_name("google.api.http")
+        else {
+            return Ok(Vec::new());
+        };
+        let options = method.options();
+        if !options.has_extension(&extension) {
+            return Ok(Vec::new());
+        }
+        let rule = options.get_extension(&extension);
+        let Some(rule) = rule.as_message() else {
+            return Err("google.api.http is not an HttpRule".to_string());
+        };
+        let mut routes = vec![Self::from_rule(method, rule)?];
+        if let Some(bindings) = rule.get_field_by_name("additional_bindings") {
+            for binding in bindings.as_list().unwrap_or_default() {
+                if let Some(binding) = binding.as_message() {
+                    routes.push(Self::from_rule(method, binding)?);
+                }
+            }
+        }
+        Ok(routes)
+    }
+
+    /// the route of a `google.api.HttpRule`
+    fn from_rule(method: &MethodDescriptor, rule: &DynamicMessage) -> Result<Self, String> {
+        let text = |name: &str| {
+            rule.get_field_by_name(name)
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default()
+        };
+        let pattern = ["get", "put", "post", "delete", "patch"]
+            .into_iter()
+            .find(|name| rule.has_field_by_name(name))
+            .map(|name| (name.to_ascii_uppercase(), text(name)));
+        let custom = rule
+            .get_field_by_name("custom")
+            .filter(|_| rule.has_field_by_name("custom"))
+            .and_then(|custom| {
+                let custom = custom.as_message()?;
+                let field =
+                    |name: &str| custom.get_field_by_name(name)?.as_str().map(str::to_string);
+                Some((field("kind")?, field("path")?))
+            });
+        let Some((http_method, path)) = pattern.or(custom) else {
+            return Err(format!(
+                "no http method in the rule of {}",
+                method.full_name()
+            ));
+        };
+        Ok(Self {
+            http_method: Method::from_bytes(http_method.as_bytes())
+                .map_err(|e| format!("invalid http method {http_method}: {e}"))?,
+            path: path.parse()?,
+            method: method.clone(),
+            body: text("body"),
+            response_body: text("response_body"),
+        })
+    }
+}
+
+/// http status answered for a grpc status code, the one chosen by grpc-gateway
+///
+/// ```
+/// use grpc_client::gateway::http_status;
+/// assert_eq!(http_status(tonic::Code::NotFound), http::StatusCode::NOT_FOUND);
+/// assert_eq!(http_status(tonic::Code::Unavailable), http::StatusCode::SERVICE_UNAVAILABLE);
+/// ```
+pub fn http_status(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::Cancelled => StatusCode::from_u16(499).expect("valid status"),
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Serves every method of a pool over http/json, see the [module documentation](self).
+pub struct Gateway {
+    client: Client,
+    pool: DescriptorPool,
+    routes: Vec<Route>,
+    options: CallOptions,
+}
+
+impl Gateway {
+    /// the routes of every method of the pool, calling them with `client`.
+    /// The annotated routes come first, an invalid annotation is left out with a warning
+    pub fn new(client: Client, pool: DescriptorPool) -> Self {
+        let methods: Vec<MethodDescriptor> = pool
+            .services()
+            .flat_map(|service| service.methods().collect::<Vec<_>>())
+            .collect();
+        let mut routes = Vec::new();
+        for method in &methods {
+            match Route::annotated(method) {
+                Ok(annotated) => routes.extend(annotated),
+                Err(e) => warn!("{} left out of the rest routes: {e}", method.full_name()),
+            }
+        }
+        routes.extend(methods.iter().map(Route::generic));
+        Self {
+            client,
+            pool,
+            routes,
+            options: CallOptions::default(),
+        }
+    }
+    /// options of every call, the metadata of the http request is added to theirs
+    pub fn options(mut self, options: CallOptions) -> Self {
+        self.options = options;
+        self
+    }
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+    /// the first route matching a request, with the values of its path variables
+    pub fn find_route(
+        &self,
+        method: &Method,
+        path: &str,
+    ) -> Option<(&Route, Vec<(String, String)>)> {
+        self.routes
+            .iter()
+            .filter(|route| route.http_method == method)
+            .find_map(|route| Some((route, route.path.matches(path)?)))
+    }
+    /// serve the routes until the listener fails
+    pub async fn serve(self, listener: TcpListener) -> Result<(), GrpcClientError> {
+        let gateway = Arc::new(self);
+        let router = axum::Router::new()
+            .fallback(move |request: http::Request<Body>| gateway.clone().handle(request));
+        axum::serve(listener, router)
+            .await
+            .map_err(|e| GrpcClientError::ServerError(e.to_string()))
+    }
+    async fn handle(self: Arc<Self>, request: http::Request<Body>) -> http::Response<Body> {
+        let (parts, body) = request.into_parts();
+        let Some((route, variables)) = self.find_route(&parts.method, parts.uri.path()) else {
+            let error = json!({
+                "code": Code::NotFound as i32,
+                "message": format!("no route for {} {}", parts.method, parts.uri.path()),
+                "details": [],
+            });
+            return json_response(StatusCode::NOT_FOUND, &error);
+        };
+        info!("gateway call {route}");
+        let body = match to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+            Err(e) => {
+                let error = GrpcClientError::InvalidInput(format!("cannot read the body: {e}"));
+                return self.error_response(&error);
+            }
+        };
+        let request = match request_message(route, variables, parts.uri.query(), &body) {
+            Ok(request) => request,
+            Err(e) => return self.error_response(&e),
+        };
+        let mut options = self.options.clone();
+        options.metadata = forwarded_metadata(&options.metadata, &parts.headers);
+        let mut client = self.client.clone();
+        match client
+            .request_message(&route.method, request, &options)
+            .await
+        {
+            Ok(result) => {
+                let messages = result
+                    .messages
+                    .iter()
+                    .map(|message| response_json(message, &route.response_body))
+                    .collect::<Result<Vec<_>, _>>();
+                let mut messages = match messages {
+                    Ok(messages) => messages,
+                    Err(e) => return self.error_response(&e),
+                };
+                let response = match route.method.is_server_streaming() {
+                    true => Value::Array(messages),
+                    false => messages.pop().unwrap_or_else(|| json!({})),
+                };
+                json_response(StatusCode::OK, &response)
+            }
+            Err(e) => self.error_response(&e),
+        }
+    }
+    /// a json error like the ones of grpc-gateway: the code, message and details of the status
+    fn error_response(&self, error: &GrpcClientError) -> http::Response<Body> {
+        let (code, body) = match error.status() {
+            Some(status) => {
+                let mut body = decode_status_details(status, Some(&self.pool))
+                    .unwrap_or_else(|| json!({"details": []}));
+                body["code"] = json!(status.code() as i32);
+                body["message"] = json!(status.message());
+                (status.code(), body)
+            }
+            None => {
+                let code = match error.exit_code() {
+                    exit_code::USAGE => Code::InvalidArgument,
+                    exit_code::CONNECTION => Code::Unavailable,
+                    _ => Code::Internal,
+                };
+                let body =
+                    json!({"code": code as i32, "message": error.to_string(), "details": []});
+                (code, body)
+            }
+        };
+        json_response(http_status(code), &body)
+    }
+}
+
+fn json_response(status: StatusCode, body: &Value) -> http::Response<Body> {
+    let mut response = http::Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        CONTENT_TYPE,
+        http::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+/// the metadata of the options, with the authorization and `grpc-metadata-` headers of the http request
+fn forwarded_metadata(metadata: &MetadataMap, headers: &HeaderMap) -> MetadataMap {
+    let mut forwarded = metadata.clone().into_headers();
+    for (name, value) in headers {
+        if name == AUTHORIZATION {
+            forwarded.append(name, value.clone());
+        } else if let Some(key) = name.as_str().strip_prefix(METADATA_PREFIX)
+            && let Ok(key) = http::HeaderName::from_bytes(key.as_bytes())
+        {
+            forwarded.append(key, value.clone());
+        }
+    }
+    MetadataMap::from_headers(forwarded)
+}
+
+/// the request of a call: the body, then the path variables, then the query parameters
+fn request_message(
+    route: &Route,
+    variables: Vec<(String, String)>,
+    query: Option<&str>,
+    body: &[u8],
+) -> Result<DynamicMessage, GrpcClientError> {
+    let input = route.method.input();
+    let body = match body.iter().all(u8::is_ascii_whitespace) {
+        true => None,
+        false => Some(
+            serde_json::from_slice::<Value>(body)
+                .map_err(|e| GrpcClientError::InvalidInput(format!("invalid json body: {e}")))?,
+        ),
+    };
+    let mut request = Value::Object(Map::new());
+    match (route.body.as_str(), body) {
+        (_, None) | ("", _) => {}
+        ("*", Some(body)) => request = body,
+        (field, Some(body)) => {
+            let (parent, field) = field_slot(&mut request, &input, field)?;
+            parent.insert(field.json_name().to_string(), body);
+        }
+    }
+    for (path, value) in variables {
+        set_text(&mut request, &input, &path, &value)?;
+    }
+    if route.body != "*" {
+        for (path, value) in form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+            set_text(&mut request, &input, &path, &value)?;
+        }
+    }
+    DynamicMessage::deserialize(input, request).map_err(|e| {
+        GrpcClientError::InvalidInput(format!("invalid {} request: {e}", route.method.full_name()))
+    })
+}
+
+/// the json object holding a field given by its dotted path of names (`book.title`), and that field
+fn field_slot<'a>(
+    request: &'a mut Value,
+    desc: &MessageDescriptor,
+    path: &str,
+) -> Result<(&'a mut Map<String, Value>, FieldDescriptor), GrpcClientError> {
+    let invalid = |reason: String| GrpcClientError::InvalidFieldPath(path.to_string(), reason);
+    let mut desc = desc.clone();
+    let mut object = request;
+    let mut names = path.split('.').peekable();
+    loop {
+        let name = names.next().expect("split gives at least one name");
+        let field = desc
+            .get_field_by_name(name)
+            .or_else(|| desc.get_field_by_json_name(name))
+            .ok_or_else(|| invalid(format!("no field '{name}' in {}", desc.full_name())))?;
+        let Value::Object(parent) = object else {
+            return Err(invalid(format!("{name} is set twice")));
+        };
+        if names.peek().is_none() {
+            return Ok((parent, field));
+        }
+        match field.kind() {
+            Kind::Message(message) if !field.is_list() && !field.is_map() => desc = message,
+            _ => return Err(invalid(format!("{name} is not a message"))),
+        }
+        object = parent
+            .entry(field.json_name().to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+/// set a field from the text of a path variable or query parameter, a repeated field gets one more value
+fn set_text(
+    request: &mut Value,
+    desc: &MessageDescriptor,
+    path: &str,
+    text: &str,
+) -> Result<(), GrpcClientError> {
+    let (parent, field) = field_slot(request, desc, path)?;
+    // the json mapping takes numbers and enums as strings, not booleans
+    let value = match field.kind() {
+        Kind::Bool => Value::Bool(text.parse().map_err(|_| {
+            GrpcClientError::InvalidFieldPath(path.to_string(), format!("'{text}' is not a bool"))
+        })?),
+        _ => Value::String(text.to_string()),
+    };
+    let name = field.json_name().to_string();
+    match parent.get_mut(&name) {
+        Some(Value::Array(values)) if field.is_list() => values.push(value),
+        _ if field.is_list() => {
+            parent.insert(name, Value::Array(vec![value]));
+        }
+        _ => {
+            parent.insert(name, value);
+        }
+    }
+    Ok(())
+}
+
+/// the json of a response, or of its `response_body` field; an error when it has no json mapping,
+/// like an `Any` of a type the descriptors do not have
+fn response_json(message: &DynamicMessage, response_body: &str) -> Result<Value, GrpcClientError> {
+    let no_json = |e: serde_json::Error| {
+        GrpcClientError::BadMessageType(format!("the response has no json mapping: {e}"))
+    };
+    if response_body.is_empty() {
+        return serde_json::to_value(message).map_err(no_json);
+    }
+    // with the default values, so the field is there even when it has one
+    let options = SerializeOptions::new().skip_default_fields(false);
+    let value = message
+        .serialize_with_options(serde_json::value::Serializer, &options)
+        .map_err(no_json)?;
+    let field = message
+        .descriptor()
+        .get_field_by_name(response_body)
+        .map(|field| field.json_name().to_string())
+        .unwrap_or_default();
+    Ok(value.get(&field).cloned().unwrap_or(Value::Null))
+}
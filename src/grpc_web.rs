@@ -0,0 +1,216 @@
+//! grpc-web over http/1.1, for servers behind a proxy translating it, like the grpc_web filter of Envoy.
+//!
+//! [`GrpcWebService`] turns the grpc requests made by tonic into grpc-web ones, and their responses
+//! back into grpc ones: the trailers, sent by grpc-web as a last frame of the body, become http
+//! trailers again. In text mode both bodies are base64 encoded.
+//!
+//! grpc-web has no client or bidi streaming, so a request body is read whole before being sent.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use bytes::{Bytes, BytesMut};
+use http::header::{ACCEPT, CONTENT_TYPE, TE};
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Uri, Version};
+use http_body::Frame;
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use tonic::Status;
+use tonic::body::Body;
+use tower::Service;
+
+use crate::transport::{FrameDecoder, causes, decoded_body};
+
+const GRPC_WEB: &str = "application/grpc-web+proto";
+const GRPC_WEB_TEXT: &str = "application/grpc-web-text+proto";
+/// flag of the frame holding the trailers, in the first byte of its header
+const TRAILERS_FLAG: u8 = 0x80;
+
+/// Sends grpc calls to a grpc-web server, see the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct GrpcWebService {
+    client: Client<HttpConnector, Full<Bytes>>,
+    /// scheme and authority of the server, the path comes from each request
+    base: Uri,
+    text: bool,
+}
+
+impl GrpcWebService {
+    /// `text` sends and asks for base64 encoded bodies, application/grpc-web-text
+    pub fn new(base: Uri, text: bool) -> Self {
+        Self {
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            base,
+            text,
+        }
+    }
+}
+
+impl Service<http::Request<Body>> for GrpcWebService {
+    type Response = http::Response<Body>;
+    type Error = Status;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move { service.send(request).await })
+    }
+}
+
+impl GrpcWebService {
+    async fn send(self, request: http::Request<Body>) -> Result<http::Response<Body>, Status> {
+        let (parts, body) = request.into_parts();
+        let mut body = body.collect().await?.to_bytes();
+        let content_type = match self.text {
+            true => {
+                body = BASE64_STANDARD.encode(&body).into();
+                GRPC_WEB_TEXT
+            }
+            false => GRPC_WEB,
+        };
+        let mut uri = self.base.into_parts();
+        uri.path_and_query = parts.uri.path_and_query().cloned();
+        let uri = Uri::from_parts(uri)
+            .map_err(|e| Status::invalid_argument(format!("invalid grpc-web url: {e}")))?;
+        let mut request = http::Request::new(Full::new(body));
+        *request.method_mut() = Method::POST;
+        *request.uri_mut() = uri;
+        *request.version_mut() = Version::HTTP_11;
+        let headers = request.headers_mut();
+        *headers = parts.headers;
+        // http/1.1 has no trailers, grpc-web sends them in the body
+        headers.remove(TE);
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers.insert(ACCEPT, HeaderValue::from_static(content_type));
+        headers.insert("x-grpc-web", HeaderValue::from_static("1"));
+
+        let response =
+            self.client.request(request).await.map_err(|e| {
+                Status::unavailable(format!("grpc-web request failed: {}", causes(&e)))
+            })?;
+        let (mut parts, body) = response.into_parts();
+        let text = parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/grpc-web-text"));
+        parts.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/grpc+proto"),
+        );
+        Ok(http::Response::from_parts(
+            parts,
+            decoded_body(body, GrpcWebDecoder::new(text)),
+        ))
+    }
+}
+
+/// Turns a grpc-web response body back into grpc frames, then http trailers.
+///
+/// ```
+/// use grpc_client::grpc_web::GrpcWebDecoder;
+/// use grpc_client::transport::FrameDecoder;
+/// let mut decoder = GrpcWebDecoder::new(false);
+/// let body = b"\x00\x00\x00\x00\x02\x08\x01\x80\x00\x00\x00\x0fgrpc-status:0\r\n";
+/// // the frames may be split anywhere
+/// let mut frames = decoder.decode(&body[..4]).unwrap();
+/// frames.extend(decoder.decode(&body[4..]).unwrap());
+/// assert_eq!(frames[0].data_ref().unwrap().as_ref(), b"\x00\x00\x00\x00\x02\x08\x01");
+/// assert_eq!(frames[1].trailers_ref().unwrap()["grpc-status"], "0");
+/// assert!(decoder.finish().is_ok());
+/// ```
+#[derive(Debug, Default)]
+pub struct GrpcWebDecoder {
+    text: bool,
+    /// base64 characters not decoded yet, a group of 4 is decoded at once
+    pending_text: Vec<u8>,
+    /// bytes of the frame being received
+    buffer: BytesMut,
+}
+
+impl GrpcWebDecoder {
+    /// `text` for base64 encoded bodies
+    pub fn new(text: bool) -> Self {
+        Self {
+            text,
+            ..Self::default()
+        }
+    }
+}
+
+impl FrameDecoder for GrpcWebDecoder {
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<Frame<Bytes>>, Status> {
+        if self.text {
+            self.pending_text
+                .extend(chunk.iter().filter(|byte| !byte.is_ascii_whitespace()));
+            let complete = self.pending_text.len() - self.pending_text.len() % 4;
+            // each group is decoded alone, a server may pad every chunk it sends
+            for group in self.pending_text[..complete].chunks(4) {
+                let bytes = BASE64_STANDARD
+                    .decode(group)
+                    .map_err(|e| Status::internal(format!("invalid grpc-web-text body: {e}")))?;
+                self.buffer.extend_from_slice(&bytes);
+            }
+            self.pending_text.drain(..complete);
+        } else {
+            self.buffer.extend_from_slice(chunk);
+        }
+        let mut frames = Vec::new();
+        while self.buffer.len() >= 5 {
+            let length =
+                u32::from_be_bytes(self.buffer[1..5].try_into().expect("4 bytes")) as usize;
+            if self.buffer.len() < 5 + length {
+                break;
+            }
+            let frame = self.buffer.split_to(5 + length).freeze();
+            if frame[0] & TRAILERS_FLAG != 0 {
+                frames.push(Frame::trailers(parse_trailers(&frame[5..])?));
+            } else {
+                frames.push(Frame::data(frame));
+            }
+        }
+        Ok(frames)
+    }
+    fn finish(&self) -> Result<(), Status> {
+        match self.buffer.is_empty() && self.pending_text.is_empty() {
+            true => Ok(()),
+            false => Err(Status::internal(
+                "grpc-web response ended in the middle of a frame",
+            )),
+        }
+    }
+}
+
+/// trailers of grpc-web, written like http/1.1 headers: `name: value` lines ended by `\r\n`
+fn parse_trailers(block: &[u8]) -> Result<HeaderMap, Status> {
+    let mut trailers = HeaderMap::new();
+    for line in block.split(|byte| *byte == b'\n') {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            continue;
+        }
+        let invalid = || {
+            Status::internal(format!(
+                "invalid grpc-web trailer '{}'",
+                String::from_utf8_lossy(line)
+            ))
+        };
+        let colon = line
+            .iter()
+            .position(|byte| *byte == b':')
+            .ok_or_else(invalid)?;
+        let name = HeaderName::from_bytes(line[..colon].trim_ascii()).map_err(|_| invalid())?;
+        let value =
+            HeaderValue::from_bytes(line[colon + 1..].trim_ascii()).map_err(|_| invalid())?;
+        trailers.append(name, value);
+    }
+    Ok(trailers)
+}
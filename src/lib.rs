@@ -15,7 +15,35 @@
 //! let response = client.request(&"filename.service", &"method", vec![("argumentname1".to_string(), "value".to_string())]);
 //! # })
 //! ```
+pub mod batch;
+pub mod bench;
+pub mod call_options;
+pub mod call_result;
+pub mod cassette;
 pub mod client;
+pub mod connect;
+pub mod describe;
+pub mod descriptor_source;
 pub mod dynamic_codec;
+pub mod encoding;
+pub mod exit_code;
+pub mod filter;
+pub mod fuzz;
+pub mod gateway;
+pub mod grpc_web;
+pub mod listing;
+pub mod mock;
+pub mod output;
+pub mod projection;
+pub mod random_message;
+pub mod raw_decode;
+pub mod reflection;
+pub mod retry;
+pub mod status_details;
+pub mod transport;
+pub use call_options::CallOptions;
+pub use call_result::CallResult;
 pub use client::Client;
 pub use client::GrpcFilters;
+pub use output::OutputOptions;
+pub use retry::RetryPolicy;
@@ -0,0 +1,304 @@
+//! Human readable listing of the services of a descriptor pool, with their messages expanded.
+//!
+//! Input and output messages are rendered recursively: nested messages, maps, oneofs and enum
+//! values are shown under the field using them, whatever file defines them. A message already
+//! being expanded is marked `(recursive)` instead of being expanded again.
+//!
+//! [`list_services`] gives the same services as data, to be serialized as json or yaml.
+use std::fmt::Write;
+
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, EnumDescriptor, FieldDescriptor, Kind, MessageDescriptor,
+    MethodDescriptor, SerializeOptions, ServiceDescriptor,
+};
+use serde::Serialize;
+use tracing::log::debug;
+
+use crate::client::GrpcFilters;
+use crate::describe::field_type;
+use crate::filter::{is_deprecated, is_service_deprecated};
+
+/// Services of a protobuf package
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageListing {
+    pub name: String,
+    pub services: Vec<ServiceListing>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceListing {
+    pub name: String,
+    pub full_name: String,
+    /// proto file defining the service
+    pub file: String,
+    pub deprecated: bool,
+    /// options set on the service, in the protobuf json mapping
+    #[serde(skip_serializing_if = "is_empty")]
+    pub options: serde_json::Value,
+    pub methods: Vec<MethodListing>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MethodListing {
+    pub name: String,
+    pub full_name: String,
+    /// fully qualified name of the request message
+    pub input_type: String,
+    /// fully qualified name of the response message
+    pub output_type: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub deprecated: bool,
+    /// options set on the method, in the protobuf json mapping
+    #[serde(skip_serializing_if = "is_empty")]
+    pub options: serde_json::Value,
+}
+
+fn is_empty(options: &serde_json::Value) -> bool {
+    options.as_object().is_none_or(|options| options.is_empty())
+}
+
+/// options as json, extensions are written as `[full.name]`
+fn options_json(options: &DynamicMessage) -> serde_json::Value {
+    options
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new())
+        .unwrap_or_default()
+}
+
+/// Every service of the pool kept by the filters, grouped by package, for machine readable output.
+///
+/// ```
+/// use grpc_client::GrpcFilters;
+/// use grpc_client::listing::list_services;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET).unwrap();
+/// let packages = list_services(&pool, &GrpcFilters::new(vec!["grpc.reflection.v1".to_string()]).unwrap());
+/// let method = &packages[0].services[0].methods[0];
+/// assert_eq!(method.full_name, "grpc.reflection.v1.ServerReflection.ServerReflectionInfo");
+/// assert!(method.client_streaming && method.server_streaming);
+/// ```
+pub fn list_services(pool: &DescriptorPool, filter: &GrpcFilters) -> Vec<PackageListing> {
+    let mut packages: Vec<PackageListing> = Vec::new();
+    for file in pool.files() {
+        if filter.filter_file(&file) {
+            continue;
+        }
+        let services: Vec<ServiceListing> = file
+            .services()
+            .filter(|service| !filter.filter_service(service))
+            .map(|service| ServiceListing {
+                name: service.name().to_string(),
+                full_name: service.full_name().to_string(),
+                file: file.name().to_string(),
+                deprecated: is_service_deprecated(&service),
+                options: options_json(&service.options()),
+                methods: service
+                    .methods()
+                    .filter(|method| !filter.filter_method(method))
+                    .map(|method| MethodListing {
+                        name: method.name().to_string(),
+                        full_name: method.full_name().to_string(),
+                        input_type: method.input().full_name().to_string(),
+                        output_type: method.output().full_name().to_string(),
+                        client_streaming: method.is_client_streaming(),
+                        server_streaming: method.is_server_streaming(),
+                        deprecated: is_deprecated(&method),
+                        options: options_json(&method.options()),
+                    })
+                    .collect(),
+            })
+            .collect();
+        if services.is_empty() {
+            continue;
+        }
+        match packages
+            .iter_mut()
+            .find(|package| package.name == file.package_name())
+        {
+            Some(package) => package.services.extend(services),
+            None => packages.push(PackageListing {
+                name: file.package_name().to_string(),
+                services,
+            }),
+        }
+    }
+    packages
+}
+
+/// Every service of the pool kept by the filters, grouped by file.
+///
+/// ```
+/// use grpc_client::GrpcFilters;
+/// use grpc_client::listing::render_services;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_reflection::pb::v1::FILE_DESCRIPTOR_SET).unwrap();
+/// // the reflection service itself is hidden unless asked for
+/// assert_eq!(render_services(&pool, &GrpcFilters::new_empty()), "");
+/// let listing = render_services(&pool, &GrpcFilters::new(vec!["grpc.reflection.v1".to_string()]).unwrap());
+/// assert!(listing.contains("  service ServerReflection\n"));
+/// assert!(listing.contains("        oneof message_request\n"));
+/// ```
+pub fn render_services(pool: &DescriptorPool, filter: &GrpcFilters) -> String {
+    let mut out = String::new();
+    debug!("filter : {:?}", filter);
+    for file in pool.files() {
+        debug!("checking file '{}'", file.package_name());
+        if filter.filter_file(&file) {
+            continue;
+        }
+        let _ = writeln!(
+            out,
+            "\nfile {} (package {}) :",
+            file.name(),
+            file.package_name()
+        );
+        for service in file.services() {
+            debug!("checking service '{}'", service.name());
+            if filter.filter_service(&service) {
+                continue;
+            }
+            render_service(&mut out, &service, filter);
+        }
+    }
+    out
+}
+
+fn render_service(out: &mut String, service: &ServiceDescriptor, filter: &GrpcFilters) {
+    let _ = writeln!(
+        out,
+        "  service {}{}",
+        service.name(),
+        deprecated(Some(is_service_deprecated(service)))
+    );
+    for method in service.methods() {
+        debug!("checking method '{}'", method.name());
+        if filter.filter_method(&method) {
+            continue;
+        }
+        render_method(out, &method);
+    }
+}
+
+fn render_method(out: &mut String, method: &MethodDescriptor) {
+    let stream = |yes: bool| if yes { "stream " } else { "" };
+    let _ = writeln!(
+        out,
+        "    rpc {}({}.{}) returns ({}.{}){}",
+        method.name(),
+        stream(method.is_client_streaming()),
+        method.input().full_name(),
+        stream(method.is_server_streaming()),
+        method.output().full_name(),
+        deprecated(Some(is_deprecated(method))),
+    );
+    let _ = writeln!(out, "      input .{}", method.input().full_name());
+    render_fields(out, &method.input(), 4, &mut Vec::new());
+    let _ = writeln!(out, "      output .{}", method.output().full_name());
+    render_fields(out, &method.output(), 4, &mut Vec::new());
+}
+
+/// Fields of a message and of every message they use, one per line.
+///
+/// ```
+/// use grpc_client::listing::render_message;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let retry_info = pool.get_message_by_name("google.rpc.RetryInfo").unwrap();
+/// assert_eq!(
+///     render_message(&retry_info),
+///     ".google.protobuf.Duration retry_delay = 1\n  int64 seconds = 1\n  int32 nanos = 2\n"
+/// );
+/// ```
+pub fn render_message(message: &MessageDescriptor) -> String {
+    let mut out = String::new();
+    render_fields(&mut out, message, 0, &mut Vec::new());
+    out
+}
+
+/// `stack` holds the messages being expanded, to stop on recursive types
+fn render_fields(
+    out: &mut String,
+    message: &MessageDescriptor,
+    indent: usize,
+    stack: &mut Vec<String>,
+) {
+    stack.push(message.full_name().to_string());
+    let mut written_oneofs = Vec::new();
+    for field in message.fields() {
+        match field.containing_oneof() {
+            Some(oneof) if !oneof.is_synthetic() => {
+                if written_oneofs.contains(&oneof.name().to_string()) {
+                    continue;
+                }
+                written_oneofs.push(oneof.name().to_string());
+                let _ = writeln!(out, "{}oneof {}", pad(indent), oneof.name());
+                for field in oneof.fields() {
+                    render_field(out, &field, indent + 1, stack);
+                }
+            }
+            _ => render_field(out, &field, indent, stack),
+        }
+    }
+    stack.pop();
+}
+
+fn render_field(out: &mut String, field: &FieldDescriptor, indent: usize, stack: &mut Vec<String>) {
+    // the value of a map is what needs to be expanded, its key is always a scalar
+    let kind = match field.kind() {
+        Kind::Message(entry) if field.is_map() => entry.map_entry_value_field().kind(),
+        kind => kind,
+    };
+    let recursive =
+        matches!(&kind, Kind::Message(inner) if stack.iter().any(|name| name == inner.full_name()));
+    let _ = writeln!(
+        out,
+        "{}{} {} = {}{}{}",
+        pad(indent),
+        field_type(field),
+        field.name(),
+        field.number(),
+        deprecated(
+            field
+                .field_descriptor_proto()
+                .options
+                .as_ref()
+                .and_then(|o| o.deprecated)
+        ),
+        if recursive { " (recursive)" } else { "" },
+    );
+    match kind {
+        Kind::Message(inner) if !recursive => render_fields(out, &inner, indent + 1, stack),
+        Kind::Enum(inner) => render_enum(out, &inner, indent + 1),
+        _ => {}
+    }
+}
+
+fn render_enum(out: &mut String, enum_desc: &EnumDescriptor, indent: usize) {
+    for value in enum_desc.values() {
+        let _ = writeln!(
+            out,
+            "{}{} = {}{}",
+            pad(indent),
+            value.name(),
+            value.number(),
+            deprecated(
+                value
+                    .enum_value_descriptor_proto()
+                    .options
+                    .as_ref()
+                    .and_then(|o| o.deprecated)
+            ),
+        );
+    }
+}
+
+fn deprecated(flag: Option<bool>) -> &'static str {
+    match flag {
+        Some(true) => " [deprecated]",
+        _ => "",
+    }
+}
+
+fn pad(indent: usize) -> String {
+    "  ".repeat(indent)
+}
@@ -1,38 +1,191 @@
 //use crate::lib::dynamic_codec::DynamicCodec;
 
-use clap::{Parser, Subcommand};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use grpc_client::batch::{self, BatchConfig};
+use grpc_client::bench::{self, BenchConfig};
+use grpc_client::call_options::{SUPPORTED_COMPRESSIONS, parse_compression};
+use grpc_client::cassette::{Cassette, Player, Recorder};
 use grpc_client::client::Client;
-use grpc_client::client::GrpcFilters;
+use grpc_client::client::{GrpcClientError, GrpcFilters};
+use grpc_client::describe::Symbol;
+use grpc_client::descriptor_source::DescriptorSource;
+use grpc_client::encoding::{
+    BytesEncoding, MessageSyntax, decode_message, encode_message, parse_message,
+};
+use grpc_client::exit_code;
+use grpc_client::filter::{Pattern, Streaming};
+use grpc_client::fuzz::{self, FuzzConfig};
+use grpc_client::gateway::Gateway;
+use grpc_client::listing::{list_services, render_services};
+use grpc_client::mock::{MockServer, load_rules};
+use grpc_client::output::{OutputFormat, ResponseFormatter};
+use grpc_client::projection::{FieldPath, Projection};
+use grpc_client::random_message::{DEFAULT_MAX_DEPTH, DEFAULT_MAX_ENTRIES};
+use grpc_client::raw_decode::decode_raw_message;
+use grpc_client::retry::parse_code;
+use grpc_client::transport::Protocol;
+use grpc_client::{CallOptions, CallResult, OutputOptions, RetryPolicy};
 
+use prost_reflect::{DescriptorPool, MessageDescriptor, ReflectMessage};
 use std::error::Error;
+use std::io::{Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+use tonic::codec::CompressionEncoding;
+use tonic::metadata::MetadataMap;
+use tonic::transport::server::TcpIncoming;
+use tonic_health::pb::health_check_response::ServingStatus;
+
+use tracing::log::{LevelFilter, debug, info};
 
-use tracing::log::info;
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, after_help = exit_code::help())]
 struct Cli {
-    /// required url to connect to (https://localhost:50051)
+    /// url to connect to (https://localhost:50051), required unless the command works offline
     #[arg(short, long, value_parser = parse_url)]
-    url: String,
+    url: Option<String>,
+
+    /// Protocol spoken with the server: grpc, grpc-web, grpc-web-text (base64 bodies), connect or connect-json
+    #[arg(long, default_value_t = Protocol::Grpc)]
+    protocol: Protocol,
+
+    /// Read the descriptors from these FileDescriptorSet files instead of reflection
+    #[arg(long, conflicts_with = "proto")]
+    protoset: Vec<PathBuf>,
+
+    /// Save every call made, with its request, metadata, responses, status and descriptors, to this json cassette
+    #[arg(long, conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Answer the calls from a cassette saved with --record, without connecting to any server
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// calls made are added here when --record is given
+    #[arg(skip)]
+    recorder: Recorder,
+
+    /// Compile the descriptors from these .proto files instead of reflection
+    #[arg(long)]
+    proto: Vec<PathBuf>,
+
+    /// Directories searched for the imports of --proto files, by default the directory of each file
+    #[arg(short = 'I', long = "import-path", requires = "proto")]
+    import_paths: Vec<PathBuf>,
 
     /// Turn debugging information on
     #[arg(short, long, action = clap::ArgAction::Count)]
     debug: u8,
 
+    /// Show the request metadata, response headers, trailers, status and timing of a call on stderr
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Metadata sent with the request, ex : -H 'authorization: Bearer token'
+    #[arg(short = 'H', long = "header", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Maximum time in seconds to wait for a response (ex: 0.5), sent to the server as grpc-timeout
+    #[arg(long, value_parser = parse_duration)]
+    max_time: Option<Duration>,
+
+    /// Total number of attempts for a failing unary call, 1 disables retries
+    #[arg(long, default_value_t = 1)]
+    retry: u32,
+
+    /// Delay in seconds before the first retry
+    #[arg(long, value_parser = parse_duration, default_value = "0.1")]
+    retry_backoff: Duration,
+
+    /// Maximum delay in seconds between two attempts
+    #[arg(long, value_parser = parse_duration, default_value = "10")]
+    retry_max_backoff: Duration,
+
+    /// Growth factor of the delay after each attempt
+    #[arg(long, default_value_t = 2.0)]
+    retry_multiplier: f64,
+
+    /// Fraction of the delay randomly added or removed
+    #[arg(long, default_value_t = 0.2)]
+    retry_jitter: f64,
+
+    /// Comma separated status codes worth a retry, ex: unavailable,resource-exhausted
+    #[arg(long, value_parser = parse_status_code, value_delimiter = ',', default_value = "unavailable")]
+    retry_on: Vec<tonic::Code>,
+
+    /// Compress the request with gzip, deflate or zstd. Compressed responses are always accepted
+    #[arg(long, value_parser = parse_compression_encoding)]
+    compress: Option<CompressionEncoding>,
+
+    /// How errors are printed on stderr
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text)]
+    format_error: ErrorFormat,
+
     /// either list all service, list one service, or make a request
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ErrorFormat {
+    /// human readable message
+    Text,
+    /// one json object with the error kind, message, status code, details and exit code
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum ListFormat {
+    /// services with their messages expanded
+    Text,
+    /// packages, services and methods as a json document
+    Json,
+    /// packages, services and methods as a yaml document
+    Yaml,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum BenchFormat {
+    /// summary, latency percentiles, histogram and status codes
+    Text,
+    /// the same report as a json document
+    Json,
+    /// the same report as `metric,value` lines
+    Csv,
+}
+
 #[derive(Subcommand, Debug, Clone)]
 enum Commands {
     /// list grpc services
     List {
         /// detail grpc services asked, by default all of them
-        /// Filter is inclusive and non regexp
-        /// ex: filename.servicename.methodname
-        /// each part is optionnal, so ".servicename." work to filter only on the servicename, but not "servicename"
-        #[clap(value_parser, num_args = 1.., value_delimiter = ' ')]
+        /// Each filter is `[!]package,service,method`, every part is optional and is a glob (`Get*`) or a `/regex/`.
+        /// A single part is matched against the package, service and method names, ex: `*.Admin*`
+        /// A filter starting with `!` excludes what it matches, ex: '!,,Delete*'
+        #[clap(value_parser, num_args = 1..)]
         list: Vec<String>,
+        /// text for humans, json or yaml for scripts
+        #[arg(short, long, value_enum, default_value_t = ListFormat::Text)]
+        output: ListFormat,
+        #[command(flatten)]
+        predicates: MethodPredicates,
+    },
+    /// print the definition of a service, method, message, field, enum or extension in proto syntax
+    Describe {
+        /// fully qualified name, ex: helloworld.Greeter.SayHello
+        symbol: String,
+    },
+    /// check the health of the server or of one service with grpc.health.v1, exit with 0 only when serving
+    Health {
+        /// service to check, by default the whole server
+        #[arg(default_value = "")]
+        service: String,
+        /// print the status each time it changes, until the server closes the stream
+        #[arg(long)]
+        watch: bool,
     },
     /// send a grpc request
     Get {
@@ -43,13 +196,291 @@ enum Commands {
         /// Tuples of method arguments, ex : -a argName=value -a argName2=val2
         #[arg(short = 'a', value_parser = parse_key_val::<String, String>)]
         arguments: Vec<(String, String)>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// send the same request many times and report the throughput, latencies and status codes
+    Bench {
+        /// Grpc service to use
+        service: String,
+        /// Grpc method to call
+        method: String,
+        /// Tuples of method arguments, ex : -a argName=value -a argName2=val2
+        #[arg(short = 'a', value_parser = parse_key_val::<String, String>)]
+        arguments: Vec<(String, String)>,
+        /// Total number of calls, 200 when no --duration is given
+        #[arg(short = 'n', long)]
+        requests: Option<u64>,
+        /// Stop starting new calls after this number of seconds
+        #[arg(short = 'z', long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+        /// Number of calls in flight at the same time
+        #[arg(short, long, default_value_t = 10)]
+        concurrency: usize,
+        /// Target number of calls started per second, as many as possible by default
+        #[arg(long, value_parser = parse_rate)]
+        rps: Option<f64>,
+        /// Number of connections the calls are spread over
+        #[arg(long, default_value_t = 1)]
+        channels: usize,
+        /// text for humans, json or csv to keep the report
+        #[arg(short, long, value_enum, default_value_t = BenchFormat::Text)]
+        output: BenchFormat,
+    },
+    /// send the requests of a NDJSON file, one {"service","method","body","metadata"} object per line,
+    /// and print one json result per request. Exit with the code of the first failed call
+    Batch {
+        /// file of requests, `-` to read stdin
+        file: PathBuf,
+        /// Number of calls in flight at the same time, 1 sends them in order
+        #[arg(short, long, default_value_t = 1)]
+        concurrency: usize,
+        /// Stop sending requests after the first failed call, by default every request is sent
+        #[arg(long)]
+        stop_on_error: bool,
+    },
+    /// send random valid requests to a method and print, as json lines, the ones ended by an
+    /// INTERNAL or UNKNOWN status or a dropped connection. Exit with the code of the first one
+    Fuzz {
+        /// Grpc service to use
+        service: String,
+        /// Grpc method to call
+        method: String,
+        /// Number of requests sent, one after the other
+        #[arg(short = 'n', long, default_value_t = fuzz::DEFAULT_REQUESTS)]
+        requests: u64,
+        /// Seed of the requests, printed at the end of each run to send the same requests again
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Nesting depth of the deepest message of a request
+        #[arg(long, default_value_t = DEFAULT_MAX_DEPTH)]
+        max_depth: usize,
+        /// Most entries of a repeated field or a map
+        #[arg(long, default_value_t = DEFAULT_MAX_ENTRIES)]
+        max_entries: usize,
+    },
+    /// serve every method of the descriptors (--protoset, --proto or the reflection of --url) with
+    /// reflection, answering from a rules file or with random messages
+    Mock {
+        /// Address the server listens on
+        #[arg(short, long, default_value = "127.0.0.1:50051")]
+        listen: SocketAddr,
+        /// yaml or json list of rules, each one a method pattern, optional `match` on the request
+        /// fields, and a `response`, `responses` or error `status` and `message`
+        #[arg(short, long)]
+        rules: Option<PathBuf>,
+        /// Answer the calls no rule matches with UNIMPLEMENTED instead of random messages
+        #[arg(long)]
+        no_random: bool,
+    },
+    /// serve every method of the server over http/json at `POST /package.Service/Method`, and at
+    /// the routes of their google.api.http annotations, calling them with grpc
+    Gateway {
+        /// Address the gateway listens on
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        listen: SocketAddr,
     },
+    /// read a message from stdin and write it serialized on stdout, without any call
+    Encode {
+        /// fully qualified message name, ex: helloworld.HelloRequest
+        message_type: String,
+        /// syntax of the message on stdin: json or text
+        #[arg(long, default_value_t = MessageSyntax::Json)]
+        input: MessageSyntax,
+        /// encoding of the bytes written: binary, base64 or hex
+        #[arg(long, default_value_t = BytesEncoding::Binary)]
+        encoding: BytesEncoding,
+    },
+    /// read a serialized message from stdin and print it, without any call
+    Decode {
+        /// fully qualified message name, ex: helloworld.HelloReply
+        message_type: String,
+        /// encoding of the bytes read: binary, base64 or hex
+        #[arg(long, default_value_t = BytesEncoding::Binary)]
+        encoding: BytesEncoding,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// print the fields of a serialized message read from stdin, without knowing its type
+    DecodeRaw {
+        /// encoding of the bytes read: binary, base64 or hex
+        #[arg(long, default_value_t = BytesEncoding::Binary)]
+        encoding: BytesEncoding,
+        /// Format of the fields: text like protoc --decode_raw, json or yaml with every interpretation of the values
+        #[arg(short, long, default_value_t = OutputFormat::Text)]
+        output: OutputFormat,
+    },
+}
+
+/// conditions every listed method must meet
+#[derive(Args, Debug, Clone, Default)]
+struct MethodPredicates {
+    /// Only methods defined in a proto file whose path matches, ex: 'admin/*.proto'
+    #[arg(long)]
+    file: Option<Pattern>,
+    /// Only methods of a package matching this glob or /regex/
+    #[arg(long)]
+    package: Option<Pattern>,
+    /// Only methods taking this message type, ex: '*.GetProductRequest'
+    #[arg(long)]
+    input_type: Option<Pattern>,
+    /// Only methods returning this message type
+    #[arg(long)]
+    output_type: Option<Pattern>,
+    /// Only methods streaming this way: unary, client, server, bidi or any
+    #[arg(long)]
+    streaming: Option<Streaming>,
+    /// Only deprecated methods
+    #[arg(long)]
+    deprecated: bool,
+    /// Only methods that are not deprecated
+    #[arg(long, conflicts_with = "deprecated")]
+    not_deprecated: bool,
+}
+impl MethodPredicates {
+    fn apply(self, mut filters: GrpcFilters) -> GrpcFilters {
+        if let Some(file) = self.file {
+            filters = filters.file(file);
+        }
+        if let Some(package) = self.package {
+            filters = filters.package(package);
+        }
+        if let Some(input_type) = self.input_type {
+            filters = filters.input_type(input_type);
+        }
+        if let Some(output_type) = self.output_type {
+            filters = filters.output_type(output_type);
+        }
+        if let Some(streaming) = self.streaming {
+            filters = filters.streaming(streaming);
+        }
+        if self.deprecated || self.not_deprecated {
+            filters = filters.deprecated(self.deprecated);
+        }
+        filters
+    }
+}
+
+/// how responses are printed
+#[derive(Args, Debug, Clone)]
+struct OutputArgs {
+    /// Format of the responses: json, yaml, text, table, binary or hex
+    #[arg(short, long, default_value_t = OutputFormat::Json)]
+    output: OutputFormat,
+    /// Only print these fields, comma separated paths checked before the call, ex: --select 'items[].id,total'
+    #[arg(long, alias = "fields", value_delimiter = ',')]
+    select: Vec<FieldPath>,
+    /// Also print fields set to their default value
+    #[arg(long)]
+    emit_defaults: bool,
+    /// Use the field names of the proto file instead of their camelCase json name
+    #[arg(long)]
+    use_proto_names: bool,
+    /// Print enums as numbers instead of names
+    #[arg(long)]
+    enums_as_ints: bool,
+    /// Print each response on a single line
+    #[arg(long)]
+    compact: bool,
+    /// Print 64 bits integers as json strings, the default
+    #[arg(long, overrides_with = "no_stringify_64bit")]
+    stringify_64bit: bool,
+    /// Print 64 bits integers as json numbers, which may lose precision in other json parsers
+    #[arg(long, overrides_with = "stringify_64bit")]
+    no_stringify_64bit: bool,
+}
+impl OutputArgs {
+    fn output_options(&self) -> OutputOptions {
+        OutputOptions::default()
+            .emit_defaults(self.emit_defaults)
+            .use_proto_names(self.use_proto_names)
+            .enums_as_ints(self.enums_as_ints)
+            .compact(self.compact)
+            .stringify_64bit(!self.no_stringify_64bit)
+    }
+    fn projection(&self) -> Option<Projection> {
+        (!self.select.is_empty()).then(|| Projection::from_paths(self.select.clone()))
+    }
+    fn formatter(&self) -> Box<dyn ResponseFormatter> {
+        self.output.formatter(self.output_options())
+    }
 }
 impl Cli {
     pub fn command(&self) -> Commands {
-        self.command
-            .clone()
-            .unwrap_or(Commands::List { list: vec![] })
+        self.command.clone().unwrap_or(Commands::List {
+            list: vec![],
+            output: ListFormat::Text,
+            predicates: MethodPredicates::default(),
+        })
+    }
+    fn descriptor_source(&self) -> DescriptorSource {
+        if !self.protoset.is_empty() {
+            DescriptorSource::Protoset(self.protoset.clone())
+        } else if !self.proto.is_empty() {
+            DescriptorSource::Protos {
+                files: self.proto.clone(),
+                import_paths: self.import_paths.clone(),
+            }
+        } else {
+            DescriptorSource::Reflection
+        }
+    }
+    /// connect to the server, with the local descriptors instead of reflection when some are given
+    async fn connect(&self) -> Result<Client, GrpcClientError> {
+        if let Some(path) = &self.replay {
+            return Ok(Client::replay(Player::new(Cassette::load(path)?)?));
+        }
+        let Some(url) = self.url.clone() else {
+            <Cli as CommandFactory>::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "--url is required to connect to a server",
+                )
+                .exit()
+        };
+        let mut client = Client::with_protocol(url, self.protocol).await?;
+        if let Some(pool) = self.descriptor_source().load_local() {
+            client.set_descriptor_pool(pool?);
+        }
+        if self.record.is_some() {
+            client.record(self.recorder.clone());
+        }
+        Ok(client)
+    }
+    /// the local descriptors, or the one of the server when none is given
+    async fn descriptor_pool(&self) -> Result<DescriptorPool, GrpcClientError> {
+        match self.descriptor_source().load_local() {
+            Some(pool) => pool,
+            None => self.connect().await?.descriptor_pool().await,
+        }
+    }
+    /// build the options shared by every call from the global arguments
+    fn call_options(&self) -> Result<CallOptions, GrpcClientError> {
+        let mut options = CallOptions::new();
+        for (key, value) in &self.headers {
+            options = options.header(key, value)?;
+        }
+        if let Some(max_time) = self.max_time {
+            options = options.timeout(max_time);
+        }
+        if let Some(encoding) = self.compress {
+            options = options.compression(encoding);
+        }
+        for encoding in SUPPORTED_COMPRESSIONS {
+            options = options.accept_compression(encoding);
+        }
+        if self.retry > 1 {
+            options = options.retry(
+                RetryPolicy::default()
+                    .max_attempts(self.retry)
+                    .initial_backoff(self.retry_backoff)
+                    .max_backoff(self.retry_max_backoff)
+                    .multiplier(self.retry_multiplier)?
+                    .jitter(self.retry_jitter)
+                    .retryable_codes(self.retry_on.clone()),
+            );
+        }
+        Ok(options)
     }
 }
 fn parse_url(s: &str) -> Result<String, String> {
@@ -60,6 +491,32 @@ fn parse_url(s: &str) -> Result<String, String> {
         Ok(format!("https://{}", s))
     }
 }
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s
+        .parse()
+        .map_err(|e| format!("invalid number of seconds `{s}`: {e}"))?;
+    Duration::try_from_secs_f64(seconds).map_err(|e| format!("invalid duration `{s}`: {e}"))
+}
+fn parse_rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(rate) if rate.is_finite() && rate > 0.0 => Ok(rate),
+        Ok(_) => Err(format!("invalid rate `{s}`: must be a number above 0")),
+        Err(e) => Err(format!("invalid rate `{s}`: {e}")),
+    }
+}
+fn parse_compression_encoding(s: &str) -> Result<CompressionEncoding, String> {
+    parse_compression(s)
+        .ok_or_else(|| format!("unsupported compression `{s}`, use gzip, deflate or zstd"))
+}
+fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (key, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header `{s}`, expected 'key: value'"))?;
+    Ok((key.trim().to_lowercase(), value.trim().to_string()))
+}
+fn parse_status_code(s: &str) -> Result<tonic::Code, String> {
+    parse_code(s).ok_or_else(|| format!("unknown grpc status code `{s}`"))
+}
 // Parse a single key-value pair to be used by clap.
 // taken from https://github.com/clap-rs/clap/blob/f45a32ec2c1506faf319d914d985927ed47b0b5e/examples/typed-derive.rs
 fn parse_key_val<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
@@ -75,43 +532,416 @@ where
     Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
 }
 
-async fn binary() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    env_logger::init();
+async fn binary(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
+    let mut logger = env_logger::Builder::from_default_env();
+    match cli.debug {
+        0 => {}
+        1 => {
+            logger.filter_level(LevelFilter::Info);
+        }
+        2 => {
+            logger.filter_level(LevelFilter::Debug);
+        }
+        _ => {
+            logger.filter_level(LevelFilter::Trace);
+        }
+    }
+    logger.init();
     info!("Starting the program");
-    let mut client = Client::new(cli.url.clone()).await?;
 
-    //println!("{:?}", proto_files);
-    match cli.command() {
-        Commands::List { list } => {
-            println!("filters : {:?}", list);
-            let filters = GrpcFilters::new(list);
+    let result = run_command(&cli).await;
+    if let Some(path) = &cli.record {
+        // saved even when the command failed, errors are recorded too
+        let saved = cli.recorder.cassette().save(path);
+        result?;
+        saved?;
+        return Ok(());
+    }
+    result
+}
 
-            //client.list_services_to_stdout(filters).await?;
-            let proto = client.get_proto_files().await?;
-            dbg!(&proto.get(0).unwrap().message_type.get(0).unwrap());
+async fn run_command(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
+    match cli.command() {
+        Commands::List {
+            list,
+            output,
+            predicates,
+        } => {
+            debug!("filters : {:?}", list);
+            let filters = predicates.apply(GrpcFilters::new(list.clone())?);
+            let pool = cli.descriptor_pool().await?;
+            match output {
+                ListFormat::Text => print!("{}", render_services(&pool, &filters)),
+                ListFormat::Json => {
+                    let packages = list_services(&pool, &filters);
+                    println!("{}", serde_json::to_string_pretty(&packages)?);
+                }
+                ListFormat::Yaml => {
+                    let packages = list_services(&pool, &filters);
+                    print!("{}", serde_yaml_ng::to_string(&packages)?);
+                }
+            }
+            Ok(())
+        }
+        Commands::Describe { symbol } => {
+            let found = match cli.descriptor_source().load_local() {
+                Some(pool) => Symbol::find(&pool?, &symbol)
+                    .ok_or_else(|| GrpcClientError::NotFoundError(format!("symbol '{symbol}'")))?,
+                None => cli.connect().await?.describe(&symbol).await?,
+            };
+            let article = match found.kind().starts_with(['a', 'e', 'i', 'o', 'u']) {
+                true => "an",
+                false => "a",
+            };
+            println!(
+                "{} is {article} {} in {}:",
+                found.full_name(),
+                found.kind(),
+                found.parent_file().name()
+            );
+            print!("{found}");
+            Ok(())
+        }
+        Commands::Health { service, watch } => {
+            let mut client = cli.connect().await?;
+            let options = cli.call_options()?;
+            let status = if watch {
+                let mut updates = std::pin::pin!(client.watch_health(&service, &options).await?);
+                let mut last = ServingStatus::Unknown;
+                let watching = async {
+                    while let Some(status) = updates.next().await {
+                        last = status?;
+                        println!("{}", last.as_str_name());
+                    }
+                    Ok::<_, GrpcClientError>(())
+                };
+                // --max-time bounds the watch, whether the server enforces the deadline or not
+                match options.timeout {
+                    Some(timeout) => {
+                        if let Ok(watched) = tokio::time::timeout(timeout, watching).await {
+                            watched?;
+                        }
+                    }
+                    None => watching.await?,
+                }
+                last
+            } else {
+                let status = client.check_health(&service, &options).await?;
+                println!("{}", status.as_str_name());
+                status
+            };
+            if status != ServingStatus::Serving {
+                return Err(GrpcClientError::NotServing(status).into());
+            }
             Ok(())
         }
         Commands::Get {
             service,
             method,
             arguments,
+            output,
+        } => {
+            let formatter = output.formatter();
+            let mut client = cli.connect().await?;
+            let mut options = cli.call_options()?;
+            if let Some(projection) = output.projection() {
+                options = options.projection(projection);
+            }
+            let started = Instant::now();
+            let result = match client
+                .request_with_options(&service, &method, arguments, &options)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    if cli.verbose {
+                        let metadata = client.last_request_metadata().unwrap_or(&options.metadata);
+                        print_verbose_error(metadata, &e, started.elapsed());
+                    }
+                    return Err(e.into());
+                }
+            };
+            if cli.verbose {
+                print_metadata("Request metadata sent", &result.request_metadata);
+                print_metadata("Response headers received", &result.headers);
+            }
+            for message in &result.messages {
+                if cli.verbose {
+                    eprintln!("\nResponse contents:");
+                }
+                formatter.write_message(message, &mut std::io::stdout().lock())?;
+            }
+            if cli.verbose {
+                print_verbose_summary(&result);
+            }
+            Ok(())
+        }
+        Commands::Bench {
+            service,
+            method,
+            arguments,
+            requests,
+            duration,
+            concurrency,
+            rps,
+            channels,
+            output,
         } => {
-            let response = client.request(&service, &method, arguments);
-            // Convert DynamicMessage → JSON string
-            let json = serde_json::to_string_pretty(&response.await?)?;
-            println!("Response as JSON:\n{}", json);
-            //println!("{:?}", response); */
+            let mut config = BenchConfig::default().concurrency(concurrency);
+            if let Some(requests) = requests {
+                config = config.requests(requests);
+            }
+            if let Some(duration) = duration {
+                config = config.duration(duration);
+            }
+            if let Some(rps) = rps {
+                config = config.rate(rps)?;
+            }
+            // the descriptors are fetched once, then shared by every channel
+            let mut first = cli.connect().await?;
+            let pool = first.descriptor_pool().await?;
+            let mut clients = vec![first];
+            for _ in 1..channels {
+                let mut client = cli.connect().await?;
+                client.set_descriptor_pool(pool.clone());
+                clients.push(client);
+            }
+            let options = cli.call_options()?;
+            let report =
+                bench::run(clients, &service, &method, arguments, &options, &config).await?;
+            match output {
+                BenchFormat::Text => print!("{report}"),
+                BenchFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                BenchFormat::Csv => print!("{}", report.to_csv()),
+            }
             Ok(())
         }
+        Commands::Batch {
+            file,
+            concurrency,
+            stop_on_error,
+        } => {
+            let input = match file.to_str() {
+                Some("-") => {
+                    let mut input = String::new();
+                    std::io::stdin().read_to_string(&mut input)?;
+                    input
+                }
+                _ => std::fs::read_to_string(&file).map_err(|e| {
+                    GrpcClientError::InvalidInput(format!("cannot read {}: {e}", file.display()))
+                })?,
+            };
+            let mut client = cli.connect().await?;
+            let config = BatchConfig::default()
+                .concurrency(concurrency)
+                .stop_on_error(stop_on_error);
+            let summary = batch::run(
+                &mut client,
+                &input,
+                &cli.call_options()?,
+                &config,
+                |result| {
+                    let line = serde_json::to_string(result).expect("batch results are valid json");
+                    println!("{line}");
+                },
+            )
+            .await?;
+            if cli.verbose {
+                eprintln!(
+                    "\n{} succeeded, {} failed, {} skipped",
+                    summary.succeeded, summary.failed, summary.skipped
+                );
+            }
+            match summary.first_error {
+                Some(error) => Err(error.into()),
+                None => Ok(()),
+            }
+        }
+        Commands::Fuzz {
+            service,
+            method,
+            requests,
+            seed,
+            max_depth,
+            max_entries,
+        } => {
+            let mut config = FuzzConfig::default()
+                .requests(requests)
+                .max_depth(max_depth)
+                .max_entries(max_entries);
+            if let Some(seed) = seed {
+                config = config.seed(seed);
+            }
+            let mut client = cli.connect().await?;
+            let report = fuzz::run(
+                &mut client,
+                &service,
+                &method,
+                &cli.call_options()?,
+                &config,
+                |finding| {
+                    let line = serde_json::to_string(finding).expect("findings are valid json");
+                    println!("{line}");
+                },
+            )
+            .await?;
+            eprint!("{report}");
+            match report.first_error {
+                Some(error) => Err(error.into()),
+                None => Ok(()),
+            }
+        }
+        Commands::Mock {
+            listen,
+            rules,
+            no_random,
+        } => {
+            let rules = match rules {
+                Some(path) => load_rules(&path)?,
+                None => Vec::new(),
+            };
+            let mock = MockServer::new(cli.descriptor_pool().await?)
+                .rules(rules)?
+                .random(!no_random);
+            let incoming = TcpIncoming::bind(listen).map_err(|e| {
+                GrpcClientError::ServerError(format!("cannot listen on {listen}: {e}"))
+            })?;
+            // the port really used, when 0 was given
+            let address = incoming.local_addr().unwrap_or(listen);
+            eprintln!("mock server listening on http://{address}");
+            mock.serve(incoming).await?;
+            Ok(())
+        }
+        Commands::Gateway { listen } => {
+            let mut client = cli.connect().await?;
+            let pool = client.descriptor_pool().await?;
+            let gateway = Gateway::new(client, pool).options(cli.call_options()?);
+            let listener = tokio::net::TcpListener::bind(listen).await.map_err(|e| {
+                GrpcClientError::ServerError(format!("cannot listen on {listen}: {e}"))
+            })?;
+            let address = listener.local_addr().unwrap_or(listen);
+            for route in gateway.routes() {
+                eprintln!("{route}");
+            }
+            eprintln!("gateway listening on http://{address}");
+            gateway.serve(listener).await?;
+            Ok(())
+        }
+        Commands::Encode {
+            message_type,
+            input,
+            encoding,
+        } => {
+            let desc = find_message(&cli.descriptor_pool().await?, &message_type)?;
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text)?;
+            let message = parse_message(desc, &text, input)?;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&encode_message(&message, encoding))?;
+            if encoding != BytesEncoding::Binary {
+                writeln!(stdout)?;
+            }
+            Ok(())
+        }
+        Commands::Decode {
+            message_type,
+            encoding,
+            output,
+        } => {
+            let desc = find_message(&cli.descriptor_pool().await?, &message_type)?;
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes)?;
+            let mut message = decode_message(desc, &bytes, encoding)?;
+            if let Some(projection) = output.projection() {
+                projection.validate(&message.descriptor())?;
+                message = projection.apply(&message);
+            }
+            output
+                .formatter()
+                .write_message(&message, &mut std::io::stdout().lock())?;
+            Ok(())
+        }
+        Commands::DecodeRaw { encoding, output } => {
+            let mut input = Vec::new();
+            std::io::stdin().read_to_end(&mut input)?;
+            let message = decode_raw_message(&encoding.decode(&input)?)?;
+            output
+                .formatter(OutputOptions::default())
+                .write_message(&message, &mut std::io::stdout().lock())?;
+            Ok(())
+        }
+    }
+}
+fn find_message(pool: &DescriptorPool, name: &str) -> Result<MessageDescriptor, GrpcClientError> {
+    pool.get_message_by_name(name.trim_start_matches('.'))
+        .ok_or_else(|| GrpcClientError::NotFoundError(format!("message '{name}'")))
+}
+fn print_metadata(title: &str, metadata: &MetadataMap) {
+    eprintln!("\n{title}:");
+    let headers = metadata.clone().into_headers();
+    if headers.is_empty() {
+        eprintln!("(empty)");
+    }
+    for (key, value) in &headers {
+        eprintln!("{key}: {}", value.to_str().unwrap_or("<non ascii value>"));
     }
 }
+fn print_verbose_summary(result: &CallResult) {
+    print_metadata("Response trailers received", &result.trailers);
+    eprintln!(
+        "\nSent 1 request and received {} response(s) in {} attempt(s)",
+        result.messages.len(),
+        result.attempts
+    );
+    eprintln!("Status code: {:?}", result.code);
+    if !result.status_message.is_empty() {
+        eprintln!("Status message: {}", result.status_message);
+    }
+    eprintln!("Elapsed: {:?}", result.elapsed);
+}
+fn print_verbose_error(metadata: &MetadataMap, e: &GrpcClientError, elapsed: Duration) {
+    match e {
+        GrpcClientError::ReflectionRequestError(status)
+        | GrpcClientError::StatusWithDetails(status, _) => {
+            print_metadata("Request metadata sent", metadata);
+            print_metadata("Response trailers received", status.metadata());
+            eprintln!("\nStatus code: {:?}", status.code());
+            eprintln!("Status message: {}", status.message());
+        }
+        GrpcClientError::DeadlineExceeded(_) => {
+            print_metadata("Request metadata sent", metadata);
+            eprintln!("\nStatus code: {:?}", tonic::Code::DeadlineExceeded);
+        }
+        // the call was not sent, there is nothing more to show
+        _ => {}
+    }
+    eprintln!("Elapsed: {elapsed:?}");
+}
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    let format_error = cli.format_error;
     // allow to properly print thiserror errors
-    if let Err(e) = binary().await {
-        eprintln!("{}", e.to_string());
-        std::process::exit(1);
+    if let Err(e) = binary(cli).await {
+        let grpc_error = e.downcast_ref::<GrpcClientError>();
+        let code = grpc_error.map_or(exit_code::UNEXPECTED, |e| e.exit_code());
+        match format_error {
+            ErrorFormat::Text => eprintln!("{}", e),
+            ErrorFormat::Json => {
+                let json = grpc_error.map_or_else(
+                    || {
+                        serde_json::json!({
+                            "error": "unexpected",
+                            "message": e.to_string(),
+                            "exit_code": code,
+                        })
+                    },
+                    |e| e.to_json(),
+                );
+                eprintln!("{json}");
+            }
+        }
+        std::process::exit(code);
     }
-    std::process::exit(0);
+    std::process::exit(exit_code::SUCCESS);
 }
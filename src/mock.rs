@@ -0,0 +1,389 @@
+//! A grpc server implementing every method of a descriptor pool, to stand in for the real one.
+//!
+//! A call is answered by the first [`Rule`] matching its method and request, otherwise with a random
+//! message of the output type (see [`crate::random_message`]). The server also serves reflection, so
+//! clients discover its services like the ones of the real server.
+//!
+//! Rules are read from yaml or json:
+//!
+//! ```yaml
+//! - method: catalog.v1.Catalog.GetProduct
+//!   match: {id: p-42}
+//!   response: {id: p-42, title: Lamp}
+//! - method: GetProduct
+//!   status: not-found
+//!   message: no such product
+//! - method: ListProducts
+//!   responses: [{id: p-1}, {id: p-2}]
+//! ```
+use std::cell::OnceCell;
+use std::path::Path;
+use std::sync::Arc;
+
+use prost_reflect::{
+    DescriptorPool, DeserializeOptions, DynamicMessage, MessageDescriptor, MethodDescriptor,
+    SerializeOptions,
+};
+use rand::RngExt;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::server::Grpc;
+use tonic::service::Routes;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic::{Code, Request, Response, Status, Streaming};
+use tower::service_fn;
+use tracing::log::info;
+
+use crate::call_options::SUPPORTED_COMPRESSIONS;
+use crate::client::GrpcClientError;
+use crate::dynamic_codec::DynamicCodec;
+use crate::filter::Pattern;
+use crate::random_message::MessageGenerator;
+use crate::reflection::PoolReflection;
+use crate::retry::parse_code;
+
+/// most random messages sent by a server streaming method for one request
+const MAX_RANDOM_RESPONSES: usize = 3;
+
+/// How the calls of some methods are answered, as written in a rules file.
+///
+/// ```
+/// use grpc_client::mock::Rule;
+/// let rules: Vec<Rule> = serde_yaml_ng::from_str("- {method: 'Get*', status: not-found}").unwrap();
+/// assert_eq!(rules[0].status.as_deref(), Some("not-found"));
+/// assert!(serde_yaml_ng::from_str::<Vec<Rule>>("- {method: Get, reply: {}}").is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// full or short name of the methods answered, a glob or a `/regex/`, ex: `catalog.v1.*`
+    pub method: String,
+    /// fields the request must have, in the protobuf json mapping. Any request matches when missing
+    #[serde(default, rename = "match")]
+    pub request: Option<Value>,
+    /// message returned, an empty one when no response is given
+    #[serde(default)]
+    pub response: Option<Value>,
+    /// messages returned in order, for server streaming methods
+    #[serde(default)]
+    pub responses: Vec<Value>,
+    /// status code of the error returned instead of a message, ex: `not-found` or `5`
+    #[serde(default)]
+    pub status: Option<String>,
+    /// message of the error status
+    #[serde(default)]
+    pub message: String,
+}
+
+/// read a list of rules from a yaml or json file
+pub fn load_rules(path: &Path) -> Result<Vec<Rule>, GrpcClientError> {
+    let text = std::fs::read_to_string(path).map_err(|e| {
+        GrpcClientError::InvalidInput(format!("cannot read {}: {e}", path.display()))
+    })?;
+    serde_yaml_ng::from_str(&text).map_err(|e| {
+        GrpcClientError::InvalidInput(format!("invalid rules {}: {e}", path.display()))
+    })
+}
+
+/// a rule checked against the methods it answers
+#[derive(Debug)]
+struct CompiledRule {
+    method: Pattern,
+    request: Option<Value>,
+    responses: Vec<Value>,
+    status: Option<Code>,
+    message: String,
+}
+
+/// Answers the calls of every method of a pool, see the [module documentation](self).
+///
+/// ```
+/// use grpc_client::mock::{MockServer, Rule};
+/// use prost_reflect::{DescriptorPool, DynamicMessage};
+/// let pool = DescriptorPool::decode(tonic_health::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let rule = Rule {
+///     method: "Check".to_string(),
+///     response: Some(serde_json::json!({"status": "NOT_SERVING"})),
+///     ..Rule::default()
+/// };
+/// let mock = MockServer::new(pool.clone()).rules(vec![rule]).unwrap();
+/// let check = pool.get_service_by_name("grpc.health.v1.Health").unwrap().methods().next().unwrap();
+/// let answer = mock.answer(&check, &DynamicMessage::new(check.input())).unwrap();
+/// assert_eq!(serde_json::to_string(&answer[0]).unwrap(), r#"{"status":"NOT_SERVING"}"#);
+/// ```
+#[derive(Debug)]
+pub struct MockServer {
+    pool: DescriptorPool,
+    rules: Vec<CompiledRule>,
+    random: bool,
+}
+
+impl MockServer {
+    pub fn new(pool: DescriptorPool) -> Self {
+        Self {
+            pool,
+            rules: Vec::new(),
+            random: true,
+        }
+    }
+    /// add rules, checked against the methods they match: a rule matching no method, or whose
+    /// messages are not valid for one of its methods, is an error
+    pub fn rules(mut self, rules: Vec<Rule>) -> Result<Self, GrpcClientError> {
+        for rule in rules {
+            let rule = self.compile(rule)?;
+            self.rules.push(rule);
+        }
+        Ok(self)
+    }
+    /// answer the calls no rule matches with random messages, else with `UNIMPLEMENTED`
+    pub fn random(mut self, yes: bool) -> Self {
+        self.random = yes;
+        self
+    }
+    fn compile(&self, rule: Rule) -> Result<CompiledRule, GrpcClientError> {
+        let name = rule.method.clone();
+        let invalid = |reason: String| {
+            GrpcClientError::InvalidInput(format!("invalid rule for '{name}': {reason}"))
+        };
+        let method: Pattern = rule.method.parse()?;
+        let methods: Vec<MethodDescriptor> = self
+            .pool
+            .services()
+            .flat_map(|service| service.methods().collect::<Vec<_>>())
+            .filter(|candidate| method.matches_symbol(candidate.full_name()))
+            .collect();
+        if methods.is_empty() {
+            return Err(invalid("no method matches".to_string()));
+        }
+        let status = match rule.status.as_deref() {
+            Some(code) => Some(
+                parse_code(code).ok_or_else(|| invalid(format!("unknown status code '{code}'")))?,
+            ),
+            None => None,
+        };
+        if rule.response.is_some() && !rule.responses.is_empty() {
+            return Err(invalid("set response or responses, not both".to_string()));
+        }
+        let responses: Vec<Value> = rule.response.into_iter().chain(rule.responses).collect();
+        if status.is_some_and(|code| code != Code::Ok) && !responses.is_empty() {
+            return Err(invalid("an error status has no response".to_string()));
+        }
+        for method in &methods {
+            if let Some(request) = &rule.request {
+                json_to_message(method.input(), request).map_err(invalid)?;
+            }
+            if responses.len() > 1 && !method.is_server_streaming() {
+                return Err(invalid(format!(
+                    "{} returns one message",
+                    method.full_name()
+                )));
+            }
+            for response in &responses {
+                json_to_message(method.output(), response).map_err(invalid)?;
+            }
+        }
+        Ok(CompiledRule {
+            method,
+            request: rule.request,
+            responses,
+            status,
+            message: rule.message,
+        })
+    }
+    /// the messages sent back for this request, at least one, or the error status of the call
+    pub fn answer(
+        &self,
+        method: &MethodDescriptor,
+        request: &DynamicMessage,
+    ) -> Result<Vec<DynamicMessage>, Status> {
+        let requests = OnceCell::new();
+        let rule = self.rules.iter().find(|rule| {
+            rule.method.matches_symbol(method.full_name())
+                && rule.request.as_ref().is_none_or(|fields| {
+                    // a request without a json mapping, like an Any of an unknown type, matches no fields
+                    requests
+                        .get_or_init(|| request_json(request))
+                        .iter()
+                        .any(|request| contains(request, fields))
+                })
+        });
+        match rule {
+            Some(rule) => {
+                if let Some(code) = rule.status.filter(|code| *code != Code::Ok) {
+                    return Err(Status::new(code, rule.message.clone()));
+                }
+                if rule.responses.is_empty() {
+                    return Ok(vec![DynamicMessage::new(method.output())]);
+                }
+                rule.responses
+                    .iter()
+                    .map(|response| json_to_message(method.output(), response))
+                    .collect::<Result<_, _>>()
+                    .map_err(Status::internal)
+            }
+            None if self.random => {
+                let count = match method.is_server_streaming() {
+                    true => rand::rng().random_range(1..=MAX_RANDOM_RESPONSES),
+                    false => 1,
+                };
+                let mut generator = MessageGenerator::new();
+                Ok((0..count)
+                    .map(|_| generator.message(&method.output()))
+                    .collect())
+            }
+            None => Err(Status::unimplemented(format!(
+                "no rule answers {}",
+                method.full_name()
+            ))),
+        }
+    }
+    /// serve every method and reflection on `incoming`, until the server fails
+    pub async fn serve(self, incoming: TcpIncoming) -> Result<(), GrpcClientError> {
+        // the files keep their extension options, like google.api.http
+        let v1 = PoolReflection::new(self.pool.clone()).into_service();
+        // the older clients get the tonic service, which drops them
+        let files = self.pool.encode_to_vec();
+        let v1alpha = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(&files)
+            .build_v1alpha()
+            .map_err(|e| {
+                GrpcClientError::ServerError(format!("invalid descriptors for reflection: {e}"))
+            })?;
+        let mock = Arc::new(self);
+        // the reflection routes come first, every other path is a method of the pool
+        let router = Routes::new(v1)
+            .add_service(v1alpha)
+            .into_axum_router()
+            .fallback(move |request: http::Request<axum::body::Body>| mock.clone().handle(request));
+        Server::builder()
+            .add_routes(Routes::from(router))
+            .serve_with_incoming(incoming)
+            .await
+            .map_err(|e| GrpcClientError::ServerError(e.to_string()))
+    }
+    async fn handle(
+        self: Arc<Self>,
+        request: http::Request<axum::body::Body>,
+    ) -> http::Response<tonic::body::Body> {
+        let path = request.uri().path().to_string();
+        let Some(method) = self.find_method(&path) else {
+            return Status::unimplemented(format!("unknown method {path}")).into_http();
+        };
+        info!("mock call of {}", method.full_name());
+        let mut grpc = Grpc::new(DynamicCodec {
+            pool: self.pool.clone(),
+            message_name: method.input().full_name().to_string(),
+            json: false,
+        });
+        for encoding in SUPPORTED_COMPRESSIONS {
+            grpc = grpc.accept_compressed(encoding).send_compressed(encoding);
+        }
+        match (method.is_client_streaming(), method.is_server_streaming()) {
+            (false, false) => {
+                let service = service_fn(move |request: Request<DynamicMessage>| {
+                    let answer = self.answer(&method, request.get_ref());
+                    async move { Ok(Response::new(answer?.swap_remove(0))) }
+                });
+                grpc.unary(service, request).await
+            }
+            (false, true) => {
+                let service = service_fn(move |request: Request<DynamicMessage>| {
+                    let answer = self.answer(&method, request.get_ref());
+                    async move {
+                        Ok(Response::new(tokio_stream::iter(
+                            answer?.into_iter().map(Ok),
+                        )))
+                    }
+                });
+                grpc.server_streaming(service, request).await
+            }
+            // the answer depends on the last message received
+            (true, false) => {
+                let service = service_fn(move |request: Request<Streaming<DynamicMessage>>| {
+                    let (mock, method) = (self.clone(), method.clone());
+                    async move {
+                        let mut requests = request.into_inner();
+                        let mut last = DynamicMessage::new(method.input());
+                        while let Some(request) = requests.message().await? {
+                            last = request;
+                        }
+                        Ok(Response::new(mock.answer(&method, &last)?.swap_remove(0)))
+                    }
+                });
+                grpc.client_streaming(service, request).await
+            }
+            // each message received is answered as soon as it arrives
+            (true, true) => {
+                let service = service_fn(move |request: Request<Streaming<DynamicMessage>>| {
+                    let (mock, method) = (self.clone(), method.clone());
+                    let mut requests = request.into_inner();
+                    let (sender, receiver) = mpsc::channel(MAX_RANDOM_RESPONSES);
+                    tokio::spawn(async move {
+                        loop {
+                            let answer = match requests.message().await {
+                                Ok(Some(request)) => mock.answer(&method, &request),
+                                Ok(None) => return,
+                                Err(status) => Err(status),
+                            };
+                            let responses = match answer {
+                                Ok(messages) => messages.into_iter().map(Ok).collect(),
+                                Err(status) => vec![Err(status)],
+                            };
+                            for response in responses {
+                                let failed = response.is_err();
+                                if sender.send(response).await.is_err() || failed {
+                                    return;
+                                }
+                            }
+                        }
+                    });
+                    async move { Ok::<_, Status>(Response::new(ReceiverStream::new(receiver))) }
+                });
+                grpc.streaming(service, request).await
+            }
+        }
+    }
+    /// the method called on this path, ex: `/helloworld.Greeter/SayHello`
+    fn find_method(&self, path: &str) -> Option<MethodDescriptor> {
+        let (service, method) = path.strip_prefix('/')?.split_once('/')?;
+        self.pool
+            .get_service_by_name(service)?
+            .methods()
+            .find(|candidate| candidate.name() == method)
+    }
+}
+
+fn json_to_message(desc: MessageDescriptor, json: &Value) -> Result<DynamicMessage, String> {
+    DynamicMessage::deserialize_with_options(desc, json, &DeserializeOptions::new())
+        .map_err(|e| format!("invalid {}: {e}", json))
+}
+
+/// the request with the json names of its fields, and with the ones of the proto file, since a
+/// rule may use either; empty when it has no json mapping
+fn request_json(request: &DynamicMessage) -> Vec<Value> {
+    [false, true]
+        .into_iter()
+        .map(|proto_names| {
+            let options = SerializeOptions::new()
+                .skip_default_fields(false)
+                .use_proto_field_name(proto_names);
+            request.serialize_with_options(serde_json::value::Serializer, &options)
+        })
+        .collect::<Result<_, _>>()
+        .unwrap_or_default()
+}
+
+/// whether `actual` has every field of `expected` with the same value
+fn contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected
+            .iter()
+            .all(|(key, value)| actual.get(key).is_some_and(|field| contains(field, value))),
+        // 64 bits integers are printed as json strings
+        (Value::String(actual), Value::Number(expected)) => *actual == expected.to_string(),
+        (actual, expected) => actual == expected,
+    }
+}
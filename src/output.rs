@@ -0,0 +1,398 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use prost::Message;
+use prost_reflect::text_format::FormatOptions;
+use prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, SerializeOptions,
+};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::raw_decode;
+
+/// How response messages are rendered as json.
+///
+/// The default follows the protobuf json mapping: camelCase names, enums by name,
+/// 64 bits integers as strings and fields with a default value left out.
+///
+/// ```
+/// use grpc_client::OutputOptions;
+/// use prost_reflect::{DescriptorPool, DynamicMessage};
+/// let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let message = DynamicMessage::new(pool.get_message_by_name("google.rpc.ErrorInfo").unwrap());
+/// assert_eq!(OutputOptions::default().compact(true).to_json(&message).unwrap(), "{}");
+/// let all_fields = OutputOptions::default().compact(true).emit_defaults(true);
+/// assert_eq!(all_fields.to_json(&message).unwrap(), r#"{"reason":"","domain":"","metadata":{}}"#);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OutputOptions {
+    /// also print fields set to their default value
+    pub emit_defaults: bool,
+    /// use the field names of the proto file instead of their camelCase json name
+    pub use_proto_names: bool,
+    /// print enums as their number instead of their name
+    pub enums_as_ints: bool,
+    /// print the json on a single line
+    pub compact: bool,
+    /// print 64 bits integers as json strings, as javascript can't read them as numbers without losing precision
+    pub stringify_64bit: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            emit_defaults: false,
+            use_proto_names: false,
+            enums_as_ints: false,
+            compact: false,
+            stringify_64bit: true,
+        }
+    }
+}
+
+impl OutputOptions {
+    pub fn emit_defaults(mut self, yes: bool) -> Self {
+        self.emit_defaults = yes;
+        self
+    }
+    pub fn use_proto_names(mut self, yes: bool) -> Self {
+        self.use_proto_names = yes;
+        self
+    }
+    pub fn enums_as_ints(mut self, yes: bool) -> Self {
+        self.enums_as_ints = yes;
+        self
+    }
+    pub fn compact(mut self, yes: bool) -> Self {
+        self.compact = yes;
+        self
+    }
+    pub fn stringify_64bit(mut self, yes: bool) -> Self {
+        self.stringify_64bit = yes;
+        self
+    }
+
+    /// the prost-reflect options matching these settings
+    pub fn serialize_options(&self) -> SerializeOptions {
+        SerializeOptions::new()
+            .skip_default_fields(!self.emit_defaults)
+            .use_proto_field_name(self.use_proto_names)
+            .use_enum_numbers(self.enums_as_ints)
+            .stringify_64_bit_integers(self.stringify_64bit)
+    }
+
+    /// render a message as a json string
+    pub fn to_json(&self, message: &DynamicMessage) -> Result<String, serde_json::Error> {
+        if let Some(raw) = raw_json(message) {
+            return match self.compact {
+                true => serde_json::to_string(&raw),
+                false => serde_json::to_string_pretty(&raw),
+            };
+        }
+        let mut out = Vec::new();
+        if self.compact {
+            let mut serializer = serde_json::Serializer::new(&mut out);
+            message.serialize_with_options(&mut serializer, &self.serialize_options())?;
+        } else {
+            let mut serializer = serde_json::Serializer::pretty(&mut out);
+            message.serialize_with_options(&mut serializer, &self.serialize_options())?;
+        }
+        Ok(String::from_utf8(out).expect("serde_json always write valid utf8"))
+    }
+
+    /// render a message as a json value, the `compact` setting is not relevant here
+    pub fn to_json_value(
+        &self,
+        message: &DynamicMessage,
+    ) -> Result<serde_json::Value, serde_json::Error> {
+        if let Some(raw) = raw_json(message) {
+            return Ok(raw);
+        }
+        message.serialize_with_options(serde_json::value::Serializer, &self.serialize_options())
+    }
+}
+
+/// a message decoded without its type has no known field, its raw fields are shown instead
+fn raw_json(message: &DynamicMessage) -> Option<Value> {
+    if !raw_decode::is_raw_message(message) {
+        return None;
+    }
+    let fields = raw_decode::decode_raw(&message.encode_to_vec()).unwrap_or_default();
+    Some(raw_decode::to_json(&fields))
+}
+
+#[derive(Error, Debug)]
+pub enum OutputError {
+    #[error("could not write the response: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not render the response as json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("could not render the response as yaml: {0}")]
+    Yaml(#[from] serde_yaml_ng::Error),
+}
+
+/// Render response messages to a writer, see [`OutputFormat::formatter`] to get one.
+pub trait ResponseFormatter {
+    /// write a single message, followed by a new line for textual formats
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError>;
+}
+
+/// Every output format known by [`OutputFormat::formatter`]
+///
+/// ```
+/// use grpc_client::output::OutputFormat;
+/// assert_eq!("yaml".parse::<OutputFormat>(), Ok(OutputFormat::Yaml));
+/// assert!("xml".parse::<OutputFormat>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// protobuf json mapping, tuned by [`OutputOptions`]
+    #[default]
+    Json,
+    /// same document as json, written as yaml
+    Yaml,
+    /// protobuf text format, as printed by protoc
+    Text,
+    /// repeated message fields as aligned columns
+    Table,
+    /// raw serialized protobuf bytes, to pipe into other tools
+    Binary,
+    /// serialized protobuf bytes in hexadecimal
+    Hex,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 6] = [
+        OutputFormat::Json,
+        OutputFormat::Yaml,
+        OutputFormat::Text,
+        OutputFormat::Table,
+        OutputFormat::Binary,
+        OutputFormat::Hex,
+    ];
+
+    /// formatter writing messages in this format, `options` only matter for json, yaml and table
+    pub fn formatter(self, options: OutputOptions) -> Box<dyn ResponseFormatter> {
+        match self {
+            OutputFormat::Json => Box::new(JsonFormatter(options)),
+            OutputFormat::Yaml => Box::new(YamlFormatter(options)),
+            OutputFormat::Text => Box::new(TextFormatter),
+            OutputFormat::Table => Box::new(TableFormatter(options)),
+            OutputFormat::Binary => Box::new(BinaryFormatter),
+            OutputFormat::Hex => Box::new(HexFormatter),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Text => "text",
+            OutputFormat::Table => "table",
+            OutputFormat::Binary => "binary",
+            OutputFormat::Hex => "hex",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OutputFormat::ALL
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!("unknown output format `{s}`, use json, yaml, text, table, binary or hex")
+            })
+    }
+}
+
+pub struct JsonFormatter(pub OutputOptions);
+
+impl ResponseFormatter for JsonFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        writeln!(out, "{}", self.0.to_json(message)?)?;
+        Ok(())
+    }
+}
+
+pub struct YamlFormatter(pub OutputOptions);
+
+impl ResponseFormatter for YamlFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        let value = self.0.to_json_value(message)?;
+        write!(out, "---\n{}", serde_yaml_ng::to_string(&value)?)?;
+        Ok(())
+    }
+}
+
+pub struct TextFormatter;
+
+impl ResponseFormatter for TextFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        if raw_decode::is_raw_message(message) {
+            let fields = raw_decode::decode_raw(&message.encode_to_vec()).unwrap_or_default();
+            write!(out, "{}", raw_decode::RawDisplay(&fields))?;
+            return Ok(());
+        }
+        let text = message.to_text_format_with_options(&FormatOptions::new().pretty(true));
+        writeln!(out, "{text}")?;
+        Ok(())
+    }
+}
+
+/// Fields of the message are printed as a single row, then each repeated message field is printed
+/// as its own table, with a column per field of the repeated message.
+pub struct TableFormatter(pub OutputOptions);
+
+impl TableFormatter {
+    fn column_names(&self, desc: &MessageDescriptor) -> Vec<String> {
+        desc.fields()
+            .map(|field| match self.0.use_proto_names {
+                true => field.name().to_string(),
+                false => field.json_name().to_string(),
+            })
+            .collect()
+    }
+}
+
+impl ResponseFormatter for TableFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        let desc = message.descriptor();
+        let json = self.0.to_json_value(message)?;
+        let is_table =
+            |field: &FieldDescriptor| field.is_list() && matches!(field.kind(), Kind::Message(_));
+
+        let (header, row): (Vec<String>, Vec<String>) = desc
+            .fields()
+            .zip(self.column_names(&desc))
+            .filter(|(field, _)| !is_table(field))
+            .map(|(_, name)| {
+                let cell = cell(json.get(&name));
+                (name, cell)
+            })
+            .unzip();
+        if !header.is_empty() {
+            write_table(out, &header, &[row])?;
+        }
+
+        for (field, name) in desc.fields().zip(self.column_names(&desc)) {
+            let Kind::Message(item_desc) = field.kind() else {
+                continue;
+            };
+            if !is_table(&field) {
+                continue;
+            }
+            let header = self.column_names(&item_desc);
+            let rows: Vec<Vec<String>> = json
+                .get(&name)
+                .and_then(Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| header.iter().map(|column| cell(item.get(column))).collect())
+                        .collect()
+                })
+                .unwrap_or_default();
+            writeln!(out, "\n{name}:")?;
+            write_table(out, &header, &rows)?;
+        }
+        Ok(())
+    }
+}
+
+/// text of a table cell: strings without their quotes, anything else as compact json
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn write_table(
+    out: &mut dyn Write,
+    header: &[String],
+    rows: &[Vec<String>],
+) -> std::io::Result<()> {
+    let mut widths: Vec<usize> = header.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let write_row = |out: &mut dyn Write, cells: &[String]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        writeln!(out, "{}", line.join("  ").trim_end())
+    };
+    write_row(out, header)?;
+    let separator: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+    write_row(out, &separator)?;
+    for row in rows {
+        write_row(out, row)?;
+    }
+    Ok(())
+}
+
+/// Messages are written back to back without any delimiter, so this is meant for a single response.
+pub struct BinaryFormatter;
+
+impl ResponseFormatter for BinaryFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        out.write_all(&message.encode_to_vec())?;
+        Ok(())
+    }
+}
+
+/// one line of lowercase hexadecimal per message
+pub struct HexFormatter;
+
+impl ResponseFormatter for HexFormatter {
+    fn write_message(
+        &self,
+        message: &DynamicMessage,
+        out: &mut dyn Write,
+    ) -> Result<(), OutputError> {
+        let hex: String = message
+            .encode_to_vec()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        writeln!(out, "{hex}")?;
+        Ok(())
+    }
+}
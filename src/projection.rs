@@ -0,0 +1,204 @@
+//! Keep only some fields of a response, selected by their path.
+//!
+//! A path is a list of field names separated by dots, like `user.address.city`.
+//! A repeated message field is followed by `[]` to select fields of each of its items: `items[].id`.
+//! Field names can be written as in the proto file (`user_id`) or as their json name (`userId`).
+use std::fmt;
+use std::str::FromStr;
+
+use prost_reflect::{
+    DynamicMessage, FieldDescriptor, Kind, MessageDescriptor, ReflectMessage, Value,
+};
+
+use crate::client::GrpcClientError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    name: String,
+    /// written with `[]`, so the field must be repeated
+    each: bool,
+}
+
+impl Segment {
+    fn matches(&self, field: &FieldDescriptor) -> bool {
+        self.name == field.name() || self.name == field.json_name()
+    }
+}
+
+/// A path to a field, see the [module documentation](self) for the syntax.
+///
+/// ```
+/// use grpc_client::projection::FieldPath;
+/// assert!("items[].id".parse::<FieldPath>().is_ok());
+/// assert!("items..id".parse::<FieldPath>().is_err());
+/// assert!("items[0].id".parse::<FieldPath>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldPath {
+    segments: Vec<Segment>,
+}
+
+impl FromStr for FieldPath {
+    type Err = GrpcClientError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| GrpcClientError::InvalidFieldPath(s.to_string(), reason.to_string());
+        let segments = s
+            .split('.')
+            .map(|part| {
+                let (name, each) = match part.strip_suffix("[]") {
+                    Some(name) => (name, true),
+                    None => (part, false),
+                };
+                if name.is_empty() {
+                    return Err(invalid("empty field name"));
+                }
+                if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(invalid(
+                        "a field name only contains letters, digits and '_'",
+                    ));
+                }
+                Ok(Segment {
+                    name: name.to_string(),
+                    each,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(FieldPath { segments })
+    }
+}
+
+impl fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .segments
+            .iter()
+            .map(|s| {
+                if s.each {
+                    format!("{}[]", s.name)
+                } else {
+                    s.name.clone()
+                }
+            })
+            .collect();
+        f.write_str(&parts.join("."))
+    }
+}
+
+impl FieldPath {
+    /// check every field of the path exist, starting from the message `desc`
+    pub fn validate(&self, desc: &MessageDescriptor) -> Result<(), GrpcClientError> {
+        let invalid = |reason: String| GrpcClientError::InvalidFieldPath(self.to_string(), reason);
+        let mut current = desc.clone();
+        for (i, segment) in self.segments.iter().enumerate() {
+            let field = current
+                .fields()
+                .find(|field| segment.matches(field))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "no field '{}' in message {}",
+                        segment.name,
+                        current.full_name()
+                    ))
+                })?;
+            if segment.each && !field.is_list() {
+                return Err(invalid(format!("field '{}' is not repeated", segment.name)));
+            }
+            let is_last = i + 1 == self.segments.len();
+            if is_last {
+                break;
+            }
+            current = match field.kind() {
+                Kind::Message(inner) if !field.is_map() => inner,
+                _ => {
+                    return Err(invalid(format!(
+                        "field '{}' is not a message, it has no sub field",
+                        segment.name
+                    )));
+                }
+            };
+        }
+        Ok(())
+    }
+}
+
+/// A set of field paths to keep in a message, every other field is cleared.
+///
+/// ```
+/// use grpc_client::projection::Projection;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let bad_request = pool.get_message_by_name("google.rpc.BadRequest").unwrap();
+/// let projection = Projection::new(["fieldViolations[].field"]).unwrap();
+/// assert!(projection.validate(&bad_request).is_ok());
+/// let typo = Projection::new(["fieldViolations[].feild"]).unwrap();
+/// assert!(typo.validate(&bad_request).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Projection {
+    paths: Vec<FieldPath>,
+}
+
+impl Projection {
+    /// parse every path, see [`FieldPath`]
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a str>) -> Result<Self, GrpcClientError> {
+        Ok(Projection {
+            paths: paths
+                .into_iter()
+                .map(FieldPath::from_str)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+    pub fn from_paths(paths: Vec<FieldPath>) -> Self {
+        Projection { paths }
+    }
+    pub fn paths(&self) -> &[FieldPath] {
+        &self.paths
+    }
+    /// check every path against the message descriptor, to catch typos before making a call
+    pub fn validate(&self, desc: &MessageDescriptor) -> Result<(), GrpcClientError> {
+        self.paths.iter().try_for_each(|path| path.validate(desc))
+    }
+    /// copy of the message with only the selected fields
+    pub fn apply(&self, message: &DynamicMessage) -> DynamicMessage {
+        let paths: Vec<&[Segment]> = self.paths.iter().map(|p| p.segments.as_slice()).collect();
+        project(message, &paths)
+    }
+}
+
+fn project(message: &DynamicMessage, paths: &[&[Segment]]) -> DynamicMessage {
+    let desc = message.descriptor();
+    let mut projected = DynamicMessage::new(desc.clone());
+    for field in desc.fields() {
+        let sub_paths: Vec<&[Segment]> = paths
+            .iter()
+            .filter(|path| path.first().is_some_and(|segment| segment.matches(&field)))
+            .map(|path| &path[1..])
+            .collect();
+        if sub_paths.is_empty() || !message.has_field(&field) {
+            continue;
+        }
+        let value = message.get_field(&field);
+        // the whole field is selected by one of the paths
+        if sub_paths.iter().any(|path| path.is_empty()) {
+            projected.set_field(&field, value.into_owned());
+            continue;
+        }
+        let value = match value.as_ref() {
+            Value::Message(inner) => Value::Message(project(inner, &sub_paths)),
+            Value::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Value::Message(inner) => Value::Message(project(inner, &sub_paths)),
+                        other => other.clone(),
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        };
+        projected.set_field(&field, value);
+    }
+    projected
+}
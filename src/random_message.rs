@@ -0,0 +1,220 @@
+//! Random messages, valid for their descriptor: every field gets a value of its kind, enums one of
+//! their values, oneofs a single field, and the well known types values they can be printed with.
+//!
+//! Nested messages stop at a maximum depth, so recursive types give finite messages.
+//! A [`MessageGenerator`] built with a seed always generates the same messages, in the same order.
+use std::collections::HashMap;
+
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor, Value};
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+
+/// nested messages deeper than this are left out
+pub const DEFAULT_MAX_DEPTH: usize = 4;
+/// repeated fields and maps get up to this number of entries
+pub const DEFAULT_MAX_ENTRIES: usize = 3;
+/// strings and bytes get up to this number of characters
+pub const DEFAULT_MAX_LENGTH: usize = 12;
+const ANY: &str = "google.protobuf.Any";
+const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Generates random messages of any type.
+///
+/// ```
+/// use grpc_client::random_message::MessageGenerator;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let desc = pool.get_message_by_name("google.rpc.BadRequest").unwrap();
+/// let mut generator = MessageGenerator::new().seed(7).max_entries(2);
+/// let message = generator.message(&desc);
+/// // the same seed gives the same messages
+/// assert_eq!(MessageGenerator::new().seed(7).max_entries(2).message(&desc), message);
+/// assert!(serde_json::to_string(&message).is_ok());
+/// ```
+#[derive(Debug)]
+pub struct MessageGenerator {
+    rng: StdRng,
+    max_depth: usize,
+    max_entries: usize,
+    max_length: usize,
+}
+
+impl Default for MessageGenerator {
+    fn default() -> Self {
+        Self {
+            rng: rand::make_rng(),
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            max_length: DEFAULT_MAX_LENGTH,
+        }
+    }
+}
+
+impl MessageGenerator {
+    /// a generator seeded from the system, see [`MessageGenerator::seed`] for reproducible messages
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+    /// nesting depth of the deepest message, 0 leaves every message field out
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+    /// most entries of a repeated field or a map
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+    /// most characters of a string, or bytes of a bytes field
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length;
+        self
+    }
+    /// a random message of this type
+    pub fn message(&mut self, desc: &MessageDescriptor) -> DynamicMessage {
+        self.message_at(desc, 0)
+    }
+
+    fn message_at(&mut self, desc: &MessageDescriptor, depth: usize) -> DynamicMessage {
+        if let Some(well_known) = self.well_known(desc) {
+            return well_known;
+        }
+        let mut message = DynamicMessage::new(desc.clone());
+        for oneof in desc.oneofs().filter(|oneof| !oneof.is_synthetic()) {
+            let fields: Vec<FieldDescriptor> = oneof.fields().collect();
+            let field = &fields[self.rng.random_range(0..fields.len())];
+            if let Some(value) = self.field_value(field, depth) {
+                message.set_field(field, value);
+            }
+        }
+        for field in desc.fields() {
+            match field.containing_oneof() {
+                Some(oneof) if !oneof.is_synthetic() => continue,
+                // proto3 optional fields are set half of the time
+                Some(_) if self.rng.random_bool(0.5) => continue,
+                _ => {}
+            }
+            if let Some(value) = self.field_value(&field, depth) {
+                message.set_field(&field, value);
+            }
+        }
+        message
+    }
+
+    /// value of a field, none for a message too deep to be set
+    fn field_value(&mut self, field: &FieldDescriptor, depth: usize) -> Option<Value> {
+        if field.is_map() {
+            let Kind::Message(entry) = field.kind() else {
+                unreachable!("map fields are messages");
+            };
+            let (key_kind, value_kind) = (
+                entry.map_entry_key_field().kind(),
+                entry.map_entry_value_field().kind(),
+            );
+            let mut map = HashMap::new();
+            for _ in 0..self.rng.random_range(0..=self.max_entries) {
+                let key = self.map_key(&key_kind);
+                if let Some(value) = self.value(&value_kind, depth) {
+                    map.insert(key, value);
+                }
+            }
+            return Some(Value::Map(map));
+        }
+        if field.is_list() {
+            let kind = field.kind();
+            let values = (0..self.rng.random_range(0..=self.max_entries))
+                .filter_map(|_| self.value(&kind, depth))
+                .collect();
+            return Some(Value::List(values));
+        }
+        self.value(&field.kind(), depth)
+    }
+
+    fn value(&mut self, kind: &Kind, depth: usize) -> Option<Value> {
+        let rng = &mut self.rng;
+        Some(match kind {
+            Kind::Double => Value::F64(rng.random_range(-1e6..1e6)),
+            Kind::Float => Value::F32(rng.random_range(-1e6..1e6)),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(rng.random()),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(rng.random()),
+            Kind::Uint32 | Kind::Fixed32 => Value::U32(rng.random()),
+            Kind::Uint64 | Kind::Fixed64 => Value::U64(rng.random()),
+            Kind::Bool => Value::Bool(rng.random()),
+            Kind::String => Value::String(self.string()),
+            Kind::Bytes => {
+                let bytes: Vec<u8> = (0..rng.random_range(0..=self.max_length))
+                    .map(|_| rng.random())
+                    .collect();
+                Value::Bytes(bytes.into())
+            }
+            Kind::Enum(desc) => {
+                let values: Vec<i32> = desc.values().map(|value| value.number()).collect();
+                Value::EnumNumber(values[rng.random_range(0..values.len())])
+            }
+            // an Any without a type to pack has no json mapping, leave it out
+            Kind::Message(desc) if desc.full_name() == ANY && any_payload(desc).is_none() => {
+                return None;
+            }
+            Kind::Message(desc) if depth >= self.max_depth => {
+                return self.well_known(desc).map(Value::Message);
+            }
+            Kind::Message(desc) => Value::Message(self.message_at(desc, depth + 1)),
+        })
+    }
+
+    fn map_key(&mut self, kind: &Kind) -> MapKey {
+        match kind {
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => MapKey::I32(self.rng.random()),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => MapKey::I64(self.rng.random()),
+            Kind::Uint32 | Kind::Fixed32 => MapKey::U32(self.rng.random()),
+            Kind::Uint64 | Kind::Fixed64 => MapKey::U64(self.rng.random()),
+            Kind::Bool => MapKey::Bool(self.rng.random()),
+            _ => MapKey::String(self.string()),
+        }
+    }
+
+    fn string(&mut self) -> String {
+        (0..self.rng.random_range(0..=self.max_length))
+            .map(|_| CHARSET[self.rng.random_range(0..CHARSET.len())] as char)
+            .collect()
+    }
+
+    /// well known types whose json mapping restricts the values, none for the other messages
+    fn well_known(&mut self, desc: &MessageDescriptor) -> Option<DynamicMessage> {
+        let mut message = DynamicMessage::new(desc.clone());
+        let rng = &mut self.rng;
+        match desc.full_name() {
+            // between 1970 and 2100
+            "google.protobuf.Timestamp" => {
+                message
+                    .set_field_by_name("seconds", Value::I64(rng.random_range(0..4_102_444_800)));
+                message.set_field_by_name("nanos", Value::I32(rng.random_range(0..1_000_000_000)));
+            }
+            "google.protobuf.Duration" => {
+                message.set_field_by_name("seconds", Value::I64(rng.random_range(0..1_000_000)));
+                message.set_field_by_name("nanos", Value::I32(rng.random_range(0..1_000_000_000)));
+            }
+            // its json mapping needs a packed type of the pool, an Empty when there is one
+            ANY => {
+                if let Some(empty) = any_payload(desc) {
+                    let type_url = format!("type.googleapis.com/{}", empty.full_name());
+                    message.set_field_by_name("type_url", Value::String(type_url));
+                }
+            }
+            // an empty FieldMask is always valid, random ones rarely are
+            "google.protobuf.FieldMask" => {}
+            _ => return None,
+        }
+        Some(message)
+    }
+}
+
+/// the message an Any of this pool can pack, none when the pool has no `google.protobuf.Empty`
+fn any_payload(any: &MessageDescriptor) -> Option<MessageDescriptor> {
+    any.parent_pool()
+        .get_message_by_name("google.protobuf.Empty")
+}
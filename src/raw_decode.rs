@@ -0,0 +1,318 @@
+//! Decode protobuf bytes without knowing their message type, like `protoc --decode_raw`.
+//!
+//! The wire format only tells the field numbers and how values are encoded, so every value
+//! comes with the interpretations it could have: a varint can be an unsigned, a signed or a zigzag
+//! encoded integer, a length delimited value can be a string, a nested message or raw bytes.
+use std::fmt;
+use std::sync::OnceLock;
+
+use base64::prelude::{BASE64_STANDARD, Engine};
+use prost_reflect::prost_types::{DescriptorProto, FileDescriptorProto};
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, ReflectMessage};
+use serde_json::{Value, json};
+
+use crate::client::GrpcClientError;
+
+/// maximum depth of nested messages guessed inside length delimited values
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+    Varint,
+    Fixed64,
+    LengthDelimited,
+    StartGroup,
+    EndGroup,
+    Fixed32,
+}
+
+impl WireType {
+    fn from_tag(tag: u64) -> Option<Self> {
+        match tag & 0x7 {
+            0 => Some(WireType::Varint),
+            1 => Some(WireType::Fixed64),
+            2 => Some(WireType::LengthDelimited),
+            3 => Some(WireType::StartGroup),
+            4 => Some(WireType::EndGroup),
+            5 => Some(WireType::Fixed32),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for WireType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            WireType::Varint => "varint",
+            WireType::Fixed64 => "fixed64",
+            WireType::LengthDelimited => "len",
+            WireType::StartGroup => "group",
+            WireType::EndGroup => "end_group",
+            WireType::Fixed32 => "fixed32",
+        })
+    }
+}
+
+/// A value of the wire format, with its guessed interpretation for length delimited ones
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawValue {
+    Varint(u64),
+    Fixed64(u64),
+    Fixed32(u32),
+    /// length delimited value made of printable utf8
+    String(String),
+    /// length delimited value that parse as a message
+    Message(Vec<RawField>),
+    /// length delimited value that is neither a string nor a message
+    Bytes(Vec<u8>),
+    /// deprecated group, delimited by start and end tags
+    Group(Vec<RawField>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawField {
+    pub number: u32,
+    pub wire_type: WireType,
+    pub value: RawValue,
+}
+
+/// Decode every field of a serialized message.
+///
+/// ```
+/// use grpc_client::raw_decode::{RawValue, decode_raw};
+/// // field 1 is the varint 150, field 2 the string "hi", field 3 a message with the varint 1 in field 1
+/// let fields = decode_raw(&[0x08, 0x96, 0x01, 0x12, 0x02, b'h', b'i', 0x1a, 0x02, 0x08, 0x01]).unwrap();
+/// assert_eq!(fields[0].value, RawValue::Varint(150));
+/// assert_eq!(fields[1].value, RawValue::String("hi".to_string()));
+/// assert!(matches!(&fields[2].value, RawValue::Message(inner) if inner[0].value == RawValue::Varint(1)));
+/// ```
+pub fn decode_raw(bytes: &[u8]) -> Result<Vec<RawField>, GrpcClientError> {
+    let mut reader = Reader { bytes, pos: 0 };
+    reader.fields(None, 0).map_err(|e| {
+        GrpcClientError::InvalidInput(format!("invalid protobuf at byte {}: {e}", reader.pos))
+    })
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Result<u64, &'static str> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.pos).ok_or("truncated varint")?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint longer than 10 bytes")
+    }
+
+    fn take(&mut self, len: usize) -> Result<&[u8], &'static str> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or("truncated value")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    /// read fields until the end of the input, or until the end tag of `group`
+    fn fields(&mut self, group: Option<u32>, depth: usize) -> Result<Vec<RawField>, &'static str> {
+        let mut fields = Vec::new();
+        while self.pos < self.bytes.len() {
+            let tag = self.varint()?;
+            let wire_type = WireType::from_tag(tag).ok_or("invalid wire type")?;
+            let number = u32::try_from(tag >> 3).map_err(|_| "field number too large")?;
+            if number == 0 || number > 536_870_911 {
+                return Err("invalid field number");
+            }
+            let value = match wire_type {
+                WireType::Varint => RawValue::Varint(self.varint()?),
+                WireType::Fixed64 => {
+                    RawValue::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+                }
+                WireType::Fixed32 => {
+                    RawValue::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+                }
+                WireType::LengthDelimited => {
+                    let len = usize::try_from(self.varint()?).map_err(|_| "length too large")?;
+                    guess_length_delimited(self.take(len)?, depth)
+                }
+                WireType::StartGroup => {
+                    if depth >= MAX_DEPTH {
+                        return Err("too many nested groups");
+                    }
+                    RawValue::Group(self.fields(Some(number), depth + 1)?)
+                }
+                WireType::EndGroup => {
+                    return match group {
+                        Some(start) if start == number => Ok(fields),
+                        _ => Err("unexpected end of group"),
+                    };
+                }
+            };
+            fields.push(RawField {
+                number,
+                wire_type,
+                value,
+            });
+        }
+        match group {
+            Some(_) => Err("missing end of group"),
+            None => Ok(fields),
+        }
+    }
+}
+
+/// a printable string first, then a nested message, raw bytes when nothing else fit
+fn guess_length_delimited(bytes: &[u8], depth: usize) -> RawValue {
+    if let Ok(text) = std::str::from_utf8(bytes)
+        && text.chars().all(|c| !c.is_control() || c.is_whitespace())
+    {
+        return RawValue::String(text.to_string());
+    }
+    if depth < MAX_DEPTH {
+        let mut reader = Reader { bytes, pos: 0 };
+        if let Ok(fields) = reader.fields(None, depth + 1) {
+            return RawValue::Message(fields);
+        }
+    }
+    RawValue::Bytes(bytes.to_vec())
+}
+
+/// decode a zigzag encoded varint, as used by sint32 and sint64
+pub fn zigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+/// every field as json, with all the possible interpretations of its value
+///
+/// ```
+/// use grpc_client::raw_decode::{decode_raw, to_json};
+/// let json = to_json(&decode_raw(&[0x08, 0x03]).unwrap());
+/// assert_eq!(json[0]["field"], 1);
+/// assert_eq!(json[0]["wire_type"], "varint");
+/// assert_eq!(json[0]["uint"], 3);
+/// assert_eq!(json[0]["zigzag"], -2);
+/// ```
+pub fn to_json(fields: &[RawField]) -> Value {
+    Value::Array(fields.iter().map(field_to_json).collect())
+}
+
+fn field_to_json(field: &RawField) -> Value {
+    let mut json = json!({
+        "field": field.number,
+        "wire_type": field.wire_type.to_string(),
+    });
+    let interpretations = match &field.value {
+        RawValue::Varint(value) => json!({
+            "uint": value,
+            "int": *value as i64,
+            "zigzag": zigzag(*value),
+        }),
+        RawValue::Fixed64(value) => json!({
+            "fixed64": value,
+            "sfixed64": *value as i64,
+            "double": f64::from_bits(*value),
+        }),
+        RawValue::Fixed32(value) => json!({
+            "fixed32": value,
+            "sfixed32": *value as i32,
+            "float": f32::from_bits(*value),
+        }),
+        RawValue::String(text) => json!({ "string": text }),
+        RawValue::Message(fields) => json!({ "message": to_json(fields) }),
+        RawValue::Bytes(bytes) => json!({ "bytes": BASE64_STANDARD.encode(bytes) }),
+        RawValue::Group(fields) => json!({ "group": to_json(fields) }),
+    };
+    if let (Value::Object(json), Value::Object(interpretations)) = (&mut json, interpretations) {
+        json.extend(interpretations);
+    }
+    json
+}
+
+/// Render fields the way `protoc --decode_raw` does
+pub struct RawDisplay<'a>(pub &'a [RawField]);
+
+impl fmt::Display for RawDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_fields(f, self.0, 0)
+    }
+}
+
+fn write_fields(f: &mut fmt::Formatter<'_>, fields: &[RawField], indent: usize) -> fmt::Result {
+    let pad = "  ".repeat(indent);
+    for field in fields {
+        match &field.value {
+            RawValue::Varint(value) => writeln!(f, "{pad}{}: {value}", field.number)?,
+            RawValue::Fixed64(value) => writeln!(f, "{pad}{}: 0x{value:016x}", field.number)?,
+            RawValue::Fixed32(value) => writeln!(f, "{pad}{}: 0x{value:08x}", field.number)?,
+            RawValue::String(text) => writeln!(f, "{pad}{}: {text:?}", field.number)?,
+            RawValue::Bytes(bytes) => {
+                let escaped: String = bytes.iter().map(|b| format!("\\x{b:02x}")).collect();
+                writeln!(f, "{pad}{}: \"{escaped}\"", field.number)?
+            }
+            RawValue::Message(inner) | RawValue::Group(inner) => {
+                writeln!(f, "{pad}{} {{", field.number)?;
+                write_fields(f, inner, indent + 1)?;
+                writeln!(f, "{pad}}}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Name of the message without any field used when the expected type is missing from the pool,
+/// every field of the response is then kept as an unknown field.
+pub const RAW_MESSAGE_NAME: &str = "grpc_client.RawMessage";
+
+/// descriptor of [`RAW_MESSAGE_NAME`]
+pub fn raw_message_descriptor() -> MessageDescriptor {
+    static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let file = FileDescriptorProto {
+            name: Some("grpc_client/raw_message.proto".to_string()),
+            package: Some("grpc_client".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("RawMessage".to_string()),
+                ..Default::default()
+            }],
+            syntax: Some("proto3".to_string()),
+            ..Default::default()
+        };
+        DescriptorPool::from_file_descriptor_set(prost_reflect::prost_types::FileDescriptorSet {
+            file: vec![file],
+        })
+        .expect("the raw message descriptor is valid")
+    })
+    .get_message_by_name(RAW_MESSAGE_NAME)
+    .expect("the raw message is in its pool")
+}
+
+/// Check the bytes are valid protobuf, then keep them in a message of type [`RAW_MESSAGE_NAME`],
+/// so they can be printed by any [`crate::output::ResponseFormatter`].
+///
+/// ```
+/// use grpc_client::OutputOptions;
+/// use grpc_client::raw_decode::decode_raw_message;
+/// let message = decode_raw_message(&[0x08, 0x96, 0x01]).unwrap();
+/// let json = OutputOptions::default().to_json_value(&message).unwrap();
+/// assert_eq!(json[0]["uint"], 150);
+/// assert!(decode_raw_message(&[0x08]).is_err());
+/// ```
+pub fn decode_raw_message(bytes: &[u8]) -> Result<DynamicMessage, GrpcClientError> {
+    decode_raw(bytes)?;
+    Ok(DynamicMessage::decode(raw_message_descriptor(), bytes)?)
+}
+
+/// true if the message was decoded without its type, see [`RAW_MESSAGE_NAME`]
+pub fn is_raw_message(message: &DynamicMessage) -> bool {
+    message.descriptor().full_name() == RAW_MESSAGE_NAME
+}
@@ -0,0 +1,139 @@
+//! The reflection v1 service of a descriptor pool, used by the [mock](crate::mock) server.
+//!
+//! The tonic-reflection service decodes the files it serves into `prost_types`, dropping their
+//! extension options like `google.api.http`. This one sends the files encoded by prost-reflect,
+//! their options included.
+use std::pin::Pin;
+
+use prost_reflect::DescriptorPool;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, Request, Response, Status, Streaming};
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1::server_reflection_server::{
+    ServerReflection, ServerReflectionServer,
+};
+use tonic_reflection::pb::v1::{
+    ErrorResponse, ExtensionNumberResponse, FileDescriptorResponse, ListServiceResponse,
+    ServerReflectionRequest, ServerReflectionResponse, ServiceResponse,
+};
+
+use crate::describe::Symbol;
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<ServerReflectionResponse, Status>> + Send>>;
+
+/// Answers the reflection requests with the services and files of a pool.
+///
+/// ```
+/// use grpc_client::reflection::PoolReflection;
+/// use prost_reflect::DescriptorPool;
+/// let pool = DescriptorPool::decode(tonic_health::pb::FILE_DESCRIPTOR_SET).unwrap();
+/// let service = PoolReflection::new(pool).into_service();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PoolReflection {
+    pool: DescriptorPool,
+}
+
+impl PoolReflection {
+    pub fn new(pool: DescriptorPool) -> Self {
+        Self { pool }
+    }
+    /// the grpc service, to add to a server
+    pub fn into_service(self) -> ServerReflectionServer<Self> {
+        ServerReflectionServer::new(self)
+    }
+    /// the answer to one request, an error response when what it asks for is unknown
+    fn answer(&self, request: &MessageRequest) -> MessageResponse {
+        let file = match request {
+            MessageRequest::ListServices(_) => {
+                return MessageResponse::ListServicesResponse(ListServiceResponse {
+                    service: self
+                        .pool
+                        .services()
+                        .map(|service| ServiceResponse {
+                            name: service.full_name().to_string(),
+                        })
+                        .collect(),
+                });
+            }
+            MessageRequest::AllExtensionNumbersOfType(name) => {
+                return match self.pool.get_message_by_name(name) {
+                    Some(message) => {
+                        MessageResponse::AllExtensionNumbersResponse(ExtensionNumberResponse {
+                            base_type_name: name.clone(),
+                            extension_number: message
+                                .extensions()
+                                .map(|extension| extension.number() as i32)
+                                .collect(),
+                        })
+                    }
+                    None => not_found(format!("message '{name}' not found")),
+                };
+            }
+            MessageRequest::FileByFilename(name) => self
+                .pool
+                .get_file_by_name(name)
+                .ok_or_else(|| format!("file '{name}' not found")),
+            MessageRequest::FileContainingSymbol(symbol) => Symbol::find(&self.pool, symbol)
+                .map(|symbol| symbol.parent_file())
+                .ok_or_else(|| format!("symbol '{symbol}' not found")),
+            MessageRequest::FileContainingExtension(extension) => self
+                .pool
+                .get_message_by_name(&extension.containing_type)
+                .and_then(|message| {
+                    u32::try_from(extension.extension_number)
+                        .ok()
+                        .and_then(|number| message.get_extension(number))
+                })
+                .map(|extension| extension.parent_file())
+                .ok_or_else(|| {
+                    format!(
+                        "extension {} of '{}' not found",
+                        extension.extension_number, extension.containing_type
+                    )
+                }),
+        };
+        match file {
+            Ok(file) => MessageResponse::FileDescriptorResponse(FileDescriptorResponse {
+                file_descriptor_proto: vec![file.encode_to_vec()],
+            }),
+            Err(message) => not_found(message),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ServerReflection for PoolReflection {
+    type ServerReflectionInfoStream = ResponseStream;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let reflection = self.clone();
+        let responses = request.into_inner().map(move |request| {
+            let request = request?;
+            let answer = match &request.message_request {
+                Some(message) => reflection.answer(message),
+                None => MessageResponse::ErrorResponse(ErrorResponse {
+                    error_code: Code::InvalidArgument as i32,
+                    error_message: "empty request".to_string(),
+                }),
+            };
+            Ok(ServerReflectionResponse {
+                valid_host: request.host.clone(),
+                original_request: Some(request),
+                message_response: Some(answer),
+            })
+        });
+        Ok(Response::new(Box::pin(responses)))
+    }
+}
+
+fn not_found(message: String) -> MessageResponse {
+    MessageResponse::ErrorResponse(ErrorResponse {
+        error_code: Code::NotFound as i32,
+        error_message: message,
+    })
+}
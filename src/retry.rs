@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use tonic::Code;
+
+use crate::client::GrpcClientError;
+
+/// How failed unary calls are retried, see [`crate::CallOptions::retry`].
+///
+/// The delay before the attempt `n` (starting at 1 for the first retry) is
+/// `initial_backoff * multiplier^(n-1)`, randomized by `jitter`, then capped to `max_backoff`.
+///
+/// ```
+/// use std::time::Duration;
+/// use grpc_client::RetryPolicy;
+/// let policy = RetryPolicy::default().max_attempts(5).jitter(0.0);
+/// assert_eq!(policy.backoff(1), Duration::from_millis(100));
+/// assert_eq!(policy.backoff(3), Duration::from_millis(400));
+/// assert!(policy.is_retryable(tonic::Code::Unavailable));
+/// assert!(!policy.is_retryable(tonic::Code::InvalidArgument));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// total number of attempts, including the first one
+    pub max_attempts: u32,
+    /// delay before the first retry
+    pub initial_backoff: Duration,
+    /// upper bound of the delay between two attempts
+    pub max_backoff: Duration,
+    /// growth factor of the delay after each attempt
+    pub multiplier: f64,
+    /// fraction of the delay randomly added or removed (0.2 means ±20%)
+    pub jitter: f64,
+    /// status codes that trigger a new attempt, any other code is returned right away
+    pub retryable_codes: Vec<Code>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: 0.2,
+            retryable_codes: vec![Code::Unavailable],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+    /// fails unless `multiplier` is a finite number of 0 or more
+    ///
+    /// ```
+    /// use grpc_client::RetryPolicy;
+    /// assert!(RetryPolicy::default().multiplier(1.5).is_ok());
+    /// assert!(RetryPolicy::default().multiplier(-2.0).is_err());
+    /// assert!(RetryPolicy::default().multiplier(f64::NAN).is_err());
+    /// ```
+    pub fn multiplier(mut self, multiplier: f64) -> Result<Self, GrpcClientError> {
+        if !multiplier.is_finite() || multiplier < 0.0 {
+            return Err(GrpcClientError::InvalidInput(format!(
+                "the retry multiplier must be a positive number, got {multiplier}"
+            )));
+        }
+        self.multiplier = multiplier;
+        Ok(self)
+    }
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    pub fn retryable_codes(mut self, codes: Vec<Code>) -> Self {
+        self.retryable_codes = codes;
+        self
+    }
+
+    /// return true if a call failing with this code should be attempted again
+    pub fn is_retryable(&self, code: Code) -> bool {
+        self.retryable_codes.contains(&code)
+    }
+
+    /// delay to wait before the retry number `retry` (1 for the first retry)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::random_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+        Duration::try_from_secs_f64(base * factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+/// parse a grpc status code from its name (`unavailable`, `RESOURCE_EXHAUSTED`, `DeadlineExceeded`) or its number
+///
+/// ```
+/// use grpc_client::retry::parse_code;
+/// assert_eq!(parse_code("resource-exhausted"), Some(tonic::Code::ResourceExhausted));
+/// assert_eq!(parse_code("14"), Some(tonic::Code::Unavailable));
+/// assert_eq!(parse_code("unknown_code"), None);
+/// ```
+pub fn parse_code(s: &str) -> Option<Code> {
+    if let Ok(number) = s.parse::<i32>() {
+        return match Code::from_i32(number) {
+            Code::Unknown if number != Code::Unknown as i32 => None,
+            code => Some(code),
+        };
+    }
+    let normalized: String = s
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    (0..=16)
+        .map(Code::from_i32)
+        .find(|code| format!("{code:?}").to_ascii_lowercase() == normalized)
+}
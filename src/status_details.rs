@@ -0,0 +1,80 @@
+use std::sync::OnceLock;
+
+use base64::prelude::{BASE64_STANDARD, Engine};
+use prost::Message;
+use prost_reflect::prost_types::Any;
+use prost_reflect::{DescriptorPool, DynamicMessage};
+use serde_json::{Value, json};
+use tonic::Status;
+
+/// descriptors of `google.rpc.Status` and the standard error details (`ErrorInfo`, `BadRequest`, `RetryInfo`...)
+pub fn builtin_pool() -> &'static DescriptorPool {
+    static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+    POOL.get_or_init(|| {
+        DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET)
+            .expect("tonic-types ship a valid google.rpc descriptor set")
+    })
+}
+
+/// Decode the `google.rpc.Status` attached to a status in `grpc-status-details-bin`, and render it as json.
+///
+/// Each detail is an `Any`, its type is first searched in the built-in `google.rpc` descriptors, then in `pool`
+/// (usually the one built from reflection). A detail with an unknown type is kept with its raw value in base64.
+/// Return None if the status has no details.
+///
+/// ```
+/// use grpc_client::status_details::decode_status_details;
+/// use tonic_types::{ErrorDetails, StatusExt};
+/// let status = tonic::Status::with_error_details(
+///     tonic::Code::InvalidArgument,
+///     "bad name",
+///     ErrorDetails::with_bad_request_violation("name", "must not be empty"),
+/// );
+/// let details = decode_status_details(&status, None).unwrap();
+/// assert_eq!(details["details"][0]["@type"], "type.googleapis.com/google.rpc.BadRequest");
+/// assert_eq!(details["details"][0]["fieldViolations"][0]["field"], "name");
+/// ```
+pub fn decode_status_details(status: &Status, pool: Option<&DescriptorPool>) -> Option<Value> {
+    if status.details().is_empty() {
+        return None;
+    }
+    let Ok(rpc_status) = tonic_types::pb::Status::decode(status.details()) else {
+        return Some(json!({
+            "code": status.code() as i32,
+            "message": status.message(),
+            "details": [{ "undecodable": BASE64_STANDARD.encode(status.details()) }],
+        }));
+    };
+    let details: Vec<Value> = rpc_status
+        .details
+        .iter()
+        .map(|detail| any_to_json(detail, pool))
+        .collect();
+    Some(json!({
+        "code": rpc_status.code,
+        "message": rpc_status.message,
+        "details": details,
+    }))
+}
+
+/// render an `Any` as json with its `@type`, like the protobuf json mapping does
+fn any_to_json(any: &Any, pool: Option<&DescriptorPool>) -> Value {
+    let type_name = any.type_url.rsplit('/').next().unwrap_or_default();
+    let descriptor = builtin_pool()
+        .get_message_by_name(type_name)
+        .or_else(|| pool.and_then(|p| p.get_message_by_name(type_name)));
+    let decoded = descriptor
+        .and_then(|desc| DynamicMessage::decode(desc, any.value.as_slice()).ok())
+        .and_then(|msg| serde_json::to_value(&msg).ok());
+    match decoded {
+        Some(Value::Object(fields)) => {
+            let mut object = serde_json::Map::new();
+            object.insert("@type".to_string(), Value::String(any.type_url.clone()));
+            object.extend(fields);
+            Value::Object(object)
+        }
+        // well known types like Duration are rendered as a single json value
+        Some(value) => json!({ "@type": any.type_url, "value": value }),
+        None => json!({ "@type": any.type_url, "value": BASE64_STANDARD.encode(&any.value) }),
+    }
+}
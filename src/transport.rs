@@ -0,0 +1,190 @@
+//! How the calls of a [`crate::Client`] reach the server: grpc over http/2, grpc-web or connect over http/1.1.
+//!
+//! Every protocol is a [`Transport`], a service sending grpc http requests and answering grpc http
+//! responses, so the reflection, health and dynamic calls work the same over any of them.
+use std::fmt;
+use std::str::FromStr;
+
+use bytes::Bytes;
+use http::Uri;
+use http::uri::Scheme;
+use http_body::Frame;
+use http_body_util::{BodyExt, StreamBody};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+use tonic::body::Body;
+use tonic::transport::Channel;
+use tower::ServiceExt;
+use tower::util::BoxCloneSyncService;
+
+use crate::client::GrpcClientError;
+use crate::connect::ConnectService;
+use crate::grpc_web::GrpcWebService;
+
+/// A connection to the server, whatever its protocol. Its errors are the status tonic would give them
+pub type Transport = BoxCloneSyncService<http::Request<Body>, http::Response<Body>, Status>;
+
+/// Protocol spoken with the server.
+///
+/// ```
+/// use grpc_client::transport::Protocol;
+/// assert_eq!("grpc-web-text".parse::<Protocol>(), Ok(Protocol::GrpcWebText));
+/// assert_eq!(Protocol::GrpcWeb.to_string(), "grpc-web");
+/// assert!(Protocol::ConnectJson.json());
+/// assert!("soap".parse::<Protocol>().is_err());
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// grpc over http/2
+    #[default]
+    Grpc,
+    /// grpc-web over http/1.1, binary bodies
+    GrpcWeb,
+    /// grpc-web over http/1.1, base64 encoded bodies
+    GrpcWebText,
+    /// connect over http/1.1, binary messages
+    Connect,
+    /// connect over http/1.1, messages in the protobuf json mapping
+    ConnectJson,
+}
+
+impl Protocol {
+    /// whether the messages of the dynamic calls are sent in json
+    pub fn json(self) -> bool {
+        self == Protocol::ConnectJson
+    }
+}
+
+/// How the messages of a call are sent, in the extensions of its request.
+///
+/// Only connect needs it: a unary call is a plain POST of its message, unlike a streaming one, and the
+/// content type tells whether the messages are json. A request without it is a binary unary call.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CallKind {
+    /// the request or the response of the method is a stream
+    pub streaming: bool,
+    /// the messages are encoded in json by the codec of the call
+    pub json: bool,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Protocol::Grpc => "grpc",
+            Protocol::GrpcWeb => "grpc-web",
+            Protocol::GrpcWebText => "grpc-web-text",
+            Protocol::Connect => "connect",
+            Protocol::ConnectJson => "connect-json",
+        })
+    }
+}
+
+impl FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "grpc" => Ok(Protocol::Grpc),
+            "grpc-web" => Ok(Protocol::GrpcWeb),
+            "grpc-web-text" => Ok(Protocol::GrpcWebText),
+            "connect" => Ok(Protocol::Connect),
+            "connect-json" => Ok(Protocol::ConnectJson),
+            _ => Err(format!(
+                "unknown protocol `{s}`, use grpc, grpc-web, grpc-web-text, connect or connect-json"
+            )),
+        }
+    }
+}
+
+/// connect to the server at `url`. Only grpc connects right away, the other protocols connect on the first call
+pub async fn connect(url: &str, protocol: Protocol) -> Result<Transport, GrpcClientError> {
+    let uri: Uri = url.parse()?;
+    // the http/1.1 protocols have no tls connector
+    if protocol != Protocol::Grpc && uri.scheme() == Some(&Scheme::HTTPS) {
+        return Err(GrpcClientError::InvalidInput(format!(
+            "{protocol} does not support https, use an http:// url"
+        )));
+    }
+    Ok(match protocol {
+        Protocol::Grpc => {
+            let channel = Channel::builder(uri).connect().await?;
+            BoxCloneSyncService::new(channel.map_err(|e| Status::from_error(Box::new(e))))
+        }
+        Protocol::GrpcWeb => BoxCloneSyncService::new(GrpcWebService::new(uri, false)),
+        Protocol::GrpcWebText => BoxCloneSyncService::new(GrpcWebService::new(uri, true)),
+        Protocol::Connect | Protocol::ConnectJson => {
+            BoxCloneSyncService::new(ConnectService::new(uri))
+        }
+    })
+}
+
+/// a transport never connected, for clients that make no call
+pub(crate) fn disconnected() -> Transport {
+    let channel = Channel::from_static("http://disconnected.invalid").connect_lazy();
+    BoxCloneSyncService::new(channel.map_err(|e| Status::from_error(Box::new(e))))
+}
+
+/// frames decoded but not yet read by tonic
+const BUFFERED_FRAMES: usize = 16;
+
+/// Turns the body of a response in another protocol back into grpc frames, then http trailers.
+pub trait FrameDecoder: Send + 'static {
+    /// the frames completed by this chunk of the body: data frames keep their grpc header
+    fn decode(&mut self, chunk: &[u8]) -> Result<Vec<Frame<Bytes>>, Status>;
+    /// fails when the body ended too early, in the middle of a frame or before its trailers
+    fn finish(&self) -> Result<(), Status>;
+}
+
+/// a grpc body decoded by a task as the response comes, so streamed messages are read one by one
+pub(crate) fn decoded_body<B>(mut body: B, mut decoder: impl FrameDecoder) -> Body
+where
+    B: http_body::Body<Data = Bytes> + Send + Unpin + 'static,
+    B::Error: fmt::Display + Send,
+{
+    let (sender, receiver) = mpsc::channel(BUFFERED_FRAMES);
+    tokio::spawn(async move {
+        while let Some(frame) = body.frame().await {
+            let frames = match frame {
+                Ok(frame) => match frame.into_data() {
+                    Ok(data) => decoder.decode(&data),
+                    // trailers of an http/2 server, already where tonic expects them
+                    Err(frame) => Ok(frame
+                        .into_trailers()
+                        .map(Frame::trailers)
+                        .into_iter()
+                        .collect()),
+                },
+                Err(e) => Err(Status::unavailable(format!("response interrupted: {e}"))),
+            };
+            let failed = frames.is_err();
+            let frames = match frames {
+                Ok(frames) => frames.into_iter().map(Ok).collect(),
+                Err(status) => vec![Err(status)],
+            };
+            for frame in frames {
+                if sender.send(frame).await.is_err() {
+                    return;
+                }
+            }
+            if failed {
+                return;
+            }
+        }
+        if let Err(status) = decoder.finish() {
+            let _ = sender.send(Err(status)).await;
+        }
+    });
+    Body::new(StreamBody::new(ReceiverStream::new(receiver)))
+}
+
+/// an error and its sources, the one of hyper alone only says `client error (Connect)`
+pub(crate) fn causes(error: &dyn std::error::Error) -> String {
+    let mut causes = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        causes.push_str(&format!(": {error}"));
+        source = error.source();
+    }
+    causes
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use std::path::PathBuf;
+
+use grpc_client::batch::{BatchConfig, BatchResult, BatchSummary, run};
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::mock::MockServer;
+use grpc_client::{CallOptions, Client};
+use tonic::transport::server::TcpIncoming;
+use tonic_health::ServingStatus;
+
+const INPUT: &str = r#"{"service":"grpc.health.v1.Health","method":"Check","metadata":{"x-team":"a"}}
+
+{"service":"grpc.health.v1.Health","method":"Check","body":{"service":"catalog.v1.Catalog"}}
+not json
+{"service":"grpc.health.v1.Health","method":"Check","body":{"service":"missing.Service"}}
+{"service":"grpc.health.v1.Health","method":"Missing"}
+"#;
+
+async fn batch(config: BatchConfig) -> (Vec<BatchResult>, BatchSummary) {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::NotServing)
+        .await;
+    let mut client = Client::new(url).await.unwrap();
+    let mut results = Vec::new();
+    let summary = run(
+        &mut client,
+        INPUT,
+        &CallOptions::default(),
+        &config,
+        |result| results.push(result.clone()),
+    )
+    .await
+    .unwrap();
+    results.sort_by_key(|result| result.line);
+    (results, summary)
+}
+
+#[tokio::test]
+async fn every_line_gets_a_result() {
+    let (results, summary) = batch(BatchConfig::default().concurrency(3)).await;
+    let statuses: Vec<(usize, &str)> = results
+        .iter()
+        .map(|result| (result.line, result.status.as_str()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            (1, "Ok"),
+            (3, "Ok"),
+            (4, "invalid_input"),
+            (5, "NotFound"),
+            (6, "not_found")
+        ]
+    );
+    assert_eq!(
+        results[1].responses.as_ref().unwrap()[0]["status"],
+        "NOT_SERVING"
+    );
+    assert_eq!(results[2].service, None);
+    assert_eq!(results[3].error.as_ref().unwrap()["exit_code"], 69);
+    assert_eq!(
+        (summary.succeeded, summary.failed, summary.skipped),
+        (2, 3, 0)
+    );
+    assert_eq!(summary.first_error.unwrap().kind(), "invalid_input");
+}
+
+#[tokio::test]
+async fn stop_on_error_skips_the_remaining_lines() {
+    let (results, summary) = batch(BatchConfig::default().stop_on_error(true)).await;
+    assert_eq!(results.len(), 3);
+    assert!(!results[2].is_ok());
+    assert_eq!(
+        (summary.succeeded, summary.failed, summary.skipped),
+        (2, 1, 2)
+    );
+}
+
+#[tokio::test]
+async fn responses_without_a_json_mapping_are_descriptor_errors() {
+    // the mock knows the type of the payload, the client does not
+    const RULES: &str = r#"
+- method: Get
+  response: {id: e-1, payload: {"@type": type.googleapis.com/catalog.v1.Product, id: p-1}}
+"#;
+    let protos = ["tests/proto/events.proto", "tests/proto/catalog.proto"].map(PathBuf::from);
+    let rules = serde_yaml_ng::from_str(RULES).unwrap();
+    let mock = MockServer::new(compile_protos(&protos, &[]).unwrap())
+        .rules(rules)
+        .unwrap();
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(mock.serve(incoming));
+
+    let mut client = Client::new(url).await.unwrap();
+    client.set_descriptor_pool(compile_protos(&protos[..1], &[]).unwrap());
+    let mut results = Vec::new();
+    run(
+        &mut client,
+        r#"{"service":"events.v1.Events","method":"Get"}"#,
+        &CallOptions::default(),
+        &BatchConfig::default(),
+        |result| results.push(result.clone()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(results[0].status, "bad_message_type");
+    assert_eq!(results[0].error.as_ref().unwrap()["exit_code"], 4);
+}
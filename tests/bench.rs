@@ -0,0 +1,101 @@
+mod common;
+
+use std::time::Duration;
+
+use grpc_client::bench::{BenchConfig, run};
+use grpc_client::client::GrpcClientError;
+use grpc_client::{CallOptions, Client};
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+async fn clients(url: &str, channels: usize) -> Vec<Client> {
+    let mut clients = Vec::new();
+    for _ in 0..channels {
+        let mut client = Client::new(url.to_string()).await.unwrap();
+        client.descriptor_pool().await.unwrap();
+        clients.push(client);
+    }
+    clients
+}
+
+#[tokio::test]
+async fn every_call_is_counted_by_status() {
+    let url = common::health_server(0).await.url;
+    let config = BenchConfig::default().requests(50).concurrency(5);
+    let report = run(
+        clients(&url, 2).await,
+        HEALTH,
+        "Check",
+        vec![],
+        &CallOptions::default(),
+        &config,
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.requests, 50);
+    assert_eq!(report.status_codes["Ok"], 50);
+    assert_eq!(report.histogram.iter().map(|b| b.count).sum::<u64>(), 50);
+    assert!(report.latency.min_ms <= report.latency.p50_ms);
+    assert!(report.latency.p99_ms <= report.latency.max_ms);
+
+    let arguments = vec![("service".to_string(), "missing.Service".to_string())];
+    let report = run(
+        clients(&url, 1).await,
+        HEALTH,
+        "Check",
+        arguments,
+        &CallOptions::default(),
+        &BenchConfig::default().requests(3),
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.status_codes["NotFound"], 3);
+}
+
+#[tokio::test]
+async fn rate_and_duration_bound_the_calls() {
+    let url = common::health_server(0).await.url;
+    let config = BenchConfig::default()
+        .duration(Duration::from_millis(500))
+        .concurrency(4)
+        .rate(20.0)
+        .unwrap();
+    let report = run(
+        clients(&url, 1).await,
+        HEALTH,
+        "Check",
+        vec![],
+        &CallOptions::default(),
+        &config,
+    )
+    .await
+    .unwrap();
+    // calls start at 0, 50, 100... 450 ms, a slow scheduler may push the last ones after the end
+    assert!((8..=10).contains(&report.requests), "{report:?}");
+}
+
+#[tokio::test]
+async fn usage_errors_stop_the_bench() {
+    let url = common::health_server(0).await.url;
+    let result = run(
+        clients(&url, 1).await,
+        HEALTH,
+        "Missing",
+        vec![],
+        &CallOptions::default(),
+        &BenchConfig::default(),
+    )
+    .await;
+    assert!(matches!(result, Err(GrpcClientError::NotFoundError(_))));
+}
+
+#[test]
+fn rates_that_are_not_positive_are_rejected() {
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let result = BenchConfig::default().rate(rate);
+        assert!(
+            matches!(result, Err(GrpcClientError::InvalidInput(_))),
+            "{result:?}"
+        );
+    }
+}
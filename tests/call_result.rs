@@ -0,0 +1,54 @@
+mod common;
+
+use std::time::Duration;
+
+use grpc_client::{CallOptions, Client};
+use tonic::Code;
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+#[tokio::test]
+async fn results_have_the_headers_trailers_and_status() {
+    let url = common::health_server(0).await.url;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().header("x-request-id", "r-1").unwrap();
+    let result = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap();
+    assert_eq!(result.request_metadata.get("x-request-id").unwrap(), "r-1");
+    assert_eq!(result.headers.get("x-served-by").unwrap(), "common");
+    assert_eq!(
+        result.headers.get("content-type").unwrap(),
+        "application/grpc"
+    );
+    assert_eq!(result.trailers.get("x-served-by").unwrap(), "common");
+    assert!(result.trailers.get("grpc-status").is_none());
+    assert_eq!(
+        (result.code, result.status_message.as_str()),
+        (Code::Ok, "")
+    );
+    assert_eq!(result.attempts, 1);
+    assert_eq!(
+        serde_json::to_string(result.message().unwrap()).unwrap(),
+        r#"{"status":"SERVING"}"#
+    );
+}
+
+#[tokio::test]
+async fn the_metadata_sent_is_kept_when_the_call_fails() {
+    let url = common::health_server(1).await.url;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new()
+        .timeout(Duration::from_secs(5))
+        .header("x-request-id", "r-2")
+        .unwrap();
+    let error = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().unwrap().code(), Code::Unavailable);
+    let sent = client.last_request_metadata().unwrap();
+    assert_eq!(sent.get("x-request-id").unwrap(), "r-2");
+    assert!(sent.get("grpc-timeout").is_some());
+}
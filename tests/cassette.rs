@@ -0,0 +1,148 @@
+mod common;
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use grpc_client::cassette::{Cassette, Player, Recorder};
+use grpc_client::client::GrpcClientError;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::mock::MockServer;
+use grpc_client::{CallOptions, Client};
+use prost_reflect::DescriptorPool;
+use tonic::transport::server::TcpIncoming;
+use tonic_health::ServingStatus;
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+fn service(name: &str) -> Vec<(String, String)> {
+    vec![("service".to_string(), name.to_string())]
+}
+
+async fn status_of(client: &mut Client, name: &str) -> Result<String, GrpcClientError> {
+    let response = client.request(HEALTH, "Check", service(name)).await?;
+    Ok(serde_json::to_value(&response).unwrap()["status"]
+        .as_str()
+        .unwrap()
+        .to_string())
+}
+
+/// calls recorded against a live server, whose status changes between two identical calls
+async fn record() -> Cassette {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    let recorder = Recorder::new();
+    let mut client = Client::new(url).await.unwrap();
+    client.record(recorder.clone());
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::Serving)
+        .await;
+    let options = CallOptions::new().header("x-team", "catalog").unwrap();
+    client
+        .request_with_options(HEALTH, "Check", service("catalog.v1.Catalog"), &options)
+        .await
+        .unwrap();
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::NotServing)
+        .await;
+    assert_eq!(
+        status_of(&mut client, "catalog.v1.Catalog").await.unwrap(),
+        "NOT_SERVING"
+    );
+    assert!(status_of(&mut client, "missing.Service").await.is_err());
+    // not sent, so not recorded
+    assert!(client.request(HEALTH, "Missing", vec![]).await.is_err());
+    recorder.cassette()
+}
+
+#[tokio::test]
+async fn calls_are_recorded_with_their_metadata_and_status() {
+    let cassette = record().await;
+    assert_eq!(cassette.interactions.len(), 3);
+    let first = &cassette.interactions[0];
+    assert_eq!(first.method, "grpc.health.v1.Health.Check");
+    assert_eq!(first.request["service"], "catalog.v1.Catalog");
+    assert_eq!(first.request_metadata["x-team"], "catalog");
+    assert_eq!(first.responses[0]["status"], "SERVING");
+    assert_eq!(cassette.interactions[2].code, "NotFound");
+    assert!(
+        cassette
+            .descriptor_pool()
+            .unwrap()
+            .get_service_by_name(HEALTH)
+            .is_some()
+    );
+}
+
+#[tokio::test]
+async fn replayed_calls_match_the_method_and_the_request() {
+    let path = std::env::temp_dir().join(format!("cassette-{}.json", std::process::id()));
+    record().await.save(&path).unwrap();
+    let cassette = Cassette::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut client = Client::replay(Player::new(cassette).unwrap());
+    // the same request was recorded twice, the answers come back in order, then the last one again
+    for expected in ["SERVING", "NOT_SERVING", "NOT_SERVING"] {
+        assert_eq!(
+            status_of(&mut client, "catalog.v1.Catalog").await.unwrap(),
+            expected
+        );
+    }
+    let error = status_of(&mut client, "missing.Service").await.unwrap_err();
+    assert_eq!(error.exit_code(), 69);
+    let error = status_of(&mut client, "other.Service").await.unwrap_err();
+    assert!(matches!(error, GrpcClientError::NotFoundError(_)));
+}
+
+#[tokio::test]
+async fn deadlines_are_replayed_as_deadlines() {
+    let common::TestServer { url, .. } = common::health_server(0).await;
+    let recorder = Recorder::new();
+    let mut client = Client::new(url).await.unwrap();
+    client.record(recorder.clone());
+    // the status of the server never changes, the watch runs until the deadline
+    let options = CallOptions::new().timeout(Duration::from_millis(100));
+    let error = client
+        .request_with_options(HEALTH, "Watch", vec![], &options)
+        .await
+        .unwrap_err();
+    assert!(matches!(error, GrpcClientError::DeadlineExceeded(_)));
+    let cassette = recorder.cassette();
+    assert_eq!(cassette.interactions[0].timeout_ms, Some(100));
+
+    let mut client = Client::replay(Player::new(cassette).unwrap());
+    let error = client
+        .request_with_options(HEALTH, "Watch", vec![], &CallOptions::default())
+        .await
+        .unwrap_err();
+    assert_eq!(error.exit_code(), 68);
+    let GrpcClientError::DeadlineExceeded(timeout) = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert_eq!(timeout, Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn the_descriptors_keep_their_extension_options() {
+    let library = compile_protos(&[PathBuf::from("tests/proto/library.proto")], &[]).unwrap();
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(MockServer::new(library.clone()).serve(incoming));
+
+    let recorder = Recorder::new();
+    let mut client = Client::new(url).await.unwrap();
+    client.set_descriptor_pool(library.clone());
+    client.record(recorder.clone());
+    client
+        .request("library.v1.Library", "Ping", vec![])
+        .await
+        .unwrap();
+    // the google.api.http options are only in the encoding of prost-reflect
+    let file = |pool: &DescriptorPool| {
+        pool.get_service_by_name("library.v1.Library")
+            .unwrap()
+            .parent_file()
+            .encode_to_vec()
+    };
+    let recorded = recorder.cassette().descriptor_pool().unwrap();
+    assert_eq!(file(&recorded), file(&library));
+}
@@ -0,0 +1,116 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use http::{HeaderMap, HeaderValue};
+use http_body_util::BodyExt;
+use tonic::Status;
+use tonic::body::Body;
+use tonic::codec::CompressionEncoding;
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic_health::server::HealthReporter;
+use tower::Service;
+
+/// A running server with the health service and reflection.
+#[allow(dead_code, reason = "each test reads the fields it needs")]
+pub struct TestServer {
+    pub reporter: HealthReporter,
+    pub url: String,
+    /// headers of the calls received, reflection excluded
+    pub requests: Arc<Mutex<Vec<HeaderMap>>>,
+}
+
+/// start a server on a free port, failing the first `failures` calls with UNAVAILABLE.
+///
+/// It answers with gzip or zstd to the clients accepting them, and with an `x-served-by` header
+/// and trailer.
+pub async fn health_server(failures: usize) -> TestServer {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    let health = health
+        .accept_compressed(CompressionEncoding::Gzip)
+        .accept_compressed(CompressionEncoding::Zstd)
+        .send_compressed(CompressionEncoding::Gzip)
+        .send_compressed(CompressionEncoding::Zstd);
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let failures = Arc::new(AtomicUsize::new(failures));
+    let recorded = requests.clone();
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(
+        Server::builder()
+            .layer(tower::layer::layer_fn(move |inner| Recording {
+                inner,
+                requests: recorded.clone(),
+                failures: failures.clone(),
+            }))
+            .add_service(health)
+            .add_service(reflection)
+            .serve_with_incoming(incoming),
+    );
+    TestServer {
+        reporter,
+        url,
+        requests,
+    }
+}
+
+#[derive(Clone)]
+struct Recording<S> {
+    inner: S,
+    requests: Arc<Mutex<Vec<HeaderMap>>>,
+    failures: Arc<AtomicUsize>,
+}
+
+type ResponseFuture<E> = Pin<Box<dyn Future<Output = Result<http::Response<Body>, E>> + Send>>;
+
+impl<S> Service<http::Request<Body>> for Recording<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>>,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        if request.uri().path().starts_with("/grpc.reflection.") {
+            return Box::pin(self.inner.call(request));
+        }
+        self.requests
+            .lock()
+            .unwrap()
+            .push(request.headers().clone());
+        let failing = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok();
+        if failing {
+            return Box::pin(async { Ok(Status::unavailable("try again").into_http()) });
+        }
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let (mut parts, body) = response.await?.into_parts();
+            let served_by = HeaderValue::from_static("common");
+            parts.headers.insert("x-served-by", served_by.clone());
+            let body = body.with_trailers(async move {
+                let mut trailers = HeaderMap::new();
+                trailers.insert("x-served-by", served_by);
+                Some(Ok(trailers))
+            });
+            Ok(http::Response::from_parts(parts, Body::new(body)))
+        })
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use grpc_client::{CallOptions, Client};
+use tonic::Code;
+use tonic::codec::CompressionEncoding;
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+#[tokio::test]
+async fn requests_and_responses_are_compressed_as_negotiated() {
+    let common::TestServer { url, requests, .. } = common::health_server(0).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new()
+        .compression(CompressionEncoding::Gzip)
+        .accept_compression(CompressionEncoding::Zstd);
+    let result = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap();
+    assert_eq!(result.response_encoding(), Some("zstd"));
+    assert_eq!(
+        serde_json::to_string(result.message().unwrap()).unwrap(),
+        r#"{"status":"SERVING"}"#
+    );
+    let sent = requests.lock().unwrap()[0].clone();
+    assert_eq!(sent["grpc-encoding"], "gzip");
+    assert!(
+        sent["grpc-accept-encoding"]
+            .to_str()
+            .unwrap()
+            .contains("zstd")
+    );
+
+    // nothing is compressed for a client accepting no encoding
+    let result = client
+        .request_with_options(HEALTH, "Check", vec![], &CallOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(result.response_encoding(), None);
+}
+
+#[tokio::test]
+async fn an_encoding_the_server_does_not_accept_is_unimplemented() {
+    let url = common::health_server(0).await.url;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().compression(CompressionEncoding::Deflate);
+    let error = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().unwrap().code(), Code::Unimplemented);
+}
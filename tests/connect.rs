@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+use axum::body::{Body, to_bytes};
+use axum::extract::{Request, State};
+use axum::response::Response;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD_NO_PAD;
+use grpc_client::client::GrpcClientError;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::status_details::decode_status_details;
+use grpc_client::transport::Protocol;
+use grpc_client::{CallOptions, Client};
+use http::StatusCode;
+use http::header::CONTENT_TYPE;
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor};
+use serde_json::json;
+
+const CATALOG: &str = "catalog.v1.Catalog";
+
+fn catalog() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap()
+}
+
+fn decode(desc: MessageDescriptor, bytes: &[u8], json: bool) -> DynamicMessage {
+    match json {
+        true => DynamicMessage::deserialize(desc, &mut serde_json::Deserializer::from_slice(bytes))
+            .unwrap(),
+        false => DynamicMessage::decode(desc, bytes).unwrap(),
+    }
+}
+
+fn encode(message: &DynamicMessage, json: bool) -> Vec<u8> {
+    match json {
+        true => serde_json::to_vec(message).unwrap(),
+        false => message.encode_to_vec(),
+    }
+}
+
+fn envelope(flags: u8, message: &[u8]) -> Vec<u8> {
+    let mut envelope = vec![flags];
+    envelope.extend((message.len() as u32).to_be_bytes());
+    envelope.extend(message);
+    envelope
+}
+
+fn product(pool: &DescriptorPool, id: &str, json: bool) -> Vec<u8> {
+    let desc = pool.get_message_by_name("catalog.v1.Product").unwrap();
+    let product = json!({"id": id, "title": "Lamp"});
+    encode(&DynamicMessage::deserialize(desc, product).unwrap(), json)
+}
+
+/// a connect server for GetProduct and ListProducts, answering in the format it is asked in
+async fn connect_server(State(pool): State<DescriptorPool>, request: Request) -> Response {
+    assert_eq!(request.headers()["connect-protocol-version"], "1");
+    let content_type = request.headers()[CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .to_string();
+    let json = content_type.ends_with("json");
+    let path = request.uri().path().to_string();
+    let body = to_bytes(request.into_body(), usize::MAX).await.unwrap();
+    let response = Response::builder().header(CONTENT_TYPE, &content_type);
+    match path.as_str() {
+        "/catalog.v1.Catalog/GetProduct" => {
+            assert!(!content_type.starts_with("application/connect+"));
+            let desc = pool
+                .get_message_by_name("catalog.v1.GetProductRequest")
+                .unwrap();
+            let id = decode(desc, &body, json)
+                .get_field_by_name("id")
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string();
+            if id == "gone" {
+                let info = tonic_types::pb::ErrorInfo {
+                    reason: "GONE".to_string(),
+                    ..Default::default()
+                };
+                let error = json!({
+                    "code": "not_found",
+                    "message": "no such product",
+                    "details": [{"type": "google.rpc.ErrorInfo", "value": BASE64_STANDARD_NO_PAD.encode(info.encode_to_vec())}],
+                });
+                return response
+                    .status(StatusCode::NOT_FOUND)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(error.to_string()))
+                    .unwrap();
+            }
+            response
+                .header("trailer-x-served-by", "test")
+                .body(Body::from(product(&pool, &id, json)))
+                .unwrap()
+        }
+        "/catalog.v1.Catalog/ListProducts" => {
+            assert!(content_type.starts_with("application/connect+"));
+            let desc = pool
+                .get_message_by_name("catalog.v1.ListProductsRequest")
+                .unwrap();
+            let request = decode(desc, &body[5..], json);
+            let category = request.get_field_by_name("category").unwrap();
+            let mut body = Vec::new();
+            let end = match category.as_str().unwrap() {
+                "lamps" => {
+                    body.extend(envelope(0, &product(&pool, "p-1", json)));
+                    body.extend(envelope(0, &product(&pool, "p-2", json)));
+                    json!({"metadata": {"x-count": ["2"]}})
+                }
+                _ => {
+                    json!({"error": {"code": "resource_exhausted", "message": "too many products"}})
+                }
+            };
+            body.extend(envelope(2, end.to_string().as_bytes()));
+            response.body(Body::from(body)).unwrap()
+        }
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    }
+}
+
+async fn client(protocol: Protocol) -> Client {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let router = axum::Router::new()
+        .fallback(connect_server)
+        .with_state(catalog());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+    let mut client = Client::with_protocol(url, protocol).await.unwrap();
+    client.set_descriptor_pool(catalog());
+    client
+}
+
+fn arguments(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn unary_calls_in_binary_and_json() {
+    for protocol in [Protocol::Connect, Protocol::ConnectJson] {
+        let mut client = client(protocol).await;
+        let options = CallOptions::default();
+        let result = client
+            .request_with_options(
+                CATALOG,
+                "GetProduct",
+                arguments(&[("id", "p-42")]),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&result.messages).unwrap(),
+            r#"[{"id":"p-42","title":"Lamp"}]"#,
+            "{protocol}"
+        );
+        assert_eq!(result.trailers.get("x-served-by").unwrap(), "test");
+
+        let error = client
+            .request_with_options(
+                CATALOG,
+                "GetProduct",
+                arguments(&[("id", "gone")]),
+                &options,
+            )
+            .await
+            .unwrap_err();
+        let status = error.status().unwrap();
+        assert_eq!(status.code(), tonic::Code::NotFound, "{protocol}");
+        assert_eq!(status.message(), "no such product");
+        let details = decode_status_details(status, None).unwrap();
+        assert_eq!(details["details"][0]["reason"], "GONE");
+    }
+}
+
+#[tokio::test]
+async fn streaming_calls_end_with_their_status() {
+    for protocol in [Protocol::Connect, Protocol::ConnectJson] {
+        let mut client = client(protocol).await;
+        let options = CallOptions::default();
+        let result = client
+            .request_with_options(
+                CATALOG,
+                "ListProducts",
+                arguments(&[("category", "lamps")]),
+                &options,
+            )
+            .await
+            .unwrap();
+        let ids: Vec<_> = result
+            .messages
+            .iter()
+            .map(|message| {
+                message
+                    .get_field_by_name("id")
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(ids, ["p-1", "p-2"], "{protocol}");
+        assert_eq!(result.trailers.get("x-count").unwrap(), "2");
+
+        let error = client
+            .request_with_options(
+                CATALOG,
+                "ListProducts",
+                arguments(&[("category", "desks")]),
+                &options,
+            )
+            .await
+            .unwrap_err();
+        let status = error.status().unwrap();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted, "{protocol}");
+        assert_eq!(status.message(), "too many products");
+    }
+}
+
+#[tokio::test]
+async fn https_urls_are_rejected() {
+    for protocol in [Protocol::Connect, Protocol::ConnectJson] {
+        let result = Client::with_protocol("https://localhost:8080".to_string(), protocol).await;
+        assert!(
+            matches!(result, Err(GrpcClientError::InvalidInput(_))),
+            "{protocol}"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use grpc_client::describe::Symbol;
+use grpc_client::descriptor_source::compile_protos;
+use prost_reflect::DescriptorPool;
+
+fn catalog() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap()
+}
+
+fn describe(name: &str) -> String {
+    Symbol::find(&catalog(), name).unwrap().to_string()
+}
+
+#[test]
+fn every_kind_of_symbol_is_found() {
+    let pool = catalog();
+    let kinds: Vec<&str> = [
+        "catalog.v1.Catalog",
+        "catalog.v1.Catalog.GetProduct",
+        ".catalog.v1.Product",
+        "catalog.v1.Product.Price",
+        "catalog.v1.Product.tags",
+        "catalog.v1.Product.availability",
+        "catalog.v1.Product.State",
+        "catalog.v1.Product.State.STATE_ACTIVE",
+        "catalog.v1.Product.STATE_ACTIVE",
+    ]
+    .iter()
+    .map(|name| Symbol::find(&pool, name).unwrap().kind())
+    .collect();
+    assert_eq!(
+        kinds,
+        [
+            "service",
+            "method",
+            "message",
+            "message",
+            "field",
+            "oneof",
+            "enum",
+            "enum value",
+            "enum value"
+        ]
+    );
+    assert!(Symbol::find(&pool, "catalog.v1.Product.missing").is_none());
+    assert!(Symbol::find(&pool, "catalog.v1.Catalog.Missing").is_none());
+}
+
+#[test]
+fn services_keep_comments_streams_and_options() {
+    assert_eq!(
+        describe("catalog.v1.Catalog"),
+        "\
+// Browse and edit the products of the catalog.
+service Catalog {
+  // Get one product by its id.
+  rpc GetProduct(.catalog.v1.GetProductRequest) returns (.catalog.v1.Product);
+  // Every product of a category, one by one.
+  rpc ListProducts(.catalog.v1.ListProductsRequest) returns (stream .catalog.v1.Product);
+  rpc UpdateProducts(stream .catalog.v1.Product) returns (.catalog.v1.UpdateProductsResponse) {
+    option deprecated = true;
+  }
+}
+"
+    );
+}
+
+#[test]
+fn messages_show_nested_types_oneofs_maps_and_reserved_ranges() {
+    assert_eq!(
+        describe("catalog.v1.Product"),
+        r#"// An item that can be bought.
+message Product {
+  option deprecated = false;
+  string id = 1;
+  string name = 2 [json_name = "title"];
+  .catalog.v1.Product.State state = 3;
+  repeated string tags = 4;
+  map<string, .catalog.v1.Product.Price> prices = 5;
+  oneof availability {
+    int32 stock = 6;
+    .google.protobuf.Timestamp restock_at = 7;
+  }
+  .catalog.v1.Product replacement = 8;
+  message Price {
+    string currency = 1;
+    int64 cents = 2;
+  }
+  enum State {
+    STATE_UNSPECIFIED = 0;
+    STATE_ACTIVE = 1;
+    STATE_RETIRED = 2 [deprecated = true];
+    reserved 5 to 9;
+    reserved "STATE_DRAFT";
+  }
+  reserved 10, 12 to 15, 100 to max;
+  reserved "sku";
+}
+"#
+    );
+}
+
+#[test]
+fn fields_keep_their_label_and_comment() {
+    assert_eq!(
+        describe("catalog.v1.ListProductsRequest.page_size"),
+        "optional int32 page_size = 2;\n"
+    );
+    assert_eq!(
+        describe("catalog.v1.GetProductRequest.id"),
+        "// id of the product, ex: \"p-42\"\nstring id = 1;\n"
+    );
+}
+
+#[test]
+fn proto2_oneof_fields_have_no_label() {
+    let pool = compile_protos(&[PathBuf::from("tests/proto/legacy.proto")], &[]).unwrap();
+    let describe = |name| Symbol::find(&pool, name).unwrap().to_string();
+    assert_eq!(
+        describe("legacy.v1.Order"),
+        r#"message Order {
+  required string id = 1;
+  optional int32 quantity = 2;
+  repeated string notes = 3;
+  oneof payment {
+    string card = 4;
+    string voucher = 5;
+  }
+}
+"#
+    );
+    assert_eq!(describe("legacy.v1.Order.card"), "string card = 4;\n");
+}
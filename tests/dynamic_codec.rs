@@ -0,0 +1,30 @@
+mod common;
+
+use grpc_client::{CallOptions, Client};
+use tonic_health::ServingStatus;
+
+#[tokio::test]
+async fn an_empty_frame_is_an_empty_message() {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    // UNKNOWN is the default status, the response is sent as a frame of 0 bytes
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::Unknown)
+        .await;
+    let mut client = Client::new(url).await.unwrap();
+    let arguments = vec![("service".to_string(), "catalog.v1.Catalog".to_string())];
+    let result = client
+        .request_with_options(
+            "grpc.health.v1.Health",
+            "Check",
+            arguments,
+            &CallOptions::default(),
+        )
+        .await
+        .unwrap();
+    let messages: Vec<String> = result
+        .messages
+        .iter()
+        .map(|message| serde_json::to_string(message).unwrap())
+        .collect();
+    assert_eq!(messages, ["{}"]);
+}
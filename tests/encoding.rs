@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use grpc_client::descriptor_source::{DescriptorSource, compile_protos};
+use grpc_client::encoding::{
+    BytesEncoding, MessageSyntax, decode_message, encode_message, parse_message,
+};
+
+fn helloworld() -> prost_reflect::DescriptorPool {
+    compile_protos(&[PathBuf::from("examples/proto/helloworld.proto")], &[]).unwrap()
+}
+
+#[test]
+fn proto_sources_are_compiled() {
+    let source = DescriptorSource::Protos {
+        files: vec![PathBuf::from("helloworld.proto")],
+        import_paths: vec![PathBuf::from("examples/proto")],
+    };
+    let pool = source.load_local().unwrap().unwrap();
+    assert!(pool.get_service_by_name("helloworld.Greeter").is_some());
+    assert!(DescriptorSource::Reflection.load_local().is_none());
+}
+
+#[test]
+fn json_and_text_round_trip_through_every_encoding() {
+    let desc = helloworld()
+        .get_message_by_name("helloworld.GoodbyReply")
+        .unwrap();
+    let from_json = parse_message(
+        desc.clone(),
+        r#"{"message": "bye", "detail": "now"}"#,
+        MessageSyntax::Json,
+    )
+    .unwrap();
+    let from_text = parse_message(
+        desc.clone(),
+        r#"message: "bye" detail: "now""#,
+        MessageSyntax::Text,
+    )
+    .unwrap();
+    assert_eq!(from_json, from_text);
+    for encoding in BytesEncoding::ALL {
+        let encoded = encode_message(&from_json, encoding);
+        let decoded = decode_message(desc.clone(), &encoded, encoding).unwrap();
+        assert_eq!(decoded, from_json, "{encoding} round trip");
+    }
+}
+
+#[test]
+fn invalid_inputs_are_rejected() {
+    let desc = helloworld()
+        .get_message_by_name("helloworld.HelloRequest")
+        .unwrap();
+    assert!(parse_message(desc.clone(), r#"{"nmae": "typo"}"#, MessageSyntax::Json).is_err());
+    assert!(parse_message(desc.clone(), "{} trailing", MessageSyntax::Json).is_err());
+    assert!(decode_message(desc.clone(), b"0a0", BytesEncoding::Hex).is_err());
+    assert!(decode_message(desc, "é1".as_bytes(), BytesEncoding::Hex).is_err());
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use std::process::Output;
+
+use grpc_client::exit_code;
+use serde_json::Value;
+use tonic_health::ServingStatus;
+
+/// run the command line, once the test server is started
+async fn main(args: &[&str]) -> Output {
+    let mut command = std::process::Command::new(env!("CARGO_BIN_EXE_main"));
+    command.args(args);
+    tokio::task::spawn_blocking(move || command.output().unwrap())
+        .await
+        .unwrap()
+}
+
+fn error_json(output: &Output) -> Value {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    serde_json::from_str(stderr.lines().last().unwrap()).unwrap()
+}
+
+/// arguments of a health check call through `get`, with `-a` request fields
+fn check<'a>(url: &'a str, fields: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec!["--url", url, "get", "grpc.health.v1.Health", "Check"];
+    for field in fields {
+        args.extend(["-a", field]);
+    }
+    args
+}
+
+#[tokio::test]
+async fn every_outcome_has_its_exit_code() {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::NotServing)
+        .await;
+    let cases = [
+        (check(&url, &[]), 0),
+        (check(&url, &["service=missing"]), 69),
+        (check(&url, &["unknown=field"]), 2),
+        (
+            vec!["--url", &url, "get", "grpc.health.v1.Health", "Missing"],
+            2,
+        ),
+        (vec!["--url", &url, "health", "catalog.v1.Catalog"], 5),
+        (check("http://127.0.0.1:1", &[]), 3),
+    ];
+    for (args, code) in cases {
+        let output = main(&args).await;
+        assert_eq!(output.status.code(), Some(code), "{args:?}: {output:?}");
+    }
+}
+
+#[tokio::test]
+async fn errors_are_printed_as_json_on_demand() {
+    let url = common::health_server(0).await.url;
+    let json = ["--format-error", "json"];
+    let output = main(&[&json[..], &check(&url, &["service=missing"])].concat()).await;
+    assert_eq!(output.status.code(), Some(69));
+    let error = error_json(&output);
+    assert_eq!(error["error"], "status");
+    assert_eq!(error["code"], "NotFound");
+    assert_eq!(error["exit_code"], 69);
+
+    let output = main(&[&json[..], &check(&url, &["unknown=field"])].concat()).await;
+    let error = error_json(&output);
+    assert_eq!(error["error"], "unknown_field");
+    assert_eq!(error["exit_code"], 2);
+}
+
+#[tokio::test]
+async fn the_help_and_the_readme_list_every_exit_code() {
+    let output = main(&["--help"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains(&exit_code::help()));
+    let readme = include_str!("../README.md");
+    for (code, meaning) in exit_code::EXIT_CODES {
+        let row = readme
+            .lines()
+            .find(|line| line.starts_with(&format!("| {code} ")))
+            .unwrap_or_else(|| panic!("no row for {code} in the README"));
+        assert_eq!(row.split('|').nth(2).unwrap().trim(), *meaning);
+    }
+}
+
+#[tokio::test]
+async fn a_filter_with_spaces_is_a_single_filter() {
+    let common::TestServer { url, .. } = common::health_server(0).await;
+    let output = main(&["--url", &url, "list", "/^grpc.health.v1$|no such package/"]).await;
+    assert_eq!(output.status.code(), Some(0), "{output:?}");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("service Health"));
+}
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+use grpc_client::GrpcFilters;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::filter::{Pattern, Streaming};
+use prost_reflect::DescriptorPool;
+
+fn catalog() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap()
+}
+
+/// full names of the methods kept by the filters
+fn kept(filters: &[&str]) -> Vec<String> {
+    let filters = GrpcFilters::new(filters.iter().map(|f| f.to_string()).collect()).unwrap();
+    kept_by(&filters)
+}
+
+fn kept_by(filters: &GrpcFilters) -> Vec<String> {
+    catalog()
+        .services()
+        .flat_map(|service| service.methods().collect::<Vec<_>>())
+        .filter(|method| !filters.filter_method(method))
+        .map(|method| {
+            method
+                .full_name()
+                .trim_start_matches("catalog.v1.")
+                .to_string()
+        })
+        .collect()
+}
+
+const ALL: [&str; 5] = [
+    "Catalog.GetProduct",
+    "Catalog.ListProducts",
+    "Catalog.UpdateProducts",
+    "CatalogAdmin.DeleteProduct",
+    "CatalogAdmin.Reindex",
+];
+
+#[test]
+fn no_filter_keeps_everything() {
+    assert_eq!(kept(&[]), ALL);
+    assert_eq!(kept(&["catalog.v1"]), ALL);
+    assert!(kept(&["other.v1"]).is_empty());
+}
+
+#[test]
+fn a_single_part_matches_packages_services_and_methods() {
+    assert_eq!(kept(&["*.CatalogAdmin"]), ALL[3..]);
+    assert_eq!(kept(&["CatalogAdmin"]), ALL[3..]);
+    assert_eq!(kept(&["Get*"]), ["Catalog.GetProduct"]);
+    assert_eq!(
+        kept(&["catalog.v1.Catalog.GetProduct", "Reindex"]),
+        ["Catalog.GetProduct", "CatalogAdmin.Reindex"]
+    );
+}
+
+#[test]
+fn parts_are_matched_in_order() {
+    assert_eq!(kept(&["catalog.*,Catalog"]), ALL[..3]);
+    assert_eq!(
+        kept(&[",,*Product"]),
+        ["Catalog.GetProduct", "CatalogAdmin.DeleteProduct"]
+    );
+    assert_eq!(kept(&["catalog.v?,,/(Get|List)Products?/"]), ALL[..2]);
+    // a method part does not match a service name
+    assert!(kept(&[",,Catalog"]).is_empty());
+    // globs and regexes match whole names
+    assert!(kept(&[",,Product"]).is_empty());
+    assert!(kept(&[",,/Product/"]).is_empty());
+}
+
+#[test]
+fn exclude_filters_win_over_include_filters() {
+    assert_eq!(kept(&["!*.CatalogAdmin*"]), ALL[..3]);
+    assert_eq!(kept(&["!*Admin*", "!,,Update*"]), ALL[..2]);
+    assert_eq!(
+        kept(&["Catalog*", "!,,/.*Products?/"]),
+        ["CatalogAdmin.Reindex"]
+    );
+}
+
+#[test]
+fn services_and_files_are_kept_by_their_methods() {
+    let pool = catalog();
+    let admin = pool.get_service_by_name("catalog.v1.CatalogAdmin").unwrap();
+    let catalog = pool.get_service_by_name("catalog.v1.Catalog").unwrap();
+    let file = catalog.parent_file();
+    let filters = GrpcFilters::new(vec![",,Reindex".to_string()]).unwrap();
+    assert!(!filters.filter_service(&admin));
+    assert!(filters.filter_service(&catalog));
+    assert!(!filters.filter_file(&file));
+    let filters = GrpcFilters::new(vec!["!catalog.v1".to_string()]).unwrap();
+    assert!(filters.filter_file(&file));
+}
+
+#[test]
+fn regexes_can_hold_commas_and_invalid_ones_are_errors() {
+    assert_eq!(kept(&[",,/[A-Z][a-z]{4,5}Product/"]), ALL[3..4]);
+    assert!(GrpcFilters::new(vec!["/(/".to_string()]).is_err());
+    assert!(GrpcFilters::new(vec!["a,b,c,d".to_string()]).is_err());
+}
+
+fn pattern(s: &str) -> Pattern {
+    s.parse().unwrap()
+}
+
+#[test]
+fn file_path_and_package_are_told_apart() {
+    let by_file = GrpcFilters::new_empty().file(pattern("catalog.proto"));
+    assert_eq!(kept_by(&by_file), ALL);
+    let package_as_file = GrpcFilters::new_empty().file(pattern("catalog.v1"));
+    assert!(kept_by(&package_as_file).is_empty());
+    let by_package = GrpcFilters::new_empty().package(pattern("catalog.*"));
+    assert_eq!(kept_by(&by_package), ALL);
+    let file_as_package = GrpcFilters::new_empty().package(pattern("*.proto"));
+    assert!(kept_by(&file_as_package).is_empty());
+}
+
+#[test]
+fn methods_are_selected_by_their_types() {
+    let input = GrpcFilters::new_empty().input_type(pattern("GetProductRequest"));
+    assert_eq!(
+        kept_by(&input),
+        ["Catalog.GetProduct", "CatalogAdmin.DeleteProduct"]
+    );
+    let output = GrpcFilters::new_empty().output_type(pattern("catalog.v1.Product"));
+    assert_eq!(kept_by(&output), ALL[..2]);
+}
+
+#[test]
+fn methods_are_selected_by_how_they_stream() {
+    let streaming = |kind| kept_by(&GrpcFilters::new_empty().streaming(kind));
+    assert_eq!(streaming(Streaming::Server), ["Catalog.ListProducts"]);
+    assert_eq!(streaming(Streaming::Client), ["Catalog.UpdateProducts"]);
+    assert_eq!(
+        streaming(Streaming::Any),
+        ["Catalog.ListProducts", "Catalog.UpdateProducts"]
+    );
+    assert!(streaming(Streaming::Bidi).is_empty());
+    assert_eq!(
+        streaming(Streaming::Unary),
+        [
+            "Catalog.GetProduct",
+            "CatalogAdmin.DeleteProduct",
+            "CatalogAdmin.Reindex"
+        ]
+    );
+}
+
+#[test]
+fn predicates_combine_with_the_filters() {
+    let deprecated = GrpcFilters::new_empty().deprecated(true);
+    assert_eq!(kept_by(&deprecated), ["Catalog.UpdateProducts"]);
+    let current_catalog = GrpcFilters::new(vec![",Catalog".to_string()])
+        .unwrap()
+        .deprecated(false);
+    assert_eq!(kept_by(&current_catalog), ALL[..2]);
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use grpc_client::client::GrpcClientError;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::fuzz::{FuzzConfig, run};
+use grpc_client::mock::{MockServer, Rule};
+use grpc_client::{CallOptions, Client};
+use tonic::transport::server::TcpIncoming;
+
+const RULES: &str = r#"
+- {method: GetProduct, status: internal, message: boom}
+- {method: DeleteProduct, status: invalid-argument}
+"#;
+
+/// a mock of the catalog failing on every GetProduct, return a client connected to it
+async fn catalog() -> Client {
+    let pool = compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap();
+    let rules: Vec<Rule> = serde_yaml_ng::from_str(RULES).unwrap();
+    let mock = MockServer::new(pool).rules(rules).unwrap();
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(mock.serve(incoming));
+    Client::new(url).await.unwrap()
+}
+
+#[tokio::test]
+async fn internal_errors_are_found_again_with_the_seed() {
+    let mut client = catalog().await;
+    let config = FuzzConfig::default().requests(5).seed(3);
+    let options = CallOptions::default();
+    let mut printed = 0;
+    let report = run(
+        &mut client,
+        "catalog.v1.Catalog",
+        "GetProduct",
+        &options,
+        &config,
+        |_| {
+            printed += 1;
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(printed, 5);
+    assert_eq!(report.seed, 3);
+    assert_eq!(report.status_codes["Internal"], 5);
+    assert_eq!(report.findings[4].index, 4);
+    assert_eq!(report.findings[0].message, "boom");
+    assert!(matches!(
+        report.first_error,
+        Some(GrpcClientError::ReflectionRequestError(_))
+    ));
+
+    let again = run(
+        &mut client,
+        "catalog.v1.Catalog",
+        "GetProduct",
+        &options,
+        &config,
+        |_| {},
+    )
+    .await
+    .unwrap();
+    let requests = |report: &grpc_client::fuzz::FuzzReport| {
+        report
+            .findings
+            .iter()
+            .map(|f| f.request.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(requests(&report), requests(&again));
+}
+
+#[tokio::test]
+async fn rejected_requests_are_not_findings() {
+    let mut client = catalog().await;
+    let options = CallOptions::default();
+    let config = FuzzConfig::default()
+        .requests(4)
+        .max_depth(0)
+        .max_entries(0);
+    for (service, method, status) in [
+        (
+            "catalog.v1.CatalogAdmin",
+            "DeleteProduct",
+            "InvalidArgument",
+        ),
+        ("catalog.v1.Catalog", "ListProducts", "Ok"),
+    ] {
+        let report = run(&mut client, service, method, &options, &config, |_| {})
+            .await
+            .unwrap();
+        assert!(report.findings.is_empty(), "{report:?}");
+        assert_eq!(report.status_codes[status], 4);
+    }
+    let missing = run(
+        &mut client,
+        "catalog.v1.Catalog",
+        "Missing",
+        &options,
+        &config,
+        |_| {},
+    )
+    .await;
+    assert!(matches!(missing, Err(GrpcClientError::NotFoundError(_))));
+}
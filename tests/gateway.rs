@@ -0,0 +1,224 @@
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use grpc_client::Client;
+use grpc_client::cassette::{Cassette, Player};
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::gateway::Gateway;
+use grpc_client::mock::MockServer;
+use http::{Method, StatusCode};
+use http_body_util::{BodyExt, Full};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use prost_reflect::DescriptorPool;
+use serde_json::{Value, json};
+use tonic::transport::server::TcpIncoming;
+
+const RULES: &str = r#"
+- method: GetBook
+  match: {name: shelves/1/books/2}
+  response: {name: shelves/1/books/2, title: Dune}
+- method: GetBook
+  match: {name: shelves/1/books/404}
+  status: not-found
+  message: no such book
+- method: ListBooks
+  match: {shelf: 1, pageSize: 2, availableOnly: true, tags: [scifi, classic]}
+  responses: [{title: Dune}, {title: Hyperion}]
+- method: CreateBook
+  match: {shelf: 3, book: {title: Solaris}}
+  response: {title: Solaris, pages: 204}
+- method: DeleteBook
+  match: {name: shelves/1/books/2}
+  response: {deleted: {title: Dune}}
+- method: Ping
+  response: {title: pong}
+"#;
+
+fn library() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/library.proto")], &[]).unwrap()
+}
+
+/// a gateway in front of a mock of the library, return its url
+async fn gateway() -> String {
+    let rules = serde_yaml_ng::from_str(RULES).unwrap();
+    let mock = MockServer::new(library())
+        .rules(rules)
+        .unwrap()
+        .random(false);
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let mock_url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(mock.serve(incoming));
+
+    // the reflection of the mock keeps the annotations
+    let mut client = Client::new(mock_url).await.unwrap();
+    let pool = client.descriptor_pool().await.unwrap();
+    let gateway = Gateway::new(client, pool);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(gateway.serve(listener));
+    url
+}
+
+async fn call(method: Method, url: String, body: &str) -> (StatusCode, Value) {
+    let client = hyper_util::client::legacy::Client::builder(TokioExecutor::new())
+        .build::<_, Full<Bytes>>(HttpConnector::new());
+    let request = http::Request::builder()
+        .method(method)
+        .uri(url)
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap();
+    let response = client.request(request).await.unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn annotated_routes_build_the_request_from_the_path_query_and_body() {
+    let url = gateway().await;
+
+    let (status, book) = call(Method::GET, format!("{url}/v1/shelves/1/books/2"), "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(book, json!({"name": "shelves/1/books/2", "title": "Dune"}));
+
+    let query = "pageSize=2&available_only=true&tags=scifi&tags=classic";
+    let (status, books) = call(Method::GET, format!("{url}/v1/shelves/1/books?{query}"), "").await;
+    assert_eq!(status, StatusCode::OK, "{books}");
+    assert_eq!(books, json!([{"title": "Dune"}, {"title": "Hyperion"}]));
+
+    let created = json!({"title": "Solaris", "pages": 204});
+    let (status, book) = call(
+        Method::POST,
+        format!("{url}/v1/shelves/3/books"),
+        r#"{"title": "Solaris"}"#,
+    )
+    .await;
+    assert_eq!((status, book), (StatusCode::OK, created.clone()));
+    // the additional binding takes the whole request from the body
+    let (status, book) = call(
+        Method::PUT,
+        format!("{url}/v1/shelves/3/books:create"),
+        r#"{"book": {"title": "Solaris"}}"#,
+    )
+    .await;
+    assert_eq!((status, book), (StatusCode::OK, created));
+
+    let (status, deleted) = call(Method::DELETE, format!("{url}/v1/shelves/1/books/2"), "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deleted["title"], "Dune");
+}
+
+#[tokio::test]
+async fn every_method_is_served_at_its_grpc_path() {
+    let url = gateway().await;
+    let (status, pong) = call(Method::POST, format!("{url}/library.v1.Library/Ping"), "").await;
+    assert_eq!((status, pong), (StatusCode::OK, json!({"title": "pong"})));
+    let (status, book) = call(
+        Method::POST,
+        format!("{url}/library.v1.Library/GetBook"),
+        r#"{"name": "shelves/1/books/2"}"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(book["title"], "Dune");
+
+    let routes: Vec<String> = Gateway::new(
+        Client::replay(Player::new(Cassette::default()).unwrap()),
+        library(),
+    )
+    .routes()
+    .iter()
+    .map(|route| route.to_string())
+    .collect();
+    assert_eq!(
+        routes[0],
+        "GET /v1/{name=shelves/*/books/*} -> library.v1.Library.GetBook"
+    );
+    assert!(
+        routes.contains(&"POST /library.v1.Library/Ping -> library.v1.Library.Ping".to_string())
+    );
+}
+
+#[tokio::test]
+async fn the_routes_are_read_through_reflection() {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let mock_url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(MockServer::new(library()).serve(incoming));
+    let mut client = Client::new(mock_url).await.unwrap();
+    let pool = client.descriptor_pool().await.unwrap();
+
+    let get_book = pool
+        .get_service_by_name("library.v1.Library")
+        .unwrap()
+        .methods()
+        .find(|method| method.name() == "GetBook")
+        .unwrap();
+    let http = pool.get_extension_by_name("google.api.http").unwrap();
+    let rule = get_book.options().get_extension(&http).into_owned();
+    let rule = rule.as_message().unwrap();
+    assert_eq!(
+        rule.get_field_by_name("get").unwrap().as_str(),
+        Some("/v1/{name=shelves/*/books/*}")
+    );
+    let routes = Gateway::new(client, pool).routes().len();
+    let given = Gateway::new(
+        Client::replay(Player::new(Cassette::default()).unwrap()),
+        library(),
+    )
+    .routes()
+    .len();
+    assert_eq!(routes, given);
+}
+
+#[tokio::test]
+async fn errors_are_json_statuses() {
+    let url = gateway().await;
+    let (status, error) = call(Method::GET, format!("{url}/v1/shelves/1/books/404"), "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(error["code"], 5);
+    assert_eq!(error["message"], "no such book");
+
+    let (status, error) = call(Method::GET, format!("{url}/v1/shelves/one/books"), "").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{error}");
+    assert_eq!(error["code"], 3);
+    let (status, _) = call(Method::POST, format!("{url}/library.v1.Library/Ping"), "{").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(Method::GET, format!("{url}/v2/shelves"), "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn responses_without_a_json_mapping_are_internal_errors() {
+    // the mock knows the type of the payload, the gateway does not
+    const RULES: &str = r#"
+- method: Get
+  response: {id: e-1, payload: {"@type": type.googleapis.com/catalog.v1.Product, id: p-1}}
+"#;
+    let protos = ["tests/proto/events.proto", "tests/proto/catalog.proto"].map(PathBuf::from);
+    let rules = serde_yaml_ng::from_str(RULES).unwrap();
+    let mock = MockServer::new(compile_protos(&protos, &[]).unwrap())
+        .rules(rules)
+        .unwrap()
+        .random(false);
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let mock_url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(mock.serve(incoming));
+
+    let client = Client::new(mock_url).await.unwrap();
+    let gateway = Gateway::new(client, compile_protos(&protos[..1], &[]).unwrap());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(gateway.serve(listener));
+
+    let (status, error) = call(Method::POST, format!("{url}/events.v1.Events/Get"), "").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(error["code"], 13);
+    assert!(
+        error["message"]
+            .as_str()
+            .unwrap()
+            .contains("catalog.v1.Product"),
+        "{error}"
+    );
+}
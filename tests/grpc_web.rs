@@ -0,0 +1,122 @@
+use grpc_client::client::GrpcClientError;
+use grpc_client::grpc_web::GrpcWebDecoder;
+use grpc_client::transport::FrameDecoder;
+use grpc_client::transport::Protocol;
+use grpc_client::{CallOptions, Client};
+use tonic::transport::Server;
+use tonic::transport::server::TcpIncoming;
+use tonic_health::ServingStatus as ReportedStatus;
+use tonic_health::pb::health_check_response::ServingStatus;
+
+/// start a server answering grpc-web only over http/1.1, like one behind the envoy grpc_web filter
+async fn grpc_web_server() -> String {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    reporter
+        .set_service_status("catalog.v1.Catalog", ReportedStatus::NotServing)
+        .await;
+    let reflection = tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET)
+        .build_v1()
+        .unwrap();
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(
+        Server::builder()
+            .accept_http1(true)
+            .layer(tonic_web::GrpcWebLayer::new())
+            .add_service(health)
+            .add_service(reflection)
+            .serve_with_incoming(incoming),
+    );
+    // the reporter is dropped, the statuses set stay
+    url
+}
+
+#[tokio::test]
+async fn reflection_and_calls_work_over_grpc_web() {
+    let url = grpc_web_server().await;
+    for protocol in [Protocol::GrpcWeb, Protocol::GrpcWebText] {
+        let mut client = Client::with_protocol(url.clone(), protocol).await.unwrap();
+        let pool = client.descriptor_pool().await.unwrap();
+        assert!(
+            pool.get_service_by_name("grpc.health.v1.Health").is_some(),
+            "{protocol}"
+        );
+
+        let options = CallOptions::default();
+        let arguments = vec![("service".to_string(), "catalog.v1.Catalog".to_string())];
+        let result = client
+            .request_with_options("grpc.health.v1.Health", "Check", arguments, &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&result.messages).unwrap(),
+            r#"[{"status":"NOT_SERVING"}]"#,
+            "{protocol}"
+        );
+        assert_eq!(
+            client.check_health("", &options).await.unwrap(),
+            ServingStatus::Serving
+        );
+
+        // the status comes in the trailers at the end of the body
+        let error = client
+            .check_health("missing.Service", &options)
+            .await
+            .unwrap_err();
+        assert_eq!(error.exit_code(), 69, "{protocol}: {error:?}");
+    }
+}
+
+#[tokio::test]
+async fn a_stopped_server_is_a_connection_failure() {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    drop(incoming);
+    let mut client = Client::with_protocol(url, Protocol::GrpcWeb).await.unwrap();
+    let error = client.descriptor_pool().await.unwrap_err();
+    let status = error.status().map(|status| status.code());
+    assert_eq!(status, Some(tonic::Code::Unavailable), "{error:?}");
+    assert!(!matches!(error, GrpcClientError::EmptyResponse(_)));
+}
+
+#[test]
+fn text_bodies_are_decoded_whatever_their_chunks() {
+    use base64::Engine;
+    let body = b"\x00\x00\x00\x00\x03abc\x80\x00\x00\x00\x22grpc-status:5\r\ngrpc-message:gone\r\n";
+    // a server may encode and pad each frame on its own
+    let text = [
+        base64::prelude::BASE64_STANDARD.encode(&body[..8]),
+        base64::prelude::BASE64_STANDARD.encode(&body[8..]),
+    ]
+    .concat();
+    let mut decoder = GrpcWebDecoder::new(true);
+    let mut frames = Vec::new();
+    for chunk in text.as_bytes().chunks(3) {
+        frames.extend(decoder.decode(chunk).unwrap());
+    }
+    decoder.finish().unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        frames[0].data_ref().unwrap().as_ref(),
+        b"\x00\x00\x00\x00\x03abc"
+    );
+    let trailers = frames[1].trailers_ref().unwrap();
+    assert_eq!(trailers["grpc-status"], "5");
+    assert_eq!(trailers["grpc-message"], "gone");
+
+    let mut truncated = GrpcWebDecoder::new(false);
+    assert!(truncated.decode(&body[..6]).unwrap().is_empty());
+    assert!(truncated.finish().is_err());
+}
+
+#[tokio::test]
+async fn https_urls_are_rejected() {
+    for protocol in [Protocol::GrpcWeb, Protocol::GrpcWebText] {
+        let result = Client::with_protocol("https://localhost:8080".to_string(), protocol).await;
+        assert!(
+            matches!(result, Err(GrpcClientError::InvalidInput(_))),
+            "{protocol}"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use grpc_client::client::GrpcClientError;
+use grpc_client::{CallOptions, Client};
+use tokio_stream::StreamExt;
+use tonic_health::ServingStatus as ReportedStatus;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::server::HealthReporter;
+
+async fn health_server() -> (HealthReporter, Client) {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    (reporter, Client::new(url).await.unwrap())
+}
+
+#[tokio::test]
+async fn check_reports_the_status_of_each_service() {
+    let (reporter, mut client) = health_server().await;
+    reporter
+        .set_service_status("catalog.v1.Catalog", ReportedStatus::NotServing)
+        .await;
+    let options = CallOptions::default();
+    // the whole server
+    assert_eq!(
+        client.check_health("", &options).await.unwrap(),
+        ServingStatus::Serving
+    );
+    assert_eq!(
+        client
+            .check_health("catalog.v1.Catalog", &options)
+            .await
+            .unwrap(),
+        ServingStatus::NotServing
+    );
+    let unknown = client.check_health("missing.Service", &options).await;
+    let Err(error @ GrpcClientError::ReflectionRequestError(_)) = unknown else {
+        panic!("unexpected result {unknown:?}");
+    };
+    assert_eq!(error.exit_code(), 69);
+    assert_eq!(
+        GrpcClientError::NotServing(ServingStatus::NotServing).exit_code(),
+        5
+    );
+}
+
+#[tokio::test]
+async fn watch_follows_status_changes() {
+    let (reporter, mut client) = health_server().await;
+    reporter
+        .set_service_status("catalog.v1.Catalog", ReportedStatus::Serving)
+        .await;
+    let updates = client
+        .watch_health("catalog.v1.Catalog", &CallOptions::default())
+        .await
+        .unwrap();
+    let mut updates = std::pin::pin!(updates);
+    assert_eq!(
+        updates.next().await.unwrap().unwrap(),
+        ServingStatus::Serving
+    );
+    reporter
+        .set_service_status("catalog.v1.Catalog", ReportedStatus::NotServing)
+        .await;
+    assert_eq!(
+        updates.next().await.unwrap().unwrap(),
+        ServingStatus::NotServing
+    );
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use grpc_client::GrpcFilters;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::listing::{list_services, render_message, render_services};
+use prost_reflect::DescriptorPool;
+
+fn catalog() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap()
+}
+
+#[test]
+fn messages_are_expanded_across_files_until_a_cycle() {
+    let product = catalog().get_message_by_name("catalog.v1.Product").unwrap();
+    assert_eq!(
+        render_message(&product),
+        "\
+string id = 1
+string name = 2
+.catalog.v1.Product.State state = 3
+  STATE_UNSPECIFIED = 0
+  STATE_ACTIVE = 1
+  STATE_RETIRED = 2 [deprecated]
+repeated string tags = 4
+map<string, .catalog.v1.Product.Price> prices = 5
+  string currency = 1
+  int64 cents = 2
+oneof availability
+  int32 stock = 6
+  .google.protobuf.Timestamp restock_at = 7
+    int64 seconds = 1
+    int32 nanos = 2
+.catalog.v1.Product replacement = 8 (recursive)
+"
+    );
+}
+
+#[test]
+fn services_show_both_messages_of_each_method() {
+    let filters = GrpcFilters::new(vec!["catalog.v1,Catalog,ListProducts".to_string()]).unwrap();
+    assert_eq!(
+        render_services(&catalog(), &filters)
+            .lines()
+            .take(7)
+            .collect::<Vec<_>>(),
+        [
+            "",
+            "file catalog.proto (package catalog.v1) :",
+            "  service Catalog",
+            "    rpc ListProducts(.catalog.v1.ListProductsRequest) returns (stream .catalog.v1.Product)",
+            "      input .catalog.v1.ListProductsRequest",
+            "        string category = 1",
+            "        optional int32 page_size = 2",
+        ]
+    );
+    let deprecated =
+        GrpcFilters::new(vec!["catalog.v1,Catalog,UpdateProducts".to_string()]).unwrap();
+    assert!(render_services(&catalog(), &deprecated).contains(
+        "rpc UpdateProducts(stream .catalog.v1.Product) returns (.catalog.v1.UpdateProductsResponse) [deprecated]\n"
+    ));
+}
+
+#[test]
+fn packages_are_serialized_with_streaming_flags_and_options() {
+    let packages = list_services(&catalog(), &GrpcFilters::new_empty());
+    assert_eq!(packages.len(), 1);
+    let json = serde_json::to_value(&packages).unwrap();
+    let methods = &json[0]["services"][0]["methods"];
+    assert_eq!(json[0]["name"], "catalog.v1");
+    assert_eq!(json[0]["services"][0]["file"], "catalog.proto");
+    assert_eq!(methods[1]["output_type"], "catalog.v1.Product");
+    assert_eq!(methods[1]["server_streaming"], true);
+    assert_eq!(methods[2]["client_streaming"], true);
+    assert_eq!(methods[2]["deprecated"], true);
+    assert_eq!(
+        methods[2]["options"],
+        serde_json::json!({"deprecated": true})
+    );
+    assert!(methods[0].get("options").is_none());
+}
+
+#[test]
+fn every_service_and_method_of_a_deprecated_file_is_deprecated() {
+    let pool = compile_protos(&[PathBuf::from("tests/proto/legacy.proto")], &[]).unwrap();
+    let json = serde_json::to_value(list_services(&pool, &GrpcFilters::new_empty())).unwrap();
+    let service = &json[0]["services"][0];
+    assert_eq!(service["name"], "Orders");
+    assert_eq!(service["deprecated"], true);
+    assert_eq!(service["methods"][0]["deprecated"], true);
+    assert!(
+        render_services(&pool, &GrpcFilters::new_empty())
+            .contains("rpc PlaceOrder(.legacy.v1.Order) returns (.legacy.v1.Order) [deprecated]\n")
+    );
+}
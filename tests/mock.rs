@@ -0,0 +1,224 @@
+use std::path::PathBuf;
+
+use grpc_client::cassette::Recorder;
+use grpc_client::client::GrpcClientError;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::mock::{MockServer, Rule};
+use grpc_client::{CallOptions, Client, GrpcFilters};
+use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+use tonic::transport::server::TcpIncoming;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+
+const CATALOG: &str = "catalog.v1.Catalog";
+
+const RULES: &str = r#"
+- method: catalog.v1.Catalog.GetProduct
+  match: {id: p-42}
+  response: {id: p-42, title: Lamp}
+- method: GetProduct
+  match: {id: gone}
+  status: not-found
+  message: no such product
+- method: ListProducts
+  match: {category: lamps}
+  responses: [{id: p-1}, {id: p-2}]
+"#;
+
+fn catalog() -> DescriptorPool {
+    compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap()
+}
+
+/// start the mock on a free port, return a client connected to it
+async fn mock(mock: MockServer) -> Client {
+    let incoming = TcpIncoming::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let url = format!("http://{}", incoming.local_addr().unwrap());
+    tokio::spawn(mock.serve(incoming));
+    Client::new(url).await.unwrap()
+}
+
+fn arguments(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+async fn get(
+    client: &mut Client,
+    method: &str,
+    pairs: &[(&str, &str)],
+) -> Result<Vec<String>, GrpcClientError> {
+    let result = client
+        .request_with_options(CATALOG, method, arguments(pairs), &CallOptions::default())
+        .await?;
+    Ok(result
+        .messages
+        .iter()
+        .map(|message| serde_json::to_string(message).unwrap())
+        .collect())
+}
+
+#[tokio::test]
+async fn rules_answer_the_matching_requests() {
+    let rules = serde_yaml_ng::from_str(RULES).unwrap();
+    let mut client = mock(MockServer::new(catalog()).rules(rules).unwrap()).await;
+
+    let product = get(&mut client, "GetProduct", &[("id", "p-42")])
+        .await
+        .unwrap();
+    assert_eq!(product, [r#"{"id":"p-42","title":"Lamp"}"#]);
+
+    let error = get(&mut client, "GetProduct", &[("id", "gone")])
+        .await
+        .unwrap_err();
+    let status = error.status().unwrap();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+    assert_eq!(status.message(), "no such product");
+
+    let products = get(&mut client, "ListProducts", &[("category", "lamps")])
+        .await
+        .unwrap();
+    assert_eq!(products, [r#"{"id":"p-1"}"#, r#"{"id":"p-2"}"#]);
+
+    // no rule matches, the product is a random one
+    let products = get(&mut client, "ListProducts", &[("category", "desks")])
+        .await
+        .unwrap();
+    assert!((1..=3).contains(&products.len()));
+    let empty = get(&mut client, "GetProduct", &[]).await.unwrap();
+    assert_eq!(empty.len(), 1);
+}
+
+#[tokio::test]
+async fn the_mock_is_discovered_with_reflection() {
+    let mut client = mock(MockServer::new(catalog())).await;
+    let pool = client.descriptor_pool().await.unwrap();
+    let filters = GrpcFilters::new_empty();
+    let services: Vec<String> = grpc_client::listing::list_services(&pool, &filters)
+        .iter()
+        .flat_map(|package| {
+            package
+                .services
+                .iter()
+                .map(|service| service.full_name.clone())
+        })
+        .collect();
+    assert!(services.contains(&CATALOG.to_string()), "{services:?}");
+    assert!(services.contains(&"catalog.v1.CatalogAdmin".to_string()));
+    let symbol = client.describe("catalog.v1.Product.Price").await.unwrap();
+    assert_eq!(symbol.kind(), "message");
+}
+
+#[tokio::test]
+async fn reflection_sends_the_files_with_their_extension_options() {
+    let library = compile_protos(&[PathBuf::from("tests/proto/library.proto")], &[]).unwrap();
+    let mut client = mock(MockServer::new(library.clone())).await;
+    let response = client
+        .make_reflection_request(MessageRequest::FileContainingSymbol(
+            "library.v1.Library.GetBook".to_string(),
+        ))
+        .await
+        .unwrap();
+    let MessageResponse::FileDescriptorResponse(response) = response else {
+        panic!("unexpected response {response:?}");
+    };
+    // the google.api.http options are only in the encoding of prost-reflect
+    let file = library
+        .get_service_by_name("library.v1.Library")
+        .unwrap()
+        .parent_file();
+    assert_eq!(response.file_descriptor_proto, [file.encode_to_vec()]);
+}
+
+#[tokio::test]
+async fn without_random_unmatched_calls_are_unimplemented() {
+    let rule = Rule {
+        method: "Reindex".to_string(),
+        response: Some(serde_json::json!({"updated": 3})),
+        ..Rule::default()
+    };
+    let mock_server = MockServer::new(catalog())
+        .rules(vec![rule])
+        .unwrap()
+        .random(false);
+    let mut client = mock(mock_server).await;
+    let result = client
+        .request_with_options(
+            "catalog.v1.CatalogAdmin",
+            "Reindex",
+            vec![],
+            &CallOptions::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&result.messages[0]).unwrap(),
+        r#"{"updated":3}"#
+    );
+    let error = get(&mut client, "GetProduct", &[("id", "p-42")])
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().unwrap().code(), tonic::Code::Unimplemented);
+}
+
+#[tokio::test]
+async fn requests_without_a_json_mapping_match_no_fields() {
+    const RULES: &str = r#"
+- {method: Publish, match: {id: e-1}, status: internal}
+- {method: Publish}
+"#;
+    let pool = compile_protos(&[PathBuf::from("tests/proto/events.proto")], &[]).unwrap();
+    let rules: Vec<Rule> = serde_yaml_ng::from_str(RULES).unwrap();
+    let mock_server = MockServer::new(pool).rules(rules).unwrap().random(false);
+    let mut client = mock(mock_server).await;
+    let recorder = Recorder::new();
+    client.record(recorder.clone());
+    let method = client
+        .find_method("events.v1.Events", "Publish")
+        .await
+        .unwrap();
+
+    // the type of the payload is not in the descriptors of the mock
+    let mut payload = DynamicMessage::new(
+        method
+            .parent_pool()
+            .get_message_by_name("google.protobuf.Any")
+            .unwrap(),
+    );
+    payload.set_field_by_name(
+        "type_url",
+        Value::String("type.googleapis.com/unknown.v1.Thing".to_string()),
+    );
+    payload.set_field_by_name("value", Value::Bytes(vec![8, 1].into()));
+    let mut event = DynamicMessage::new(method.input());
+    event.set_field_by_name("id", Value::String("e-1".to_string()));
+    event.set_field_by_name("payload", Value::Message(payload));
+    let result = client
+        .request_message(&method, event, &CallOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(serde_json::to_string(&result.messages[0]).unwrap(), "{}");
+    // nor can it be recorded
+    assert!(recorder.cassette().interactions.is_empty());
+}
+
+#[test]
+fn invalid_rules_are_rejected() {
+    let invalid = [
+        "- {method: Missing}",
+        "- {method: GetProduct, status: no-such-code}",
+        "- {method: GetProduct, response: {sku: 1}}",
+        "- {method: GetProduct, match: {name: x}}",
+        "- {method: GetProduct, responses: [{}, {}]}",
+        "- {method: GetProduct, status: internal, response: {}}",
+    ];
+    for rules in invalid {
+        let rules: Vec<Rule> = serde_yaml_ng::from_str(rules).unwrap();
+        let result = MockServer::new(catalog()).rules(rules);
+        assert!(
+            matches!(result, Err(GrpcClientError::InvalidInput(_))),
+            "{result:?}"
+        );
+    }
+}
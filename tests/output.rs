@@ -0,0 +1,137 @@
+mod common;
+
+use std::path::PathBuf;
+
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::output::OutputFormat;
+use grpc_client::{CallOptions, Client, OutputOptions};
+use prost_reflect::{DescriptorPool, DynamicMessage, Value};
+use serde_json::json;
+use tonic_health::ServingStatus;
+
+/// a `google.rpc.BadRequest` with two field violations, the repeated message we have at hand
+fn bad_request() -> DynamicMessage {
+    let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+    let violation_desc = pool
+        .get_message_by_name("google.rpc.BadRequest.FieldViolation")
+        .unwrap();
+    let violations = [("name", "too long"), ("email", "invalid")]
+        .into_iter()
+        .map(|(field, description)| {
+            let mut violation = DynamicMessage::new(violation_desc.clone());
+            violation.set_field_by_name("field", Value::String(field.to_string()));
+            violation.set_field_by_name("description", Value::String(description.to_string()));
+            Value::Message(violation)
+        })
+        .collect();
+    let mut message =
+        DynamicMessage::new(pool.get_message_by_name("google.rpc.BadRequest").unwrap());
+    message.set_field_by_name("field_violations", Value::List(violations));
+    message
+}
+
+fn render(format: OutputFormat) -> String {
+    let mut out = Vec::new();
+    format
+        .formatter(OutputOptions::default())
+        .write_message(&bad_request(), &mut out)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn table_renders_repeated_messages_as_columns() {
+    let table = render(OutputFormat::Table);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines[1], "fieldViolations:");
+    assert!(lines[2].starts_with("field  description  reason  localizedMessage"));
+    assert!(lines[4].starts_with("name   too long"));
+    assert!(lines[5].starts_with("email  invalid"));
+}
+
+#[test]
+fn yaml_keeps_the_field_order() {
+    let yaml = render(OutputFormat::Yaml);
+    assert!(yaml.starts_with("---\nfieldViolations:\n- field: name\n  description: too long\n"));
+}
+
+#[test]
+fn binary_and_hex_match_the_wire_format() {
+    use prost::Message;
+    let bytes = bad_request().encode_to_vec();
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    assert_eq!(render(OutputFormat::Hex), format!("{hex}\n"));
+    let mut out = Vec::new();
+    OutputFormat::Binary
+        .formatter(OutputOptions::default())
+        .write_message(&bad_request(), &mut out)
+        .unwrap();
+    assert_eq!(out, bytes);
+}
+
+#[tokio::test]
+async fn options_change_the_json_of_the_responses() {
+    let common::TestServer { reporter, url, .. } = common::health_server(0).await;
+    reporter
+        .set_service_status("catalog.v1.Catalog", ServingStatus::Unknown)
+        .await;
+    let mut client = Client::new(url).await.unwrap();
+    let check = |service: &str| vec![("service".to_string(), service.to_string())];
+    let options = CallOptions::default();
+    let serving = client
+        .request_with_options("grpc.health.v1.Health", "Check", check(""), &options)
+        .await
+        .unwrap()
+        .into_message()
+        .unwrap();
+    let unknown = client
+        .request_with_options(
+            "grpc.health.v1.Health",
+            "Check",
+            check("catalog.v1.Catalog"),
+            &options,
+        )
+        .await
+        .unwrap()
+        .into_message()
+        .unwrap();
+
+    let default = OutputOptions::default();
+    assert_eq!(
+        default.to_json(&serving).unwrap(),
+        "{\n  \"status\": \"SERVING\"\n}"
+    );
+    let compact = default.compact(true);
+    assert_eq!(
+        compact.to_json(&serving).unwrap(),
+        r#"{"status":"SERVING"}"#
+    );
+    let numbers = compact.clone().enums_as_ints(true);
+    assert_eq!(numbers.to_json(&serving).unwrap(), r#"{"status":1}"#);
+    // UNKNOWN is the default value of the status
+    assert_eq!(compact.to_json(&unknown).unwrap(), "{}");
+    let defaults = compact.clone().emit_defaults(true);
+    assert_eq!(
+        defaults.to_json(&unknown).unwrap(),
+        r#"{"status":"UNKNOWN"}"#
+    );
+}
+
+#[test]
+fn options_change_the_field_names_and_64_bits_integers() {
+    let pool = compile_protos(&[PathBuf::from("tests/proto/catalog.proto")], &[]).unwrap();
+    let desc = pool.get_message_by_name("catalog.v1.Product").unwrap();
+    let product = json!({"title": "Lamp", "prices": {"eur": {"cents": 1250}}});
+    let product = DynamicMessage::deserialize(desc, product).unwrap();
+
+    let options = OutputOptions::default();
+    assert_eq!(
+        options.to_json_value(&product).unwrap(),
+        json!({"title": "Lamp", "prices": {"eur": {"cents": "1250"}}})
+    );
+    let options = options.use_proto_names(true).stringify_64bit(false);
+    assert_eq!(
+        options.to_json_value(&product).unwrap(),
+        json!({"name": "Lamp", "prices": {"eur": {"cents": 1250}}})
+    );
+}
@@ -0,0 +1,58 @@
+use grpc_client::projection::Projection;
+use prost_reflect::{DescriptorPool, DynamicMessage, ReflectMessage, Value};
+
+fn error_info() -> DynamicMessage {
+    let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+    let mut info = DynamicMessage::new(pool.get_message_by_name("google.rpc.ErrorInfo").unwrap());
+    info.set_field_by_name("reason", Value::String("QUOTA".to_string()));
+    info.set_field_by_name("domain", Value::String("example.com".to_string()));
+    info
+}
+
+#[test]
+fn only_selected_fields_are_kept() {
+    let projection = Projection::new(["reason"]).unwrap();
+    let projected = projection.apply(&error_info());
+    assert_eq!(
+        serde_json::to_string(&projected).unwrap(),
+        r#"{"reason":"QUOTA"}"#
+    );
+}
+
+#[test]
+fn repeated_messages_are_projected_item_by_item() {
+    let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+    let violation_desc = pool
+        .get_message_by_name("google.rpc.BadRequest.FieldViolation")
+        .unwrap();
+    let mut violation = DynamicMessage::new(violation_desc);
+    violation.set_field_by_name("field", Value::String("name".to_string()));
+    violation.set_field_by_name("description", Value::String("too long".to_string()));
+    let mut bad_request =
+        DynamicMessage::new(pool.get_message_by_name("google.rpc.BadRequest").unwrap());
+    bad_request.set_field_by_name(
+        "field_violations",
+        Value::List(vec![Value::Message(violation)]),
+    );
+
+    // proto names work as well as json names
+    let projection = Projection::new(["field_violations[].field"]).unwrap();
+    projection.validate(&bad_request.descriptor()).unwrap();
+    let projected = projection.apply(&bad_request);
+    assert_eq!(
+        serde_json::to_string(&projected).unwrap(),
+        r#"{"fieldViolations":[{"field":"name"}]}"#
+    );
+}
+
+#[test]
+fn invalid_paths_are_rejected_by_validation() {
+    let desc = error_info().descriptor();
+    for path in ["reasons", "reason.inner", "reason[]", "metadata.key"] {
+        let projection = Projection::new([path]).unwrap();
+        assert!(
+            projection.validate(&desc).is_err(),
+            "{path} should be invalid"
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::random_message::MessageGenerator;
+use prost_reflect::DescriptorPool;
+
+#[test]
+fn messages_with_an_any_are_valid_json() {
+    // google.rpc.Status has repeated Any details, and no Empty to pack in its pool
+    let pool = DescriptorPool::decode(tonic_types::pb::FILE_DESCRIPTOR_SET).unwrap();
+    let status = pool.get_message_by_name("google.rpc.Status").unwrap();
+    for seed in 0..20 {
+        let message = MessageGenerator::new().seed(seed).message(&status);
+        let json = serde_json::to_value(&message).unwrap();
+        assert!(json.get("details").is_none(), "seed {seed}: {json}");
+    }
+
+    let pool = compile_protos(&[PathBuf::from("tests/proto/events.proto")], &[]).unwrap();
+    let event = pool.get_message_by_name("events.v1.Event").unwrap();
+    let mut packed = 0;
+    for seed in 0..20 {
+        let message = MessageGenerator::new().seed(seed).message(&event);
+        let json = serde_json::to_value(&message).unwrap();
+        if let Some(payload) = json.get("payload") {
+            assert_eq!(
+                payload["@type"],
+                "type.googleapis.com/google.protobuf.Empty"
+            );
+            packed += 1;
+        }
+    }
+    assert!(packed > 0);
+}
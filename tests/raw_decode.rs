@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use grpc_client::OutputOptions;
+use grpc_client::descriptor_source::compile_protos;
+use grpc_client::encoding::{MessageSyntax, parse_message};
+use grpc_client::output::OutputFormat;
+use grpc_client::raw_decode::{
+    RawDisplay, RawValue, WireType, decode_raw, decode_raw_message, is_raw_message, to_json,
+};
+use prost::Message;
+
+#[test]
+fn every_wire_type_is_decoded() {
+    let bytes = [
+        0x08, 0x01, // 1: varint 1
+        0x11, 1, 0, 0, 0, 0, 0, 0, 0, // 2: fixed64 1
+        0x1d, 0, 0, 0x80, 0x3f, // 3: fixed32 1.0f
+        0x22, 0x02, 0xff, 0xfe, // 4: bytes that are neither text nor a message
+        0x2b, 0x08, 0x02, 0x2c, // 5: group holding 1: varint 2
+    ];
+    let fields = decode_raw(&bytes).unwrap();
+    let wire_types: Vec<WireType> = fields.iter().map(|f| f.wire_type).collect();
+    assert_eq!(
+        wire_types,
+        [
+            WireType::Varint,
+            WireType::Fixed64,
+            WireType::Fixed32,
+            WireType::LengthDelimited,
+            WireType::StartGroup,
+        ]
+    );
+    assert_eq!(fields[2].value, RawValue::Fixed32(0x3f80_0000));
+    assert_eq!(fields[3].value, RawValue::Bytes(vec![0xff, 0xfe]));
+    assert!(
+        matches!(&fields[4].value, RawValue::Group(inner) if inner[0].value == RawValue::Varint(2))
+    );
+
+    let json = to_json(&fields);
+    assert_eq!(json[1]["double"], 5e-324);
+    assert_eq!(json[2]["float"], 1.0);
+    assert_eq!(json[3]["bytes"], "//4=");
+    assert_eq!(json[4]["group"][0]["zigzag"], 1);
+}
+
+#[test]
+fn negative_varints_have_every_interpretation() {
+    // -1 as an int64 takes ten bytes
+    let mut bytes = vec![0x08];
+    bytes.extend([0xff; 9]);
+    bytes.push(0x01);
+    let json = to_json(&decode_raw(&bytes).unwrap());
+    assert_eq!(json[0]["uint"], u64::MAX);
+    assert_eq!(json[0]["int"], -1);
+    assert_eq!(json[0]["zigzag"], i64::MIN);
+}
+
+#[test]
+fn invalid_bytes_are_rejected() {
+    assert!(decode_raw(&[0x0a, 0x05, b'a']).is_err(), "truncated length");
+    assert!(decode_raw(&[0x00, 0x01]).is_err(), "field number 0");
+    assert!(decode_raw(&[0x0f]).is_err(), "wire type 7");
+    assert!(
+        decode_raw(&[0x0b, 0x08, 0x01]).is_err(),
+        "group never ended"
+    );
+    assert!(decode_raw(&[0x0c]).is_err(), "end of a group never started");
+    assert_eq!(decode_raw(&[]).unwrap(), vec![]);
+}
+
+#[test]
+fn raw_messages_are_printed_by_the_formatters() {
+    let pool = compile_protos(&[PathBuf::from("examples/proto/helloworld.proto")], &[]).unwrap();
+    let desc = pool.get_message_by_name("helloworld.GoodbyReply").unwrap();
+    let message = parse_message(
+        desc,
+        r#"{"message": "bye", "detail": "now"}"#,
+        MessageSyntax::Json,
+    )
+    .unwrap();
+    let raw = decode_raw_message(&message.encode_to_vec()).unwrap();
+    assert!(is_raw_message(&raw));
+    assert_eq!(raw.encode_to_vec(), message.encode_to_vec());
+
+    let mut text = Vec::new();
+    OutputFormat::Text
+        .formatter(OutputOptions::default())
+        .write_message(&raw, &mut text)
+        .unwrap();
+    let fields = decode_raw(&message.encode_to_vec()).unwrap();
+    assert_eq!(
+        String::from_utf8(text).unwrap(),
+        RawDisplay(&fields).to_string()
+    );
+    assert_eq!(RawDisplay(&fields).to_string(), "1: \"bye\"\n2: \"now\"\n");
+
+    let json = OutputOptions::default()
+        .compact(true)
+        .to_json(&raw)
+        .unwrap();
+    assert_eq!(
+        json,
+        r#"[{"field":1,"wire_type":"len","string":"bye"},{"field":2,"wire_type":"len","string":"now"}]"#
+    );
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use grpc_client::client::GrpcClientError;
+use grpc_client::{CallOptions, Client, RetryPolicy};
+use tonic::Code;
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::default()
+        .max_attempts(3)
+        .initial_backoff(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn unary_calls_are_retried_with_the_attempt_count() {
+    let common::TestServer { url, requests, .. } = common::health_server(2).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().retry(policy());
+    let result = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap();
+    assert_eq!(result.attempts, 3);
+    assert_eq!(
+        result
+            .request_metadata
+            .get("grpc-previous-rpc-attempts")
+            .unwrap(),
+        "2"
+    );
+
+    let previous: Vec<_> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|headers| headers.get("grpc-previous-rpc-attempts").cloned())
+        .collect();
+    assert_eq!(
+        previous,
+        [None, Some("1".parse().unwrap()), Some("2".parse().unwrap())]
+    );
+}
+
+#[tokio::test]
+async fn calls_fail_once_the_attempts_are_spent() {
+    let common::TestServer { url, requests, .. } = common::health_server(5).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().retry(policy());
+    let error = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().unwrap().code(), Code::Unavailable);
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn streaming_calls_are_not_retried() {
+    let common::TestServer { url, requests, .. } = common::health_server(1).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().retry(policy());
+    let error = client
+        .request_with_options(HEALTH, "Watch", vec![], &options)
+        .await
+        .unwrap_err();
+    assert_eq!(error.status().unwrap().code(), Code::Unavailable);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn a_backoff_past_the_deadline_fails_right_away() {
+    let common::TestServer { url, requests, .. } = common::health_server(1).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new()
+        .timeout(Duration::from_millis(500))
+        .retry(policy().initial_backoff(Duration::from_secs(10)));
+    let started = Instant::now();
+    let error = client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, GrpcClientError::DeadlineExceeded(_)),
+        "{error:?}"
+    );
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn the_jitter_never_goes_past_the_max_backoff() {
+    let policy = policy().max_backoff(Duration::from_millis(100)).jitter(0.5);
+    for retry in 1..10 {
+        assert!(policy.backoff(retry) <= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use grpc_client::client::GrpcClientError;
+use grpc_client::status_details::decode_status_details;
+use prost::Message;
+use prost_reflect::prost_types::Any;
+use tonic::{Code, Status};
+use tonic_types::{ErrorDetails, StatusExt};
+
+#[test]
+fn status_without_details_is_not_decoded() {
+    let status = Status::unavailable("down");
+    assert!(decode_status_details(&status, None).is_none());
+    assert!(matches!(
+        GrpcClientError::from_status(status, None),
+        GrpcClientError::ReflectionRequestError(_)
+    ));
+}
+
+#[test]
+fn standard_details_are_rendered_as_json() {
+    let mut details = ErrorDetails::with_retry_info(Some(Duration::from_secs(3)));
+    details.set_error_info(
+        "QUOTA",
+        "example.com",
+        [("zone".to_string(), "eu".to_string())],
+    );
+    let status = Status::with_error_details(Code::ResourceExhausted, "slow down", details);
+
+    let error = GrpcClientError::from_status(status, None);
+    let GrpcClientError::StatusWithDetails(_, json) = &error else {
+        panic!("details should be decoded, got {error:?}");
+    };
+    assert_eq!(json["code"], Code::ResourceExhausted as i32);
+    assert_eq!(json["message"], "slow down");
+    let retry = &json["details"][0];
+    assert_eq!(retry["@type"], "type.googleapis.com/google.rpc.RetryInfo");
+    assert_eq!(retry["retryDelay"], "3s");
+    let info = &json["details"][1];
+    assert_eq!(info["reason"], "QUOTA");
+    assert_eq!(info["metadata"]["zone"], "eu");
+    assert!(error.to_string().contains("\"reason\": \"QUOTA\""));
+}
+
+#[test]
+fn unknown_details_keep_their_raw_value() {
+    let rpc_status = tonic_types::pb::Status {
+        code: Code::Internal as i32,
+        message: "boom".to_string(),
+        details: vec![Any {
+            type_url: "type.googleapis.com/my.company.Detail".to_string(),
+            value: vec![1, 2, 3],
+        }],
+    };
+    let status = Status::with_details(Code::Internal, "boom", rpc_status.encode_to_vec().into());
+    let json = decode_status_details(&status, None).unwrap();
+    assert_eq!(
+        json["details"][0]["@type"],
+        "type.googleapis.com/my.company.Detail"
+    );
+    assert_eq!(json["details"][0]["value"], "AQID");
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use grpc_client::client::GrpcClientError;
+use grpc_client::{CallOptions, Client};
+
+const HEALTH: &str = "grpc.health.v1.Health";
+
+#[tokio::test]
+async fn the_timeout_is_sent_as_grpc_timeout() {
+    let common::TestServer { url, requests, .. } = common::health_server(0).await;
+    let mut client = Client::new(url).await.unwrap();
+    let options = CallOptions::new().timeout(Duration::from_secs(5));
+    client
+        .request_with_options(HEALTH, "Check", vec![], &options)
+        .await
+        .unwrap();
+    client
+        .request_with_options(HEALTH, "Check", vec![], &CallOptions::default())
+        .await
+        .unwrap();
+    let requests = requests.lock().unwrap();
+    let timeout = requests[0].get("grpc-timeout").unwrap().to_str().unwrap();
+    // what is left of the 5 seconds, in milliseconds or a finer unit
+    assert!(timeout.ends_with(['m', 'u', 'n']), "{timeout}");
+    assert!(requests[1].get("grpc-timeout").is_none());
+}
+
+#[tokio::test]
+async fn calls_still_running_at_the_timeout_are_deadline_exceeded() {
+    let url = common::health_server(0).await.url;
+    let mut client = Client::new(url).await.unwrap();
+    let timeout = Duration::from_millis(200);
+    let options = CallOptions::new().timeout(timeout);
+    let started = Instant::now();
+    // Watch streams until the client stops it
+    let error = client
+        .request_with_options(HEALTH, "Watch", vec![], &options)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(error, GrpcClientError::DeadlineExceeded(t) if t == timeout),
+        "{error:?}"
+    );
+    assert_eq!(error.exit_code(), 68);
+    assert!(started.elapsed() < Duration::from_secs(2));
+}